@@ -0,0 +1,116 @@
+use crate::AvmError;
+
+use super::{Address, AvmData, AvmVersion};
+
+// consensus parameters as on MainNet that determine by how much
+// the minimum balance of an account grows with its holdings
+const APP_FLAT_PARAMS_MIN_BALANCE: u64 = 100_000;
+const APP_FLAT_OPT_IN_MIN_BALANCE: u64 = 100_000;
+const SCHEMA_MIN_BALANCE_PER_ENTRY: u64 = 25_000;
+const SCHEMA_UINT_MIN_BALANCE: u64 = 3_500;
+const SCHEMA_BYTES_MIN_BALANCE: u64 = 25_000;
+const BOX_FLAT_MIN_BALANCE: u64 = 2_500;
+const BOX_BYTE_MIN_BALANCE: u64 = 400;
+
+// the state of an account as exposed via acct_params_get
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AccountParams {
+    pub balance: u64,
+    pub auth_addr: Address,
+    // the schemas of all created apps and of all opted-in apps' local states
+    pub total_num_uint: u64,
+    pub total_num_byte_slice: u64,
+    pub total_extra_app_pages: u64,
+    pub total_apps_created: u64,
+    pub total_apps_opted_in: u64,
+    pub total_assets_created: u64,
+    // the number of assets the account holds or is opted in to
+    pub total_assets: u64,
+    pub total_boxes: u64,
+    // the names and contents of all boxes
+    pub total_box_bytes: u64,
+}
+
+impl AccountParams {
+    // base_min_balance is both the minimum balance of an empty
+    // account and the increment for every asset it holds
+    pub fn min_balance(&self, base_min_balance: u64) -> u64 {
+        let total_schema = self.total_num_uint + self.total_num_byte_slice;
+        base_min_balance
+            + base_min_balance * self.total_assets
+            + APP_FLAT_PARAMS_MIN_BALANCE * (self.total_apps_created + self.total_extra_app_pages)
+            + APP_FLAT_OPT_IN_MIN_BALANCE * self.total_apps_opted_in
+            + SCHEMA_MIN_BALANCE_PER_ENTRY * total_schema
+            + SCHEMA_UINT_MIN_BALANCE * self.total_num_uint
+            + SCHEMA_BYTES_MIN_BALANCE * self.total_num_byte_slice
+            + BOX_FLAT_MIN_BALANCE * self.total_boxes
+            + BOX_BYTE_MIN_BALANCE * self.total_box_bytes
+    }
+
+    pub fn field(&self, field: AcctParamsField, base_min_balance: u64) -> AvmData {
+        match field {
+            AcctParamsField::AcctBalance => self.balance.into(),
+            AcctParamsField::AcctMinBalance => self.min_balance(base_min_balance).into(),
+            AcctParamsField::AcctAuthAddr => self.auth_addr.to_vec().into(),
+            AcctParamsField::AcctTotalNumUint => self.total_num_uint.into(),
+            AcctParamsField::AcctTotalNumByteSlice => self.total_num_byte_slice.into(),
+            AcctParamsField::AcctTotalExtraAppPages => self.total_extra_app_pages.into(),
+            AcctParamsField::AcctTotalAppsCreated => self.total_apps_created.into(),
+            AcctParamsField::AcctTotalAppsOptedIn => self.total_apps_opted_in.into(),
+            AcctParamsField::AcctTotalAssetsCreated => self.total_assets_created.into(),
+            AcctParamsField::AcctTotalAssets => self.total_assets.into(),
+            AcctParamsField::AcctTotalBoxes => self.total_boxes.into(),
+            AcctParamsField::AcctTotalBoxBytes => self.total_box_bytes.into(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AcctParamsField {
+    AcctBalance = 0,
+    AcctMinBalance = 1,
+    AcctAuthAddr = 2,
+    AcctTotalNumUint = 3,
+    AcctTotalNumByteSlice = 4,
+    AcctTotalExtraAppPages = 5,
+    AcctTotalAppsCreated = 6,
+    AcctTotalAppsOptedIn = 7,
+    AcctTotalAssetsCreated = 8,
+    AcctTotalAssets = 9,
+    AcctTotalBoxes = 10,
+    AcctTotalBoxBytes = 11,
+}
+
+// all fields in the order of their numbering
+const ACCT_PARAMS_FIELDS: [AcctParamsField; 12] = [
+    AcctParamsField::AcctBalance,
+    AcctParamsField::AcctMinBalance,
+    AcctParamsField::AcctAuthAddr,
+    AcctParamsField::AcctTotalNumUint,
+    AcctParamsField::AcctTotalNumByteSlice,
+    AcctParamsField::AcctTotalExtraAppPages,
+    AcctParamsField::AcctTotalAppsCreated,
+    AcctParamsField::AcctTotalAppsOptedIn,
+    AcctParamsField::AcctTotalAssetsCreated,
+    AcctParamsField::AcctTotalAssets,
+    AcctParamsField::AcctTotalBoxes,
+    AcctParamsField::AcctTotalBoxBytes,
+];
+
+impl AcctParamsField {
+    pub fn parse(value: u8, version: &AvmVersion) -> Result<Self, AvmError> {
+        match ACCT_PARAMS_FIELDS.get(value as usize) {
+            Some(field) if *version >= field.version() => Ok(*field),
+            _ => Err(AvmError::UnknownField("acct_params", value)),
+        }
+    }
+
+    pub fn version(self) -> AvmVersion {
+        match self {
+            AcctParamsField::AcctBalance
+            | AcctParamsField::AcctMinBalance
+            | AcctParamsField::AcctAuthAddr => AvmVersion::V6,
+            _ => AvmVersion::V8,
+        }
+    }
+}
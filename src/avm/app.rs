@@ -0,0 +1,65 @@
+use crate::AvmError;
+
+use super::{transaction, Address, AvmData, StateSchema};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AppParams {
+    pub approval_program: Vec<u8>,
+    pub clear_state_program: Vec<u8>,
+    pub global_schema: StateSchema,
+    pub local_schema: StateSchema,
+    pub extra_program_pages: u64,
+    pub creator: Address,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AppParamsField {
+    AppApprovalProgram = 0,
+    AppClearStateProgram = 1,
+    AppGlobalNumUint = 2,
+    AppGlobalNumByteSlice = 3,
+    AppLocalNumUint = 4,
+    AppLocalNumByteSlice = 5,
+    AppExtraProgramPages = 6,
+    AppCreator = 7,
+    AppAddress = 8,
+}
+
+// all fields in the order of their numbering
+const APP_PARAMS_FIELDS: [AppParamsField; 9] = [
+    AppParamsField::AppApprovalProgram,
+    AppParamsField::AppClearStateProgram,
+    AppParamsField::AppGlobalNumUint,
+    AppParamsField::AppGlobalNumByteSlice,
+    AppParamsField::AppLocalNumUint,
+    AppParamsField::AppLocalNumByteSlice,
+    AppParamsField::AppExtraProgramPages,
+    AppParamsField::AppCreator,
+    AppParamsField::AppAddress,
+];
+
+impl AppParamsField {
+    // all fields are available since v5, the version that introduced app_params_get
+    pub fn parse(value: u8) -> Result<Self, AvmError> {
+        APP_PARAMS_FIELDS
+            .get(value as usize)
+            .copied()
+            .ok_or(AvmError::UnknownField("app_params", value))
+    }
+}
+
+impl AppParams {
+    pub fn field(&self, field: AppParamsField, app_id: u64) -> AvmData {
+        match field {
+            AppParamsField::AppApprovalProgram => self.approval_program.clone().into(),
+            AppParamsField::AppClearStateProgram => self.clear_state_program.clone().into(),
+            AppParamsField::AppGlobalNumUint => self.global_schema.num_uint.into(),
+            AppParamsField::AppGlobalNumByteSlice => self.global_schema.num_byte_slice.into(),
+            AppParamsField::AppLocalNumUint => self.local_schema.num_uint.into(),
+            AppParamsField::AppLocalNumByteSlice => self.local_schema.num_byte_slice.into(),
+            AppParamsField::AppExtraProgramPages => self.extra_program_pages.into(),
+            AppParamsField::AppCreator => self.creator.to_vec().into(),
+            AppParamsField::AppAddress => transaction::application_address(app_id).to_vec().into(),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::AvmError;
+
+use super::{Address, AvmData, AvmVersion};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct AssetHolding {
+    pub amount: u64,
+    pub frozen: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AssetParams {
+    pub total: u64,
+    pub decimals: u64,
+    pub default_frozen: bool,
+    pub unit_name: Vec<u8>,
+    pub name: Vec<u8>,
+    pub url: Vec<u8>,
+    pub metadata_hash: Vec<u8>,
+    pub manager: Address,
+    pub reserve: Address,
+    pub freeze: Address,
+    pub clawback: Address,
+    pub creator: Address,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AssetHoldingField {
+    AssetBalance = 0,
+    AssetFrozen = 1,
+}
+
+// all fields in the order of their numbering
+const ASSET_HOLDING_FIELDS: [AssetHoldingField; 2] = [
+    AssetHoldingField::AssetBalance,
+    AssetHoldingField::AssetFrozen,
+];
+
+impl AssetHoldingField {
+    // all fields are available since v2, the version that introduced assets
+    pub fn parse(value: u8) -> Result<Self, AvmError> {
+        ASSET_HOLDING_FIELDS
+            .get(value as usize)
+            .copied()
+            .ok_or(AvmError::UnknownField("asset_holding", value))
+    }
+}
+
+impl AssetHolding {
+    pub fn field(&self, field: AssetHoldingField) -> AvmData {
+        match field {
+            AssetHoldingField::AssetBalance => self.amount.into(),
+            AssetHoldingField::AssetFrozen => self.frozen.into(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AssetParamsField {
+    AssetTotal = 0,
+    AssetDecimals = 1,
+    AssetDefaultFrozen = 2,
+    AssetUnitName = 3,
+    AssetName = 4,
+    AssetURL = 5,
+    AssetMetadataHash = 6,
+    AssetManager = 7,
+    AssetReserve = 8,
+    AssetFreeze = 9,
+    AssetClawback = 10,
+    AssetCreator = 11,
+}
+
+// all fields in the order of their numbering
+const ASSET_PARAMS_FIELDS: [AssetParamsField; 12] = [
+    AssetParamsField::AssetTotal,
+    AssetParamsField::AssetDecimals,
+    AssetParamsField::AssetDefaultFrozen,
+    AssetParamsField::AssetUnitName,
+    AssetParamsField::AssetName,
+    AssetParamsField::AssetURL,
+    AssetParamsField::AssetMetadataHash,
+    AssetParamsField::AssetManager,
+    AssetParamsField::AssetReserve,
+    AssetParamsField::AssetFreeze,
+    AssetParamsField::AssetClawback,
+    AssetParamsField::AssetCreator,
+];
+
+impl AssetParamsField {
+    pub fn parse(value: u8, version: &AvmVersion) -> Result<Self, AvmError> {
+        match ASSET_PARAMS_FIELDS.get(value as usize) {
+            Some(field) if *version >= field.version() => Ok(*field),
+            _ => Err(AvmError::UnknownField("asset_params", value)),
+        }
+    }
+
+    pub fn version(self) -> AvmVersion {
+        match self {
+            AssetParamsField::AssetCreator => AvmVersion::V5,
+            _ => AvmVersion::V2,
+        }
+    }
+}
+
+impl AssetParams {
+    pub fn field(&self, field: AssetParamsField) -> AvmData {
+        match field {
+            AssetParamsField::AssetTotal => self.total.into(),
+            AssetParamsField::AssetDecimals => self.decimals.into(),
+            AssetParamsField::AssetDefaultFrozen => self.default_frozen.into(),
+            AssetParamsField::AssetUnitName => self.unit_name.clone().into(),
+            AssetParamsField::AssetName => self.name.clone().into(),
+            AssetParamsField::AssetURL => self.url.clone().into(),
+            AssetParamsField::AssetMetadataHash => self.metadata_hash.clone().into(),
+            AssetParamsField::AssetManager => self.manager.to_vec().into(),
+            AssetParamsField::AssetReserve => self.reserve.to_vec().into(),
+            AssetParamsField::AssetFreeze => self.freeze.to_vec().into(),
+            AssetParamsField::AssetClawback => self.clawback.to_vec().into(),
+            AssetParamsField::AssetCreator => self.creator.to_vec().into(),
+        }
+    }
+}
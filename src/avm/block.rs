@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::AvmError;
+
+use super::AvmData;
+
+// the fields of a past block as exposed via the block opcode
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct BlockHeader {
+    pub seed: [u8; 32],
+    pub timestamp: u64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockField {
+    BlkSeed = 0,
+    BlkTimestamp = 1,
+}
+
+// all fields in the order of their numbering
+const BLOCK_FIELDS: [BlockField; 2] = [BlockField::BlkSeed, BlockField::BlkTimestamp];
+
+impl BlockField {
+    // all fields are available since v7, the version that introduced block
+    pub fn parse(value: u8) -> Result<Self, AvmError> {
+        BLOCK_FIELDS
+            .get(value as usize)
+            .copied()
+            .ok_or(AvmError::UnknownField("block", value))
+    }
+}
+
+impl BlockHeader {
+    pub fn field(&self, field: BlockField) -> AvmData {
+        match field {
+            BlockField::BlkSeed => self.seed.to_vec().into(),
+            BlockField::BlkTimestamp => self.timestamp.into(),
+        }
+    }
+}
+
+// the headers of past blocks that programs read through the block opcode.
+// Implement this trait to evaluate programs against a node's history
+// or to supply fixed seeds in tests.
+pub trait BlockHistory: Debug {
+    // None if the block is unknown
+    fn block_header(&self, round: u64) -> Option<BlockHeader>;
+}
+
+// a block history that keeps the headers in memory, which
+// is the default block history of an Avm
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryBlockHistory {
+    pub headers: HashMap<u64, BlockHeader>,
+}
+
+impl BlockHistory for MemoryBlockHistory {
+    fn block_header(&self, round: u64) -> Option<BlockHeader> {
+        self.headers.get(&round).copied()
+    }
+}
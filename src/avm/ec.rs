@@ -0,0 +1,395 @@
+use ark_bls12_381::Bls12_381;
+use ark_bn254::{Bn254, Config as Bn254Config};
+use ark_ec::bn::BnConfig;
+use ark_ec::hashing::curve_maps::swu::parity;
+use ark_ec::hashing::curve_maps::wb::{WBConfig, WBMap};
+use ark_ec::hashing::map_to_curve_hasher::MapToCurve;
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, Projective, SWCurveConfig};
+use ark_ec::{AffineRepr, CurveGroup, Group};
+use ark_ff::{
+    BigInt, BigInteger, Field, Fp, Fp2, Fp2Config, MontBackend, MontConfig, One, PrimeField, Zero,
+};
+
+use crate::AvmError;
+
+// scalars are big-endian integers of at most 32 bytes
+pub const MAX_SCALAR_LEN: usize = 32;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EcGroup {
+    BN254g1 = 0,
+    BN254g2 = 1,
+    BLS12_381g1 = 2,
+    BLS12_381g2 = 3,
+}
+
+// all groups in the order of their numbering
+const EC_GROUPS: [EcGroup; 4] = [
+    EcGroup::BN254g1,
+    EcGroup::BN254g2,
+    EcGroup::BLS12_381g1,
+    EcGroup::BLS12_381g2,
+];
+
+impl EcGroup {
+    pub fn parse(value: u8) -> Result<Self, AvmError> {
+        EC_GROUPS
+            .get(value as usize)
+            .copied()
+            .ok_or(AvmError::UnknownField("EC", value))
+    }
+
+    // the length of an encoded point
+    pub fn point_len(self) -> usize {
+        match self {
+            EcGroup::BN254g1 => 64,
+            EcGroup::BN254g2 => 128,
+            EcGroup::BLS12_381g1 => 96,
+            EcGroup::BLS12_381g2 => 192,
+        }
+    }
+}
+
+// calls a function that is generic over the curve of the group
+macro_rules! with_curve {
+    ($group:expr, $f:ident($($arg:expr),*)) => {
+        match $group {
+            EcGroup::BN254g1 => $f::<ark_bn254::g1::Config>($group, $($arg),*),
+            EcGroup::BN254g2 => $f::<ark_bn254::g2::Config>($group, $($arg),*),
+            EcGroup::BLS12_381g1 => $f::<ark_bls12_381::g1::Config>($group, $($arg),*),
+            EcGroup::BLS12_381g2 => $f::<ark_bls12_381::g2::Config>($group, $($arg),*),
+        }
+    };
+}
+
+pub fn add(group: EcGroup, a: &[u8], b: &[u8]) -> Result<Vec<u8>, AvmError> {
+    with_curve!(group, add_points(a, b))
+}
+
+// multiplies by the full integer, i.e., the scalar is not reduced
+// modulo the order of the group
+pub fn scalar_mul(group: EcGroup, point: &[u8], scalar: &[u8]) -> Result<Vec<u8>, AvmError> {
+    if scalar.len() > MAX_SCALAR_LEN {
+        return Err(AvmError::EcScalarTooLong(scalar.len()));
+    }
+    with_curve!(group, mul_point(point, scalar))
+}
+
+// whether the product of the pairings of the points in a with the
+// respective points in b is the identity of the target group
+pub fn pairing_check(group: EcGroup, a: &[u8], b: &[u8]) -> Result<bool, AvmError> {
+    let (g1, g2) = match group {
+        EcGroup::BN254g1 | EcGroup::BLS12_381g1 => (a, b),
+        EcGroup::BN254g2 | EcGroup::BLS12_381g2 => (b, a),
+    };
+    match group {
+        EcGroup::BN254g1 | EcGroup::BN254g2 => pair::<Bn254>(
+            decode_subgroup_points(EcGroup::BN254g1, g1)?,
+            decode_subgroup_points(EcGroup::BN254g2, g2)?,
+        ),
+        EcGroup::BLS12_381g1 | EcGroup::BLS12_381g2 => pair::<Bls12_381>(
+            decode_subgroup_points(EcGroup::BLS12_381g1, g1)?,
+            decode_subgroup_points(EcGroup::BLS12_381g2, g2)?,
+        ),
+    }
+}
+
+// every point is multiplied by its own scalar of exactly 32 bytes
+pub fn multi_scalar_mul(
+    group: EcGroup,
+    points: &[u8],
+    scalars: &[u8],
+) -> Result<Vec<u8>, AvmError> {
+    with_curve!(group, mul_points(points, scalars))
+}
+
+pub fn subgroup_check(group: EcGroup, point: &[u8]) -> Result<bool, AvmError> {
+    with_curve!(group, in_subgroup(point))
+}
+
+// maps a field element to a point of the subgroup. G1 elements may
+// be shorter than the field size, while the two elements of G2 must
+// be padded.
+pub fn map_to(group: EcGroup, element: &[u8]) -> Result<Vec<u8>, AvmError> {
+    Ok(match group {
+        EcGroup::BN254g1 => encode_point(map_svdw::<ark_bn254::g1::Config>(decode_element(
+            group, element, true,
+        )?)),
+        EcGroup::BN254g2 => encode_point(clear_bn254_g2_cofactor(map_svdw(decode_element(
+            group, element, false,
+        )?))),
+        EcGroup::BLS12_381g1 => encode_point(map_wb::<ark_bls12_381::g1::Config>(decode_element(
+            group, element, true,
+        )?)),
+        EcGroup::BLS12_381g2 => encode_point(map_wb::<ark_bls12_381::g2::Config>(decode_element(
+            group, element, false,
+        )?)),
+    })
+}
+
+// field elements are big-endian and padded to their full size, and
+// elements of quadratic extensions are encoded as c0 followed by c1
+trait Element: Field {
+    const SIZE: usize;
+
+    // the bytes must have the full size of the element
+    fn decode(bytes: &[u8]) -> Option<Self>;
+
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+impl<P: MontConfig<N>, const N: usize> Element for Fp<MontBackend<P, N>, N> {
+    const SIZE: usize = N * 8;
+
+    // values of at least the modulus are rejected
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let bits: Vec<bool> = bytes
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+            .collect();
+        Self::from_bigint(BigInt::from_bits_be(&bits))
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend(self.into_bigint().to_bytes_be());
+    }
+}
+
+impl<P: Fp2Config> Element for Fp2<P>
+where
+    P::Fp: Element,
+{
+    const SIZE: usize = 2 * P::Fp::SIZE;
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (c0, c1) = bytes.split_at(P::Fp::SIZE);
+        Some(Fp2::new(P::Fp::decode(c0)?, P::Fp::decode(c1)?))
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.c0.encode(out);
+        self.c1.encode(out);
+    }
+}
+
+fn decode_element<F: Element>(group: EcGroup, bytes: &[u8], padded: bool) -> Result<F, AvmError> {
+    let mut element = vec![
+        0;
+        if padded {
+            F::SIZE.saturating_sub(bytes.len())
+        } else {
+            0
+        }
+    ];
+    element.extend(bytes);
+    if element.len() != F::SIZE {
+        return Err(AvmError::InvalidEcFieldElement(group));
+    }
+    F::decode(&element).ok_or(AvmError::InvalidEcFieldElement(group))
+}
+
+// points are encoded as their coordinates X and Y, and the point
+// at infinity is encoded as all zeros. Points must be on the curve
+// but not necessarily in the subgroup.
+fn decode_point<P: SWCurveConfig>(group: EcGroup, bytes: &[u8]) -> Result<Affine<P>, AvmError>
+where
+    P::BaseField: Element,
+{
+    let size = P::BaseField::SIZE;
+    if bytes.len() != 2 * size {
+        return Err(AvmError::InvalidEcPoint(group));
+    }
+    let (x, y) = match (
+        P::BaseField::decode(&bytes[..size]),
+        P::BaseField::decode(&bytes[size..]),
+    ) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Err(AvmError::InvalidEcPoint(group)),
+    };
+    if x.is_zero() && y.is_zero() {
+        return Ok(Affine::identity());
+    }
+    let point = Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(AvmError::InvalidEcPoint(group));
+    }
+    Ok(point)
+}
+
+fn decode_points<P: SWCurveConfig>(group: EcGroup, bytes: &[u8]) -> Result<Vec<Affine<P>>, AvmError>
+where
+    P::BaseField: Element,
+{
+    if !bytes.len().is_multiple_of(group.point_len()) {
+        return Err(AvmError::InvalidEcPoint(group));
+    }
+    bytes
+        .chunks(group.point_len())
+        .map(|point| decode_point(group, point))
+        .collect()
+}
+
+fn encode_point<P: SWCurveConfig>(point: impl Into<Affine<P>>) -> Vec<u8>
+where
+    P::BaseField: Element,
+{
+    let mut out = Vec::with_capacity(2 * P::BaseField::SIZE);
+    match point.into().xy() {
+        Some((x, y)) => {
+            x.encode(&mut out);
+            y.encode(&mut out);
+        }
+        None => out.resize(2 * P::BaseField::SIZE, 0),
+    }
+    out
+}
+
+// the little-endian limbs of a big-endian scalar
+fn scalar_limbs(scalar: &[u8]) -> [u64; 4] {
+    let mut limbs = [0; 4];
+    for (i, byte) in scalar.iter().rev().enumerate() {
+        limbs[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    limbs
+}
+
+fn add_points<P: SWCurveConfig>(group: EcGroup, a: &[u8], b: &[u8]) -> Result<Vec<u8>, AvmError>
+where
+    P::BaseField: Element,
+{
+    let sum = decode_point::<P>(group, a)?.into_group() + decode_point::<P>(group, b)?;
+    Ok(encode_point(sum))
+}
+
+fn mul_point<P: SWCurveConfig>(
+    group: EcGroup,
+    point: &[u8],
+    scalar: &[u8],
+) -> Result<Vec<u8>, AvmError>
+where
+    P::BaseField: Element,
+{
+    let point = decode_point::<P>(group, point)?;
+    Ok(encode_point(point.mul_bigint(scalar_limbs(scalar))))
+}
+
+fn mul_points<P: SWCurveConfig>(
+    group: EcGroup,
+    points: &[u8],
+    scalars: &[u8],
+) -> Result<Vec<u8>, AvmError>
+where
+    P::BaseField: Element,
+{
+    let points = decode_points::<P>(group, points)?;
+    if scalars.len() != points.len() * MAX_SCALAR_LEN {
+        return Err(AvmError::InvalidEcScalars(
+            scalars.len(),
+            points.len() * MAX_SCALAR_LEN,
+        ));
+    }
+    let sum: Projective<P> = points
+        .iter()
+        .zip(scalars.chunks(MAX_SCALAR_LEN))
+        .map(|(point, scalar)| point.mul_bigint(scalar_limbs(scalar)))
+        .sum();
+    Ok(encode_point(sum))
+}
+
+fn in_subgroup<P: SWCurveConfig>(group: EcGroup, point: &[u8]) -> Result<bool, AvmError>
+where
+    P::BaseField: Element,
+{
+    Ok(decode_point::<P>(group, point)?.is_in_correct_subgroup_assuming_on_curve())
+}
+
+// pairings are only defined for points of the subgroups
+fn decode_subgroup_points<P: SWCurveConfig>(
+    group: EcGroup,
+    bytes: &[u8],
+) -> Result<Vec<Affine<P>>, AvmError>
+where
+    P::BaseField: Element,
+{
+    let points = decode_points::<P>(group, bytes)?;
+    if points
+        .iter()
+        .any(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+    {
+        return Err(AvmError::EcPointNotInSubgroup(group));
+    }
+    Ok(points)
+}
+
+fn pair<E: Pairing>(g1: Vec<E::G1Affine>, g2: Vec<E::G2Affine>) -> Result<bool, AvmError> {
+    if g1.is_empty() || g1.len() != g2.len() {
+        return Err(AvmError::EcPointCountMismatch(g1.len(), g2.len()));
+    }
+    Ok(E::multi_pairing(g1, g2).0.is_one())
+}
+
+// the Shallue-van de Woestijne map of RFC 9380 (section 6.6.1) with
+// Z = 1, which is the Z of the RFC's search for both groups of BN254
+fn map_svdw<P: SWCurveConfig>(u: P::BaseField) -> Affine<P> {
+    let g = |x: P::BaseField| (x.square() + P::COEFF_A) * x + P::COEFF_B;
+    let one = P::BaseField::one();
+    let z = one;
+    let g_z = g(z);
+    let h_z = z.square() * P::BaseField::from(3u64) + P::COEFF_A * P::BaseField::from(4u64);
+    let c2 = -z / P::BaseField::from(2u64);
+    let mut c3 = (-g_z * h_z).sqrt().expect("Z is suitable for the curve");
+    if parity(&c3) {
+        c3 = -c3;
+    }
+    let c4 = -g_z * P::BaseField::from(4u64) / h_z;
+
+    let tv1 = u.square() * g_z;
+    let tv2 = one + tv1;
+    let tv1 = one - tv1;
+    let tv3 = (tv1 * tv2).inverse().unwrap_or_default();
+    let tv4 = u * tv1 * tv3 * c3;
+    let x1 = c2 - tv4;
+    let x2 = c2 + tv4;
+    let x3 = (tv2.square() * tv3).square() * c4 + z;
+    // g(x3) is always a square
+    let (x, mut y) = [x1, x2, x3]
+        .into_iter()
+        .find_map(|x| g(x).sqrt().map(|y| (x, y)))
+        .expect("one of the candidates is on the curve");
+    if parity(&y) != parity(&u) {
+        y = -y;
+    }
+    Affine::new_unchecked(x, y)
+}
+
+// the cofactor clearing of gnark-crypto for BN254 G2, which computes
+// [x]Q + psi([3x]Q) + psi^2([x]Q) + psi^3(Q) for the curve parameter x
+// (https://eprint.iacr.org/2008/530)
+fn clear_bn254_g2_cofactor(point: Affine<ark_bn254::g2::Config>) -> Affine<ark_bn254::g2::Config> {
+    let psi = |point: Projective<ark_bn254::g2::Config>| -> Projective<ark_bn254::g2::Config> {
+        match point.into_affine().xy() {
+            Some((x, y)) => Affine::new_unchecked(
+                x.frobenius_map(1) * Bn254Config::TWIST_MUL_BY_Q_X,
+                y.frobenius_map(1) * Bn254Config::TWIST_MUL_BY_Q_Y,
+            )
+            .into_group(),
+            None => Projective::zero(),
+        }
+    };
+    let x_point = point.mul_bigint(Bn254Config::X);
+    let cleared = x_point
+        + psi(x_point + x_point.double())
+        + psi(psi(x_point))
+        + psi(psi(psi(point.into_group())));
+    cleared.into_affine()
+}
+
+// the simplified SWU map to an isogenous curve followed by the
+// isogeny, with the cofactor cleared as in RFC 9380
+fn map_wb<P: WBConfig>(u: P::BaseField) -> Affine<P> {
+    WBMap::<P>::new()
+        .and_then(|map| map.map_to_curve(u))
+        .expect("the map is defined for every field element")
+        .clear_cofactor()
+}
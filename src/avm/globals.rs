@@ -0,0 +1,169 @@
+use crate::AvmError;
+
+use super::{transaction, Address, AvmData, AvmVersion, RunMode};
+
+// the environment a program is evaluated in, i.e., the values
+// that are exposed to programs via the global opcode
+#[derive(Debug, PartialEq, Clone)]
+pub struct Globals {
+    pub min_txn_fee: u64,
+    pub min_balance: u64,
+    pub max_txn_life: u64,
+    pub round: u64,
+    pub latest_timestamp: u64,
+    pub creator_address: Address,
+    pub group_id: [u8; 32],
+    // 0 unless the program is evaluated as part of an inner transaction
+    pub caller_application_id: u64,
+    pub asset_create_min_balance: u64,
+    pub asset_opt_in_min_balance: u64,
+    pub genesis_hash: [u8; 32],
+}
+
+impl Default for Globals {
+    // consensus parameters as on MainNet
+    fn default() -> Self {
+        Globals {
+            min_txn_fee: 1000,
+            min_balance: 100_000,
+            max_txn_life: 1000,
+            round: 0,
+            latest_timestamp: 0,
+            creator_address: [0; 32],
+            group_id: [0; 32],
+            caller_application_id: 0,
+            asset_create_min_balance: 100_000,
+            asset_opt_in_min_balance: 100_000,
+            genesis_hash: [0; 32],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GlobalField {
+    MinTxnFee = 0,
+    MinBalance = 1,
+    MaxTxnLife = 2,
+    ZeroAddress = 3,
+    GroupSize = 4,
+    LogicSigVersion = 5,
+    Round = 6,
+    LatestTimestamp = 7,
+    CurrentApplicationID = 8,
+    CreatorAddress = 9,
+    CurrentApplicationAddress = 10,
+    GroupID = 11,
+    OpcodeBudget = 12,
+    CallerApplicationID = 13,
+    CallerApplicationAddress = 14,
+    AssetCreateMinBalance = 15,
+    AssetOptInMinBalance = 16,
+    GenesisHash = 17,
+}
+
+// all fields in the order of their numbering
+const GLOBAL_FIELDS: [GlobalField; 18] = [
+    GlobalField::MinTxnFee,
+    GlobalField::MinBalance,
+    GlobalField::MaxTxnLife,
+    GlobalField::ZeroAddress,
+    GlobalField::GroupSize,
+    GlobalField::LogicSigVersion,
+    GlobalField::Round,
+    GlobalField::LatestTimestamp,
+    GlobalField::CurrentApplicationID,
+    GlobalField::CreatorAddress,
+    GlobalField::CurrentApplicationAddress,
+    GlobalField::GroupID,
+    GlobalField::OpcodeBudget,
+    GlobalField::CallerApplicationID,
+    GlobalField::CallerApplicationAddress,
+    GlobalField::AssetCreateMinBalance,
+    GlobalField::AssetOptInMinBalance,
+    GlobalField::GenesisHash,
+];
+
+impl GlobalField {
+    pub fn parse(value: u8, version: &AvmVersion) -> Result<Self, AvmError> {
+        match GLOBAL_FIELDS.get(value as usize) {
+            Some(field) if *version >= field.version() => Ok(*field),
+            _ => Err(AvmError::UnknownField("global", value)),
+        }
+    }
+
+    pub fn version(self) -> AvmVersion {
+        match self {
+            GlobalField::MinTxnFee
+            | GlobalField::MinBalance
+            | GlobalField::MaxTxnLife
+            | GlobalField::ZeroAddress
+            | GlobalField::GroupSize => AvmVersion::V1,
+            GlobalField::LogicSigVersion
+            | GlobalField::Round
+            | GlobalField::LatestTimestamp
+            | GlobalField::CurrentApplicationID => AvmVersion::V2,
+            GlobalField::CreatorAddress => AvmVersion::V3,
+            GlobalField::CurrentApplicationAddress | GlobalField::GroupID => AvmVersion::V5,
+            GlobalField::OpcodeBudget
+            | GlobalField::CallerApplicationID
+            | GlobalField::CallerApplicationAddress => AvmVersion::V6,
+            GlobalField::AssetCreateMinBalance
+            | GlobalField::AssetOptInMinBalance
+            | GlobalField::GenesisHash => AvmVersion::V10,
+        }
+    }
+
+    // fields that only make sense for applications
+    pub fn mode(self) -> Option<RunMode> {
+        match self {
+            GlobalField::Round
+            | GlobalField::LatestTimestamp
+            | GlobalField::CurrentApplicationID
+            | GlobalField::CreatorAddress
+            | GlobalField::CurrentApplicationAddress
+            | GlobalField::CallerApplicationID
+            | GlobalField::CallerApplicationAddress => Some(RunMode::Application),
+            _ => None,
+        }
+    }
+}
+
+impl Globals {
+    // values that depend on the state of the evaluation are passed in explicitly
+    pub fn field(
+        &self,
+        field: GlobalField,
+        group_size: usize,
+        application_id: u64,
+        opcode_budget: u64,
+    ) -> AvmData {
+        match field {
+            GlobalField::MinTxnFee => self.min_txn_fee.into(),
+            GlobalField::MinBalance => self.min_balance.into(),
+            GlobalField::MaxTxnLife => self.max_txn_life.into(),
+            GlobalField::ZeroAddress => vec![0; 32].into(),
+            GlobalField::GroupSize => (group_size as u64).into(),
+            // the highest supported version
+            GlobalField::LogicSigVersion => 10.into(),
+            GlobalField::Round => self.round.into(),
+            GlobalField::LatestTimestamp => self.latest_timestamp.into(),
+            GlobalField::CurrentApplicationID => application_id.into(),
+            GlobalField::CreatorAddress => self.creator_address.to_vec().into(),
+            GlobalField::CurrentApplicationAddress => {
+                transaction::application_address(application_id)
+                    .to_vec()
+                    .into()
+            }
+            GlobalField::GroupID => self.group_id.to_vec().into(),
+            GlobalField::OpcodeBudget => opcode_budget.into(),
+            GlobalField::CallerApplicationID => self.caller_application_id.into(),
+            GlobalField::CallerApplicationAddress => match self.caller_application_id {
+                0 => vec![0; 32].into(),
+                caller => transaction::application_address(caller).to_vec().into(),
+            },
+            GlobalField::AssetCreateMinBalance => self.asset_create_min_balance.into(),
+            GlobalField::AssetOptInMinBalance => self.asset_opt_in_min_balance.into(),
+            GlobalField::GenesisHash => self.genesis_hash.to_vec().into(),
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use crate::AvmError;
+
+use super::{
+    application_address, approved, atomically, execute_program, Address, AppParams, AssetHolding,
+    AssetParams, Avm, Globals, Ledger, OnCompletion, StateSchema, Transaction, TxType, TxnField,
+    APP_CALL_BUDGET, MAX_APP_CALL_DEPTH, MAX_INNER_TXNS, MAX_TXN_GROUP_SIZE,
+};
+
+const ZERO_ADDRESS: Address = [0; 32];
+
+// starts a new inner transaction at the end of the pending group. It is
+// sent by the current application by default and, as far as the fee
+// credit allows, its fee is covered by fees that were paid beyond the minimum.
+pub fn begin(avm: &mut Avm) -> Result<(), AvmError> {
+    if avm.pending_inner_txns.len() >= MAX_TXN_GROUP_SIZE {
+        return Err(AvmError::TxnGroupTooLarge(avm.pending_inner_txns.len() + 1));
+    }
+    let min_fee = avm.globals.min_txn_fee;
+    let available = fee_credit(avm)
+        .saturating_add(paid_fees(&avm.pending_inner_txns))
+        .saturating_sub(min_fee * avm.pending_inner_txns.len() as u64);
+    let txn = avm.txn();
+    let inner_txn = Transaction {
+        sender: application_address(txn.current_application_id()),
+        fee: min_fee.saturating_sub(available),
+        first_valid: txn.first_valid,
+        last_valid: txn.last_valid,
+        ..Default::default()
+    };
+    avm.pending_inner_txns.push(inner_txn);
+    Ok(())
+}
+
+// evaluates the pending group of inner transactions, applying their effects
+// to the ledger, and returns the group with the effects recorded on it
+pub fn submit(avm: &mut Avm) -> Result<Vec<Transaction>, AvmError> {
+    if avm.pending_inner_txns.is_empty() {
+        return Err(AvmError::InnerTxnNotStarted);
+    }
+    let mut group = std::mem::take(&mut avm.pending_inner_txns);
+    if avm.inner_txn_count + group.len() > MAX_INNER_TXNS {
+        return Err(AvmError::TooManyInnerTxns(MAX_INNER_TXNS));
+    }
+    // the group pays the minimum fee for each of its transactions,
+    // and whatever it pays beyond that adds to the fee credit
+    let required = avm.globals.min_txn_fee * group.len() as u64;
+    let available = fee_credit(avm).saturating_add(paid_fees(&group));
+    if available < required {
+        return Err(AvmError::InnerFeesTooLow(available, required));
+    }
+    let (fee_credit, inner_txn_count) = (avm.fee_credit, avm.inner_txn_count);
+    avm.fee_credit = Some(available - required);
+    avm.inner_txn_count += group.len();
+    // the group is applied as a whole, so a failing transaction
+    // also undoes the changes of those before it
+    let ledger = avm.ledger.clone();
+    let result = atomically(&ledger, || {
+        (0..group.len()).try_for_each(|idx| evaluate(avm, &mut group, idx))
+    });
+    if result.is_err() {
+        avm.fee_credit = fee_credit;
+        avm.inner_txn_count = inner_txn_count;
+    }
+    result.map(|_| group)
+}
+
+// computed from the top-level group the first time it is needed
+fn fee_credit(avm: &mut Avm) -> u64 {
+    let required = avm.globals.min_txn_fee * avm.txn_group.len() as u64;
+    *avm.fee_credit
+        .get_or_insert_with(|| paid_fees(&avm.txn_group).saturating_sub(required))
+}
+
+fn paid_fees(group: &[Transaction]) -> u64 {
+    group
+        .iter()
+        .fold(0, |paid: u64, txn| paid.saturating_add(txn.fee))
+}
+
+fn evaluate(avm: &mut Avm, group: &mut [Transaction], idx: usize) -> Result<(), AvmError> {
+    let txn = group[idx].clone();
+    let app_address = application_address(avm.txn().current_application_id());
+    {
+        let mut ledger = avm.ledger.borrow_mut();
+        // the application may send from its own account and from
+        // all accounts that were rekeyed to it
+        let auth_addr = ledger.account_params(&txn.sender).auth_addr;
+        let authorizer = match auth_addr {
+            ZERO_ADDRESS => txn.sender,
+            auth_addr => auth_addr,
+        };
+        if authorizer != app_address {
+            return Err(AvmError::Unauthorized("send from this account"));
+        }
+        debit(&mut *ledger, &txn.sender, txn.fee)?;
+        match txn.tx_type {
+            TxType::Unknown => return Err(AvmError::InvalidInnerTxnValue(TxnField::Type)),
+            TxType::Payment => pay(&mut *ledger, &txn)?,
+            TxType::KeyRegistration | TxType::ApplicationCall => {}
+            TxType::AssetConfig => {
+                group[idx].created_asset_id = configure_asset(&mut *ledger, &txn)?
+            }
+            TxType::AssetTransfer => transfer_asset(&mut *ledger, &txn)?,
+            TxType::AssetFreeze => freeze_asset(&mut *ledger, &txn)?,
+        }
+    }
+    if txn.tx_type == TxType::ApplicationCall {
+        call_app(avm, group, idx)?;
+    }
+    let mut ledger = avm.ledger.borrow_mut();
+    if txn.rekey_to != ZERO_ADDRESS {
+        ledger.set_auth_addr(&txn.sender, txn.rekey_to);
+    }
+    check_min_balance(&*ledger, &txn.sender, avm.globals.min_balance)?;
+    if txn.tx_type == TxType::Payment {
+        check_min_balance(&*ledger, &txn.receiver, avm.globals.min_balance)?;
+    }
+    Ok(())
+}
+
+// closed accounts, i.e., those without any balance, are exempt
+fn check_min_balance(
+    ledger: &dyn Ledger,
+    account: &Address,
+    base_min_balance: u64,
+) -> Result<(), AvmError> {
+    let params = ledger.account_params(account);
+    let min_balance = params.min_balance(base_min_balance);
+    if params.balance > 0 && params.balance < min_balance {
+        return Err(AvmError::BelowMinBalance(params.balance, min_balance));
+    }
+    Ok(())
+}
+
+fn debit(ledger: &mut dyn Ledger, account: &Address, amount: u64) -> Result<(), AvmError> {
+    let balance = ledger.account_params(account).balance;
+    let balance = balance
+        .checked_sub(amount)
+        .ok_or(AvmError::InsufficientBalance(balance, amount))?;
+    ledger.set_balance(account, balance);
+    Ok(())
+}
+
+fn credit(ledger: &mut dyn Ledger, account: &Address, amount: u64) -> Result<(), AvmError> {
+    let balance = ledger.account_params(account).balance;
+    let balance = balance
+        .checked_add(amount)
+        .ok_or(AvmError::IntegerOverflow)?;
+    ledger.set_balance(account, balance);
+    Ok(())
+}
+
+// closing the account pays out its remaining balance
+fn pay(ledger: &mut dyn Ledger, txn: &Transaction) -> Result<(), AvmError> {
+    debit(ledger, &txn.sender, txn.amount)?;
+    credit(ledger, &txn.receiver, txn.amount)?;
+    if txn.close_remainder_to != ZERO_ADDRESS {
+        let remainder = ledger.account_params(&txn.sender).balance;
+        debit(ledger, &txn.sender, remainder)?;
+        credit(ledger, &txn.close_remainder_to, remainder)?;
+    }
+    Ok(())
+}
+
+fn asset_params(ledger: &dyn Ledger, asset_id: u64) -> Result<AssetParams, AvmError> {
+    ledger
+        .asset_params(asset_id)
+        .ok_or(AvmError::AssetNotFound(asset_id))
+}
+
+fn asset_holding(
+    ledger: &dyn Ledger,
+    account: &Address,
+    asset_id: u64,
+) -> Result<AssetHolding, AvmError> {
+    ledger
+        .asset_holding(account, asset_id)
+        .ok_or(AvmError::AssetNotOptedIn(asset_id))
+}
+
+// moves units between two accounts that are opted in to the asset,
+// where only the clawback address may move units that are frozen
+fn move_asset(
+    ledger: &mut dyn Ledger,
+    asset_id: u64,
+    from: &Address,
+    to: &Address,
+    amount: u64,
+    clawback: bool,
+) -> Result<(), AvmError> {
+    let mut source = asset_holding(ledger, from, asset_id)?;
+    let target = asset_holding(ledger, to, asset_id)?;
+    if !clawback && (source.frozen || target.frozen) {
+        return Err(AvmError::AssetFrozen(asset_id));
+    }
+    source.amount = source
+        .amount
+        .checked_sub(amount)
+        .ok_or(AvmError::InsufficientBalance(source.amount, amount))?;
+    ledger.set_asset_holding(from, asset_id, Some(source));
+    // read again, as the source may be the target
+    let mut target = asset_holding(ledger, to, asset_id)?;
+    target.amount = target
+        .amount
+        .checked_add(amount)
+        .ok_or(AvmError::IntegerOverflow)?;
+    ledger.set_asset_holding(to, asset_id, Some(target));
+    Ok(())
+}
+
+// a transfer of zero units to the sender itself opts in to the asset,
+// and the clawback address may transfer units from any account
+fn transfer_asset(ledger: &mut dyn Ledger, txn: &Transaction) -> Result<(), AvmError> {
+    let asset_id = txn.xfer_asset;
+    let params = asset_params(ledger, asset_id)?;
+    let clawback = txn.asset_sender != ZERO_ADDRESS;
+    if !clawback
+        && txn.asset_receiver == txn.sender
+        && txn.asset_amount == 0
+        && ledger.asset_holding(&txn.sender, asset_id).is_none()
+    {
+        let holding = AssetHolding {
+            amount: 0,
+            frozen: params.default_frozen,
+        };
+        ledger.set_asset_holding(&txn.sender, asset_id, Some(holding));
+        return Ok(());
+    }
+    if clawback && txn.sender != params.clawback {
+        return Err(AvmError::Unauthorized("claw back the asset"));
+    }
+    let source = if clawback {
+        txn.asset_sender
+    } else {
+        txn.sender
+    };
+    move_asset(
+        ledger,
+        asset_id,
+        &source,
+        &txn.asset_receiver,
+        txn.asset_amount,
+        clawback,
+    )?;
+    if txn.asset_close_to != ZERO_ADDRESS {
+        let remainder = asset_holding(ledger, &source, asset_id)?.amount;
+        move_asset(
+            ledger,
+            asset_id,
+            &source,
+            &txn.asset_close_to,
+            remainder,
+            clawback,
+        )?;
+        ledger.set_asset_holding(&source, asset_id, None);
+    }
+    Ok(())
+}
+
+// creates a new asset if none is given and otherwise reconfigures the
+// asset, or destroys it if all of its addresses are cleared. Returns the
+// id of the created asset, or 0 if no asset was created.
+pub(super) fn configure_asset(ledger: &mut dyn Ledger, txn: &Transaction) -> Result<u64, AvmError> {
+    if txn.config_asset == 0 {
+        let asset_id = ledger.new_creatable_id();
+        let params = AssetParams {
+            total: txn.config_asset_total,
+            decimals: txn.config_asset_decimals,
+            default_frozen: txn.config_asset_default_frozen,
+            unit_name: txn.config_asset_unit_name.clone(),
+            name: txn.config_asset_name.clone(),
+            url: txn.config_asset_url.clone(),
+            metadata_hash: txn.config_asset_metadata_hash.clone(),
+            manager: txn.config_asset_manager,
+            reserve: txn.config_asset_reserve,
+            freeze: txn.config_asset_freeze,
+            clawback: txn.config_asset_clawback,
+            creator: txn.sender,
+        };
+        let holding = AssetHolding {
+            amount: params.total,
+            frozen: false,
+        };
+        ledger.set_asset_params(asset_id, Some(params));
+        ledger.set_asset_holding(&txn.sender, asset_id, Some(holding));
+        return Ok(asset_id);
+    }
+    let asset_id = txn.config_asset;
+    let params = asset_params(ledger, asset_id)?;
+    if txn.sender != params.manager {
+        return Err(AvmError::Unauthorized("configure the asset"));
+    }
+    let addresses = [
+        txn.config_asset_manager,
+        txn.config_asset_reserve,
+        txn.config_asset_freeze,
+        txn.config_asset_clawback,
+    ];
+    if addresses == [ZERO_ADDRESS; 4] {
+        // only once the creator holds all units again
+        let held = ledger
+            .asset_holding(&params.creator, asset_id)
+            .map(|holding| holding.amount);
+        if held != Some(params.total) {
+            return Err(AvmError::Unauthorized("destroy an asset held by others"));
+        }
+        ledger.set_asset_holding(&params.creator, asset_id, None);
+        ledger.set_asset_params(asset_id, None);
+    } else {
+        let [manager, reserve, freeze, clawback] = addresses;
+        let params = AssetParams {
+            manager,
+            reserve,
+            freeze,
+            clawback,
+            ..params
+        };
+        ledger.set_asset_params(asset_id, Some(params));
+    }
+    Ok(0)
+}
+
+fn freeze_asset(ledger: &mut dyn Ledger, txn: &Transaction) -> Result<(), AvmError> {
+    let asset_id = txn.freeze_asset;
+    let params = asset_params(ledger, asset_id)?;
+    if txn.sender != params.freeze {
+        return Err(AvmError::Unauthorized("freeze the asset"));
+    }
+    let mut holding = asset_holding(ledger, &txn.freeze_asset_account, asset_id)?;
+    holding.frozen = txn.freeze_asset_frozen;
+    ledger.set_asset_holding(&txn.freeze_asset_account, asset_id, Some(holding));
+    Ok(())
+}
+
+// creates the application of a creation call and returns its id
+pub(super) fn create_app(ledger: &mut dyn Ledger, txn: &Transaction) -> u64 {
+    let app_id = ledger.new_creatable_id();
+    let params = AppParams {
+        approval_program: txn.approval_program.clone(),
+        clear_state_program: txn.clear_state_program.clone(),
+        global_schema: StateSchema {
+            num_uint: txn.global_num_uint,
+            num_byte_slice: txn.global_num_byte_slice,
+        },
+        local_schema: StateSchema {
+            num_uint: txn.local_num_uint,
+            num_byte_slice: txn.local_num_byte_slice,
+        },
+        extra_program_pages: txn.extra_program_pages,
+        creator: txn.sender,
+    };
+    ledger.set_app_params(app_id, Some(params));
+    app_id
+}
+
+// evaluates the approval program of the called application in a nested Avm
+// that shares the ledger, the limit on inner transactions and the fee credit.
+// The call is approved if the program leaves a single non-zero uint64.
+fn call_app(avm: &mut Avm, group: &mut [Transaction], idx: usize) -> Result<(), AvmError> {
+    let txn = group[idx].clone();
+    let caller_id = avm.txn().current_application_id();
+    if avm.app_call_stack.len() >= MAX_APP_CALL_DEPTH {
+        return Err(AvmError::AppCallDepthExceeded(MAX_APP_CALL_DEPTH));
+    }
+    if txn.application_id == caller_id || avm.app_call_stack.contains(&txn.application_id) {
+        return Err(AvmError::AppReentrance(txn.application_id));
+    }
+    let app_id = match txn.application_id {
+        0 => {
+            let app_id = create_app(&mut *avm.ledger.borrow_mut(), &txn);
+            group[idx].created_application_id = app_id;
+            app_id
+        }
+        app_id => app_id,
+    };
+    let params = avm
+        .ledger
+        .borrow()
+        .app_params(app_id)
+        .ok_or(AvmError::AppNotFound(app_id))?;
+    if txn.on_completion == OnCompletion::OptIn {
+        avm.ledger.borrow_mut().app_opt_in(app_id, &txn.sender);
+    }
+
+    let program = match txn.on_completion {
+        OnCompletion::ClearState => &params.clear_state_program,
+        _ => &params.approval_program,
+    };
+    let mut inner = Avm::for_group(program, group.to_vec(), idx)?;
+    inner.globals = Globals {
+        caller_application_id: caller_id,
+        ..avm.globals.clone()
+    };
+    inner.ledger = avm.ledger.clone();
+    inner.blocks = avm.blocks.clone();
+    inner.inner_txn_count = avm.inner_txn_count;
+    inner.fee_credit = avm.fee_credit;
+    // the inner app call adds to the budget that its caller has left
+    inner.budget = avm.budget.saturating_sub(avm.cost) + APP_CALL_BUDGET;
+    inner.app_call_stack = avm
+        .app_call_stack
+        .iter()
+        .copied()
+        .chain([caller_id])
+        .collect();
+    let run = |inner: &mut Avm| {
+        execute_program(inner)?;
+        if approved(inner) {
+            Ok(())
+        } else {
+            Err(AvmError::InnerAppRejected(app_id))
+        }
+    };
+    if txn.on_completion == OnCompletion::ClearState {
+        // the local state is cleared even if the program fails or
+        // rejects, in which case only the program's changes are undone
+        let _ = atomically(&avm.ledger, || run(&mut inner));
+    } else {
+        run(&mut inner)?;
+    }
+    avm.inner_txn_count = inner.inner_txn_count;
+    avm.fee_credit = inner.fee_credit;
+    avm.budget = avm.cost + inner.budget.saturating_sub(inner.cost);
+    group[idx].logs = inner.logs;
+
+    let mut ledger = avm.ledger.borrow_mut();
+    match txn.on_completion {
+        OnCompletion::CloseOut | OnCompletion::ClearState => {
+            ledger.app_close_out(app_id, &txn.sender)
+        }
+        OnCompletion::UpdateApplication => {
+            let params = AppParams {
+                approval_program: txn.approval_program,
+                clear_state_program: txn.clear_state_program,
+                ..params
+            };
+            ledger.set_app_params(app_id, Some(params));
+        }
+        OnCompletion::DeleteApplication => ledger.set_app_params(app_id, None),
+        OnCompletion::NoOp | OnCompletion::OptIn => {}
+    }
+    Ok(())
+}
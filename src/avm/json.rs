@@ -0,0 +1,361 @@
+use crate::AvmError;
+
+// JSON parsing for json_ref. The parser follows RFC 8259 but is stricter
+// than most JSON libraries: the top-level value must be an object, keys
+// may not occur more than once within an object (at any nesting level),
+// and only space, tab, line feed and carriage return count as whitespace.
+
+// objects and arrays are parsed recursively, so their nesting is limited
+// to bound the stack space that the parser needs
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, PartialEq)]
+pub enum JsonValue<'a> {
+    Null,
+    Bool(bool),
+    // numbers are kept in their textual representation since
+    // json_ref only ever needs to interpret them as uint64
+    Number(&'a [u8]),
+    String(Vec<u8>),
+    Array(Vec<Json<'a>>),
+    Object(Vec<(Vec<u8>, Json<'a>)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Json<'a> {
+    // the text of the value, without surrounding whitespace
+    pub raw: &'a [u8],
+    pub value: JsonValue<'a>,
+}
+
+impl<'a> Json<'a> {
+    pub fn get(&self, key: &[u8]) -> Option<&Json<'a>> {
+        match &self.value {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_object(text: &[u8]) -> Result<Json<'_>, AvmError> {
+    if std::str::from_utf8(text).is_err() {
+        return Err(AvmError::InvalidJson("invalid utf-8"));
+    }
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    if parser.peek() != Some(b'{') {
+        return Err(AvmError::InvalidJson("only json objects are allowed"));
+    }
+    let json = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos != text.len() {
+        return Err(AvmError::InvalidJson("trailing characters"));
+    }
+    Ok(json)
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    // the number of objects and arrays that enclose the current position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, AvmError> {
+        let c = self
+            .peek()
+            .ok_or(AvmError::InvalidJson("unexpected end of text"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), AvmError> {
+        if self.next()? == expected {
+            Ok(())
+        } else {
+            Err(AvmError::InvalidJson("unexpected character"))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json<'a>, AvmError> {
+        let start = self.pos;
+        let value = match self.peek() {
+            Some(b'{') => self.parse_nested(Self::parse_object)?,
+            Some(b'[') => self.parse_nested(Self::parse_array)?,
+            Some(b'"') => JsonValue::String(self.parse_string()?),
+            Some(b'-' | b'0'..=b'9') => self.parse_number()?,
+            Some(b't') => self.parse_literal(b"true", JsonValue::Bool(true))?,
+            Some(b'f') => self.parse_literal(b"false", JsonValue::Bool(false))?,
+            Some(b'n') => self.parse_literal(b"null", JsonValue::Null)?,
+            Some(_) => return Err(AvmError::InvalidJson("unexpected character")),
+            None => return Err(AvmError::InvalidJson("unexpected end of text")),
+        };
+        Ok(Json {
+            raw: &self.text[start..self.pos],
+            value,
+        })
+    }
+
+    fn parse_nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<JsonValue<'a>, AvmError>,
+    ) -> Result<JsonValue<'a>, AvmError> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(AvmError::InvalidJson("nesting too deep"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &[u8],
+        value: JsonValue<'a>,
+    ) -> Result<JsonValue<'a>, AvmError> {
+        if self.text[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(AvmError::InvalidJson("invalid literal"))
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue<'a>, AvmError> {
+        self.expect(b'{')?;
+        let mut members: Vec<(Vec<u8>, Json<'a>)> = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(AvmError::InvalidJson("object keys must be strings"));
+            }
+            let key = self.parse_string()?;
+            if members.iter().any(|(other, _)| *other == key) {
+                return Err(AvmError::InvalidJson("duplicate keys are not allowed"));
+            }
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b'}' => return Ok(JsonValue::Object(members)),
+                _ => return Err(AvmError::InvalidJson("unexpected character")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue<'a>, AvmError> {
+        self.expect(b'[')?;
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(elements));
+        }
+        loop {
+            self.skip_whitespace();
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b']' => return Ok(JsonValue::Array(elements)),
+                _ => return Err(AvmError::InvalidJson("unexpected character")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<Vec<u8>, AvmError> {
+        self.expect(b'"')?;
+        let mut decoded = vec![];
+        loop {
+            match self.next()? {
+                b'"' => return Ok(decoded),
+                b'\\' => match self.next()? {
+                    b'"' => decoded.push(b'"'),
+                    b'\\' => decoded.push(b'\\'),
+                    b'/' => decoded.push(b'/'),
+                    b'b' => decoded.push(0x08),
+                    b'f' => decoded.push(0x0c),
+                    b'n' => decoded.push(b'\n'),
+                    b'r' => decoded.push(b'\r'),
+                    b't' => decoded.push(b'\t'),
+                    b'u' => {
+                        let c = self.parse_unicode_escape()?;
+                        let mut buffer = [0u8; 4];
+                        decoded.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                    }
+                    _ => return Err(AvmError::InvalidJson("invalid escape sequence")),
+                },
+                0x00..=0x1f => return Err(AvmError::InvalidJson("control character in string")),
+                c => decoded.push(c),
+            }
+        }
+    }
+
+    // lone surrogates are decoded to the unicode replacement character
+    fn parse_unicode_escape(&mut self) -> Result<char, AvmError> {
+        let high = self.parse_hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return Ok(char::from_u32(high as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        if !self.text[self.pos..].starts_with(b"\\u") {
+            return Ok(char::REPLACEMENT_CHARACTER);
+        }
+        let checkpoint = self.pos;
+        self.pos += 2;
+        let low = self.parse_hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            // the second escape is not part of a surrogate pair
+            self.pos = checkpoint;
+            return Ok(char::REPLACEMENT_CHARACTER);
+        }
+        let c = 0x10000 + (((high as u32) - 0xd800) << 10) + ((low as u32) - 0xdc00);
+        Ok(char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u16, AvmError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = (self.next()? as char)
+                .to_digit(16)
+                .ok_or(AvmError::InvalidJson("invalid unicode escape"))?;
+            value = (value << 4) | digit as u16;
+        }
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue<'a>, AvmError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.next()? {
+            // leading zeros are not allowed
+            b'0' => {}
+            b'1'..=b'9' => self.skip_digits(),
+            _ => return Err(AvmError::InvalidJson("invalid number")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.expect_digits()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.expect_digits()?;
+        }
+        Ok(JsonValue::Number(&self.text[start..self.pos]))
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect_digits(&mut self) -> Result<(), AvmError> {
+        match self.peek() {
+            Some(b'0'..=b'9') => {
+                self.skip_digits();
+                Ok(())
+            }
+            _ => Err(AvmError::InvalidJson("invalid number")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_object() {
+        let text = r#" {"a": [1, -2.5e3, true, null], "b": {"c": "é\ud83d\ude00"}} "#;
+        let json = parse_object(text.as_bytes()).unwrap();
+
+        let a = json.get(b"a").unwrap();
+        assert_eq!(&b"[1, -2.5e3, true, null]"[..], a.raw);
+        let b = json.get(b"b").unwrap();
+        assert_eq!(r#"{"c": "é\ud83d\ude00"}"#.as_bytes(), b.raw);
+        let c = b.get(b"c").unwrap();
+        assert_eq!(JsonValue::String("é😀".as_bytes().to_vec()), c.value);
+        assert_eq!(None, json.get(b"c"));
+    }
+
+    #[test]
+    fn test_parse_object_rejects_non_objects() {
+        for text in [&b"[]"[..], b"1", b"\"a\"", b"null", b""] {
+            assert_eq!(
+                AvmError::InvalidJson("only json objects are allowed"),
+                parse_object(text).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_object_rejects_duplicate_keys() {
+        let err = parse_object(br#"{"a": 1, "a": 2}"#).unwrap_err();
+        assert_eq!(AvmError::InvalidJson("duplicate keys are not allowed"), err);
+        let err = parse_object(br#"{"a": {"b": 1, "b": 2}}"#).unwrap_err();
+        assert_eq!(AvmError::InvalidJson("duplicate keys are not allowed"), err);
+    }
+
+    #[test]
+    fn test_parse_object_rejects_invalid_text() {
+        for text in [
+            &br#"{"a": 1,}"#[..],
+            br#"{"a": 01}"#,
+            br#"{"a": 1.}"#,
+            br#"{a: 1}"#,
+            br#"{"a": "\x"}"#,
+            b"{\"a\": \"\n\"}",
+            br#"{"a": tru}"#,
+            br#"{"a": 1} {}"#,
+            b"{\"a\": \xff}",
+        ] {
+            assert!(parse_object(text).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_object_nesting_depth() {
+        let nested =
+            |depth: usize| format!(r#"{{"a": {}{}}}"#, "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_object(nested(MAX_NESTING_DEPTH - 1).as_bytes()).is_ok());
+        for depth in [MAX_NESTING_DEPTH, 2040] {
+            assert_eq!(
+                AvmError::InvalidJson("nesting too deep"),
+                parse_object(nested(depth).as_bytes()).unwrap_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use super::{
+    application_address, AccountParams, Address, AppParams, AssetHolding, AssetParams, AvmData,
+};
+
+// the maximum number of values an application may keep in its state
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct StateSchema {
+    pub num_uint: u64,
+    pub num_byte_slice: u64,
+}
+
+impl StateSchema {
+    // the number of values of each type in the given state
+    fn usage<'a>(values: impl Iterator<Item = &'a AvmData>) -> Self {
+        let mut usage = StateSchema::default();
+        for value in values {
+            match value {
+                AvmData::Uint64(_) => usage.num_uint += 1,
+                AvmData::Bytes(_) => usage.num_byte_slice += 1,
+            }
+        }
+        usage
+    }
+}
+
+// the state that programs read and modify through the state opcodes.
+// Implement this trait to evaluate programs against a custom store,
+// e.g., one that is backed by a node's database.
+pub trait Ledger: Debug {
+    fn app_global_schema(&self, app_id: u64) -> StateSchema;
+    // the number of values of each type currently in the global state
+    fn app_global_usage(&self, app_id: u64) -> StateSchema;
+    fn app_global_get(&self, app_id: u64, key: &[u8]) -> Option<AvmData>;
+    fn app_global_put(&mut self, app_id: u64, key: &[u8], value: AvmData);
+    fn app_global_del(&mut self, app_id: u64, key: &[u8]);
+
+    fn app_local_schema(&self, app_id: u64) -> StateSchema;
+    fn app_opted_in(&self, app_id: u64, account: &Address) -> bool;
+    // allocates an empty local state for the account
+    fn app_opt_in(&mut self, app_id: u64, account: &Address);
+    // removes the local state of the account
+    fn app_close_out(&mut self, app_id: u64, account: &Address);
+    // the number of values of each type currently in the local state
+    fn app_local_usage(&self, app_id: u64, account: &Address) -> StateSchema;
+    fn app_local_get(&self, app_id: u64, account: &Address, key: &[u8]) -> Option<AvmData>;
+    fn app_local_put(&mut self, app_id: u64, account: &Address, key: &[u8], value: AvmData);
+    fn app_local_del(&mut self, app_id: u64, account: &Address, key: &[u8]);
+
+    fn box_get(&self, app_id: u64, name: &[u8]) -> Option<Vec<u8>>;
+    // creates the box or replaces its contents
+    fn box_put(&mut self, app_id: u64, name: &[u8], contents: Vec<u8>);
+    // returns whether the box existed
+    fn box_del(&mut self, app_id: u64, name: &[u8]) -> bool;
+
+    fn account_params(&self, account: &Address) -> AccountParams;
+    fn set_balance(&mut self, account: &Address, balance: u64);
+    fn set_auth_addr(&mut self, account: &Address, auth_addr: Address);
+
+    // None if the account is not opted in to the asset
+    fn asset_holding(&self, account: &Address, asset_id: u64) -> Option<AssetHolding>;
+    // None if the asset does not exist
+    fn asset_params(&self, asset_id: u64) -> Option<AssetParams>;
+    // None opts the account out of the asset
+    fn set_asset_holding(
+        &mut self,
+        account: &Address,
+        asset_id: u64,
+        holding: Option<AssetHolding>,
+    );
+    // None destroys the asset
+    fn set_asset_params(&mut self, asset_id: u64, params: Option<AssetParams>);
+
+    // None if the application does not exist
+    fn app_params(&self, app_id: u64) -> Option<AppParams>;
+    // creates or updates the application, where None deletes it
+    fn set_app_params(&mut self, app_id: u64, params: Option<AppParams>);
+
+    // an id that is not yet taken by any asset or application
+    fn new_creatable_id(&mut self) -> u64;
+
+    // saves the current state, so that the changes that follow can be
+    // undone. Checkpoints nest, e.g., for inner transactions of a group.
+    fn checkpoint(&mut self);
+    // keeps the changes since the last checkpoint and discards the checkpoint
+    fn commit(&mut self);
+    // undoes the changes since the last checkpoint
+    fn rollback(&mut self);
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Application {
+    pub creator: Address,
+    pub approval_program: Vec<u8>,
+    pub clear_state_program: Vec<u8>,
+    pub extra_program_pages: u64,
+    pub global_schema: StateSchema,
+    pub global_state: HashMap<Vec<u8>, AvmData>,
+    pub local_schema: StateSchema,
+    // the local states of all accounts that opted in to the application
+    pub local_states: HashMap<Address, HashMap<Vec<u8>, AvmData>>,
+    pub boxes: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Account {
+    pub balance: u64,
+    pub auth_addr: Address,
+    // the assets the account opted in to
+    pub assets: HashMap<u64, AssetHolding>,
+}
+
+// a ledger that keeps all state in memory, which is the default
+// ledger of an Avm and meant for testing programs
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryLedger {
+    pub accounts: HashMap<Address, Account>,
+    pub apps: HashMap<u64, Application>,
+    pub assets: HashMap<u64, AssetParams>,
+    // the last id handed out to a created asset or application
+    pub last_creatable_id: u64,
+    // the states saved by checkpoints, the most recent last
+    pub checkpoints: Vec<MemoryLedger>,
+}
+
+impl Ledger for MemoryLedger {
+    fn app_global_schema(&self, app_id: u64) -> StateSchema {
+        self.apps
+            .get(&app_id)
+            .map(|app| app.global_schema)
+            .unwrap_or_default()
+    }
+
+    fn app_global_usage(&self, app_id: u64) -> StateSchema {
+        match self.apps.get(&app_id) {
+            Some(app) => StateSchema::usage(app.global_state.values()),
+            None => StateSchema::default(),
+        }
+    }
+
+    fn app_global_get(&self, app_id: u64, key: &[u8]) -> Option<AvmData> {
+        self.apps
+            .get(&app_id)
+            .and_then(|app| app.global_state.get(key))
+            .cloned()
+    }
+
+    fn app_global_put(&mut self, app_id: u64, key: &[u8], value: AvmData) {
+        self.apps
+            .entry(app_id)
+            .or_default()
+            .global_state
+            .insert(key.to_vec(), value);
+    }
+
+    fn app_global_del(&mut self, app_id: u64, key: &[u8]) {
+        if let Some(app) = self.apps.get_mut(&app_id) {
+            app.global_state.remove(key);
+        }
+    }
+
+    fn app_local_schema(&self, app_id: u64) -> StateSchema {
+        self.apps
+            .get(&app_id)
+            .map(|app| app.local_schema)
+            .unwrap_or_default()
+    }
+
+    fn app_opted_in(&self, app_id: u64, account: &Address) -> bool {
+        self.apps
+            .get(&app_id)
+            .is_some_and(|app| app.local_states.contains_key(account))
+    }
+
+    fn app_opt_in(&mut self, app_id: u64, account: &Address) {
+        self.apps
+            .entry(app_id)
+            .or_default()
+            .local_states
+            .entry(*account)
+            .or_default();
+    }
+
+    fn app_close_out(&mut self, app_id: u64, account: &Address) {
+        if let Some(app) = self.apps.get_mut(&app_id) {
+            app.local_states.remove(account);
+        }
+    }
+
+    fn app_local_usage(&self, app_id: u64, account: &Address) -> StateSchema {
+        match self
+            .apps
+            .get(&app_id)
+            .and_then(|app| app.local_states.get(account))
+        {
+            Some(state) => StateSchema::usage(state.values()),
+            None => StateSchema::default(),
+        }
+    }
+
+    fn app_local_get(&self, app_id: u64, account: &Address, key: &[u8]) -> Option<AvmData> {
+        self.apps
+            .get(&app_id)
+            .and_then(|app| app.local_states.get(account))
+            .and_then(|state| state.get(key))
+            .cloned()
+    }
+
+    fn app_local_put(&mut self, app_id: u64, account: &Address, key: &[u8], value: AvmData) {
+        self.apps
+            .entry(app_id)
+            .or_default()
+            .local_states
+            .entry(*account)
+            .or_default()
+            .insert(key.to_vec(), value);
+    }
+
+    fn app_local_del(&mut self, app_id: u64, account: &Address, key: &[u8]) {
+        if let Some(state) = self
+            .apps
+            .get_mut(&app_id)
+            .and_then(|app| app.local_states.get_mut(account))
+        {
+            state.remove(key);
+        }
+    }
+
+    fn box_get(&self, app_id: u64, name: &[u8]) -> Option<Vec<u8>> {
+        self.apps
+            .get(&app_id)
+            .and_then(|app| app.boxes.get(name))
+            .cloned()
+    }
+
+    fn box_put(&mut self, app_id: u64, name: &[u8], contents: Vec<u8>) {
+        self.apps
+            .entry(app_id)
+            .or_default()
+            .boxes
+            .insert(name.to_vec(), contents);
+    }
+
+    fn box_del(&mut self, app_id: u64, name: &[u8]) -> bool {
+        self.apps
+            .get_mut(&app_id)
+            .is_some_and(|app| app.boxes.remove(name).is_some())
+    }
+
+    // the totals are derived from the assets and apps the account created or
+    // opted in to, and, for application accounts, the boxes of the application
+    fn account_params(&self, account: &Address) -> AccountParams {
+        let mut params = AccountParams::default();
+        if let Some(acct) = self.accounts.get(account) {
+            params.balance = acct.balance;
+            params.auth_addr = acct.auth_addr;
+            params.total_assets = acct.assets.len() as u64;
+        }
+        params.total_assets_created = self
+            .assets
+            .values()
+            .filter(|asset| asset.creator == *account)
+            .count() as u64;
+        for (app_id, app) in &self.apps {
+            if app.creator == *account {
+                params.total_apps_created += 1;
+                params.total_extra_app_pages += app.extra_program_pages;
+                params.total_num_uint += app.global_schema.num_uint;
+                params.total_num_byte_slice += app.global_schema.num_byte_slice;
+            }
+            if app.local_states.contains_key(account) {
+                params.total_apps_opted_in += 1;
+                params.total_num_uint += app.local_schema.num_uint;
+                params.total_num_byte_slice += app.local_schema.num_byte_slice;
+            }
+            if application_address(*app_id) == *account {
+                params.total_boxes = app.boxes.len() as u64;
+                params.total_box_bytes = app
+                    .boxes
+                    .iter()
+                    .map(|(name, contents)| (name.len() + contents.len()) as u64)
+                    .sum();
+            }
+        }
+        params
+    }
+
+    fn set_balance(&mut self, account: &Address, balance: u64) {
+        self.accounts.entry(*account).or_default().balance = balance;
+    }
+
+    fn set_auth_addr(&mut self, account: &Address, auth_addr: Address) {
+        self.accounts.entry(*account).or_default().auth_addr = auth_addr;
+    }
+
+    fn asset_holding(&self, account: &Address, asset_id: u64) -> Option<AssetHolding> {
+        self.accounts
+            .get(account)
+            .and_then(|acct| acct.assets.get(&asset_id))
+            .copied()
+    }
+
+    fn asset_params(&self, asset_id: u64) -> Option<AssetParams> {
+        self.assets.get(&asset_id).cloned()
+    }
+
+    fn set_asset_holding(
+        &mut self,
+        account: &Address,
+        asset_id: u64,
+        holding: Option<AssetHolding>,
+    ) {
+        let assets = &mut self.accounts.entry(*account).or_default().assets;
+        match holding {
+            Some(holding) => assets.insert(asset_id, holding),
+            None => assets.remove(&asset_id),
+        };
+    }
+
+    fn set_asset_params(&mut self, asset_id: u64, params: Option<AssetParams>) {
+        match params {
+            Some(params) => self.assets.insert(asset_id, params),
+            None => self.assets.remove(&asset_id),
+        };
+    }
+
+    fn app_params(&self, app_id: u64) -> Option<AppParams> {
+        self.apps.get(&app_id).map(|app| AppParams {
+            approval_program: app.approval_program.clone(),
+            clear_state_program: app.clear_state_program.clone(),
+            global_schema: app.global_schema,
+            local_schema: app.local_schema,
+            extra_program_pages: app.extra_program_pages,
+            creator: app.creator,
+        })
+    }
+
+    // the state of an existing application is kept when it is updated
+    fn set_app_params(&mut self, app_id: u64, params: Option<AppParams>) {
+        let Some(params) = params else {
+            self.apps.remove(&app_id);
+            return;
+        };
+        let app = self.apps.entry(app_id).or_default();
+        app.creator = params.creator;
+        app.approval_program = params.approval_program;
+        app.clear_state_program = params.clear_state_program;
+        app.extra_program_pages = params.extra_program_pages;
+        app.global_schema = params.global_schema;
+        app.local_schema = params.local_schema;
+    }
+
+    // ids are shared by assets and applications and never reused
+    fn new_creatable_id(&mut self) -> u64 {
+        let last_id = self
+            .assets
+            .keys()
+            .chain(self.apps.keys())
+            .copied()
+            .chain([self.last_creatable_id])
+            .max()
+            .unwrap_or_default();
+        self.last_creatable_id = last_id + 1;
+        self.last_creatable_id
+    }
+
+    fn checkpoint(&mut self) {
+        let checkpoints = std::mem::take(&mut self.checkpoints);
+        let saved = self.clone();
+        self.checkpoints = checkpoints;
+        self.checkpoints.push(saved);
+    }
+
+    fn commit(&mut self) {
+        self.checkpoints.pop();
+    }
+
+    fn rollback(&mut self) {
+        if let Some(saved) = self.checkpoints.pop() {
+            let checkpoints = std::mem::take(&mut self.checkpoints);
+            *self = saved;
+            self.checkpoints = checkpoints;
+        }
+    }
+}
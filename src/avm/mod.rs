@@ -2,16 +2,62 @@ use crate::{
     encoding::{VarBytes, VarUint64},
     AvmError,
 };
+pub use account::{AccountParams, AcctParamsField};
+pub use app::{AppParams, AppParamsField};
+pub use asset::{AssetHolding, AssetHoldingField, AssetParams, AssetParamsField};
+pub use block::{BlockField, BlockHeader, BlockHistory, MemoryBlockHistory};
+pub use ec::EcGroup;
+pub use globals::{GlobalField, Globals};
+pub use ledger::{Account, Application, Ledger, MemoryLedger, StateSchema};
 use opcodes::OP_SPECS;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::vec::Vec;
+pub use transaction::{
+    application_address, Address, BoxReference, OnCompletion, Transaction, TxType, TxnField,
+};
+pub use vrf::VrfStandard;
 
+mod account;
+mod app;
+mod asset;
+mod block;
+mod ec;
+mod globals;
+mod inner;
+mod json;
+mod ledger;
 mod opcodes;
+mod transaction;
+mod vrf;
 
 const FALSE: AvmData = AvmData::Uint64(0);
 const TRUE: AvmData = AvmData::Uint64(1);
 
-const LABEL_UINT64: &'static str = "uint64";
-const LABEL_BYTES: &'static str = "bytes";
+// the app calls of a group, including inner ones, share the budget
+// that each of them adds, while a logic signature has its own
+const APP_CALL_BUDGET: u64 = 700;
+const LOGICSIG_BUDGET: u64 = 20000;
+const MAX_STACK_DEPTH: usize = 1000;
+const MAX_BYTES_LEN: usize = 4096;
+const MAX_LOG_CALLS: usize = 32;
+const MAX_LOG_SIZE: usize = 1024;
+const MAX_TXN_GROUP_SIZE: usize = 16;
+const MAX_APP_KEY_LEN: usize = 64;
+const MAX_APP_KEY_VALUE_LEN: usize = 128;
+const MAX_BOX_NAME_LEN: usize = 64;
+const MAX_BOX_SIZE: u64 = 32768;
+// each box reference in a group adds this many bytes to the box quota
+const BOX_REFERENCE_QUOTA: u64 = 1024;
+// shared by all programs of a top-level group, including inner app calls
+const MAX_INNER_TXNS: usize = 256;
+const MAX_APP_CALL_DEPTH: usize = 8;
+
+// ARC-4 methods return values by logging them with this prefix
+const ABI_RETURN_PREFIX: [u8; 4] = [0x15, 0x1f, 0x7c, 0x75];
+
+const LABEL_UINT64: &str = "uint64";
+const LABEL_BYTES: &str = "bytes";
 
 #[derive(Debug)]
 pub struct Avm<'a> {
@@ -22,6 +68,37 @@ pub struct Avm<'a> {
     pub intc: Vec<u64>,
     pub bytec: Vec<&'a [u8]>,
     pub scratch: Vec<AvmData>,
+    pub cost: u64,
+    // the cost the program may reach before it fails
+    pub budget: u64,
+    pub mode: RunMode,
+    pub logs: Vec<Vec<u8>>,
+    pub args: Vec<Vec<u8>>,
+    pub txn_group: Vec<Transaction>,
+    pub group_index: usize,
+    // the final scratch space of each transaction in the group that has
+    // already been evaluated, empty for all other transactions
+    pub group_scratch: Vec<Vec<AvmData>>,
+    pub globals: Globals,
+    // shared by all programs of a group, so that they observe
+    // each other's state changes
+    pub ledger: Rc<RefCell<dyn Ledger>>,
+    // the headers of past blocks, shared like the ledger
+    pub blocks: Rc<dyn BlockHistory>,
+    // inner transactions that have been started but not yet submitted
+    pub pending_inner_txns: Vec<Transaction>,
+    // the inner group that was submitted last, including its
+    // effects such as created ids and the logs of app calls
+    pub last_inner_group: Vec<Transaction>,
+    // the number of inner transactions submitted so far by all
+    // programs of the top-level group
+    pub inner_txn_count: usize,
+    // the fees paid beyond the minimum so far, which pay for inner
+    // transactions; None until it is first needed
+    pub fee_credit: Option<u64>,
+    // the applications whose inner transactions led to
+    // this evaluation, the top-level application first
+    pub app_call_stack: Vec<u64>,
 }
 
 impl<'a> Avm<'a> {
@@ -46,9 +123,79 @@ impl<'a> Avm<'a> {
             intc: vec![],
             bytec: vec![],
             scratch,
+            cost: 0,
+            budget: APP_CALL_BUDGET,
+            mode: RunMode::Application,
+            logs: vec![],
+            args: vec![],
+            txn_group: vec![Transaction::default()],
+            group_index: 0,
+            group_scratch: vec![vec![]],
+            globals: Globals::default(),
+            ledger: Rc::new(RefCell::new(MemoryLedger::default())),
+            blocks: Rc::new(MemoryBlockHistory::default()),
+            pending_inner_txns: vec![],
+            last_inner_group: vec![],
+            inner_txn_count: 0,
+            fee_credit: None,
+            app_call_stack: vec![],
         })
     }
 
+    // creates an Avm that evaluates a program for the given transaction
+    pub fn for_transaction(program: &'a [u8], txn: Transaction) -> Result<Self, AvmError> {
+        Avm::for_group(program, vec![txn], 0)
+    }
+
+    // creates an Avm that evaluates a program for the transaction at
+    // position group_index within the given transaction group
+    pub fn for_group(
+        program: &'a [u8],
+        txn_group: Vec<Transaction>,
+        group_index: usize,
+    ) -> Result<Self, AvmError> {
+        if txn_group.len() > MAX_TXN_GROUP_SIZE {
+            return Err(AvmError::TxnGroupTooLarge(txn_group.len()));
+        }
+        if group_index >= txn_group.len() {
+            return Err(AvmError::GroupIndexOutOfRange(group_index, txn_group.len()));
+        }
+        let mut avm = Avm::for_program(program)?;
+        avm.group_scratch = vec![vec![]; txn_group.len()];
+        avm.txn_group = txn_group;
+        avm.group_index = group_index;
+        Ok(avm)
+    }
+
+    pub fn txn(&self) -> &Transaction {
+        &self.txn_group[self.group_index]
+    }
+
+    // creates an Avm that evaluates a logic signature with the given arguments
+    pub fn for_logicsig(program: &'a [u8], args: Vec<Vec<u8>>) -> Result<Self, AvmError> {
+        let mut avm = Avm::for_program(program)?;
+        avm.mode = RunMode::Signature;
+        avm.budget = LOGICSIG_BUDGET;
+        avm.args = args;
+        Ok(avm)
+    }
+
+    // the value returned by an ARC-4 method call, i.e., the last
+    // log entry stripped of its return prefix
+    pub fn abi_return_value(&self) -> Option<&[u8]> {
+        self.logs
+            .last()
+            .and_then(|log| log.strip_prefix(&ABI_RETURN_PREFIX[..]))
+    }
+
+    fn ensure_mode(&self, mode: RunMode) -> Result<(), AvmError> {
+        if self.mode == mode {
+            Ok(())
+        } else {
+            Err(AvmError::InvalidRunMode(mode))
+        }
+    }
+
     fn read_byte(&mut self) -> Result<u8, AvmError> {
         if self.pc < self.program.len() {
             let byte = self.program[self.pc];
@@ -105,9 +252,11 @@ impl<'a> Avm<'a> {
     }
 }
 
-pub fn execute_program<'a>(avm: &'a mut Avm<'a>) -> Result<&'a mut Avm<'a>, AvmError> {
-    let mut cost = 0;
-    while cost < 700 && avm.pc < avm.program.len() {
+// runs the program until it ends, failing as soon as it exceeds its budget.
+// The program's changes go straight to the ledger whether or not it
+// approves, see execute_group for the evaluation of transactions.
+pub fn execute_program<'a, 'b>(avm: &'b mut Avm<'a>) -> Result<&'b mut Avm<'a>, AvmError> {
+    while avm.pc < avm.program.len() {
         let opcode = avm.read_byte()?;
         let spec_idx = OP_SPECS
             .iter()
@@ -117,13 +266,176 @@ pub fn execute_program<'a>(avm: &'a mut Avm<'a>) -> Result<&'a mut Avm<'a>, AvmE
             None => return Err(AvmError::UnknownOpcode(opcode)),
         };
 
+        // the cost is charged before the opcode runs, so that
+        // the opcode observes its own cost in the budget
+        avm.cost += opspec.cost;
         (opspec.eval)(avm)?;
-        cost += opspec.cost;
+        if avm.cost > avm.budget {
+            return Err(AvmError::CostBudgetExceeded(avm.cost, avm.budget));
+        }
+        if avm.data_stack.len() > MAX_STACK_DEPTH {
+            return Err(AvmError::StackOverflow(MAX_STACK_DEPTH));
+        }
     }
 
     Ok(avm)
 }
 
+// evaluates the programs of a transaction group in order, one optional
+// program per transaction. The logs and the final scratch space of each
+// evaluated program are made available to the programs that follow it,
+// and app calls opt in to or close out of their application. All programs
+// share the limit on inner transactions and the credit for their fees, and
+// the programs of app calls share the budget that each app call adds.
+// Programs of other transactions are logic signatures. The group fails as
+// a whole if any program fails or rejects, in which case none of its
+// changes are applied to the ledger.
+pub fn execute_group<'a>(
+    programs: &[Option<&'a [u8]>],
+    txn_group: Vec<Transaction>,
+    globals: Globals,
+    ledger: Rc<RefCell<dyn Ledger>>,
+    blocks: Rc<dyn BlockHistory>,
+) -> Result<Vec<Option<Avm<'a>>>, AvmError> {
+    if programs.len() != txn_group.len() {
+        return Err(AvmError::ProgramCountMismatch(
+            programs.len(),
+            txn_group.len(),
+        ));
+    }
+    if txn_group.len() > MAX_TXN_GROUP_SIZE {
+        return Err(AvmError::TxnGroupTooLarge(txn_group.len()));
+    }
+    let group_ledger = ledger.clone();
+    atomically(&ledger, || {
+        evaluate_group(programs, txn_group, globals, group_ledger, blocks)
+    })
+}
+
+fn evaluate_group<'a>(
+    programs: &[Option<&'a [u8]>],
+    txn_group: Vec<Transaction>,
+    globals: Globals,
+    ledger: Rc<RefCell<dyn Ledger>>,
+    blocks: Rc<dyn BlockHistory>,
+) -> Result<Vec<Option<Avm<'a>>>, AvmError> {
+    let mut txn_group = txn_group;
+    let mut group_scratch = vec![vec![]; txn_group.len()];
+    let mut inner_txn_count = 0;
+    let mut fee_credit = None;
+    let app_calls = txn_group
+        .iter()
+        .filter(|txn| txn.tx_type == TxType::ApplicationCall)
+        .count() as u64;
+    let mut pooled_budget = APP_CALL_BUDGET * app_calls;
+    let mut evaluated = vec![];
+    for (group_index, program) in programs.iter().enumerate() {
+        // creations get their ids in group order, so that later
+        // transactions can read them with gaid and gaids
+        let txn = &txn_group[group_index];
+        match txn.tx_type {
+            TxType::ApplicationCall if txn.application_id == 0 => {
+                let app_id = inner::create_app(&mut *ledger.borrow_mut(), txn);
+                txn_group[group_index].created_application_id = app_id;
+            }
+            TxType::AssetConfig if txn.config_asset == 0 => {
+                let asset_id = inner::configure_asset(&mut *ledger.borrow_mut(), txn)?;
+                txn_group[group_index].created_asset_id = asset_id;
+            }
+            _ => {}
+        }
+        let Some(program) = program else {
+            evaluated.push(None);
+            continue;
+        };
+        let txn = &txn_group[group_index];
+        let is_app_call = txn.tx_type == TxType::ApplicationCall;
+        // the sender's local state is allocated before the approval program
+        // of an opt-in runs, and removed once a close-out has been approved.
+        // The opt-in is undone with the rest of the group if a program fails.
+        if is_app_call && txn.on_completion == OnCompletion::OptIn {
+            ledger
+                .borrow_mut()
+                .app_opt_in(txn.current_application_id(), &txn.sender);
+        }
+        let mut avm = Avm::for_group(program, txn_group.clone(), group_index)?;
+        avm.group_scratch = group_scratch.clone();
+        avm.globals = globals.clone();
+        avm.ledger = ledger.clone();
+        avm.blocks = blocks.clone();
+        avm.inner_txn_count = inner_txn_count;
+        avm.fee_credit = fee_credit;
+        if is_app_call {
+            avm.budget = pooled_budget;
+        } else {
+            avm.mode = RunMode::Signature;
+            avm.budget = LOGICSIG_BUDGET;
+        }
+        if is_app_call && txn_group[group_index].on_completion == OnCompletion::ClearState {
+            // the local state is cleared even if the program fails or
+            // rejects, in which case only the program's changes are undone
+            let _ = atomically(&ledger, || {
+                execute_program(&mut avm)?;
+                if approved(&avm) {
+                    Ok(())
+                } else {
+                    Err(AvmError::ProgramRejected(group_index))
+                }
+            });
+        } else {
+            execute_program(&mut avm)?;
+            if !approved(&avm) {
+                return Err(AvmError::ProgramRejected(group_index));
+            }
+        }
+        inner_txn_count = avm.inner_txn_count;
+        fee_credit = avm.fee_credit;
+        if is_app_call {
+            pooled_budget = avm.budget.saturating_sub(avm.cost);
+        }
+        let txn = &txn_group[group_index];
+        if is_app_call
+            && matches!(
+                txn.on_completion,
+                OnCompletion::CloseOut | OnCompletion::ClearState
+            )
+        {
+            ledger
+                .borrow_mut()
+                .app_close_out(txn.current_application_id(), &txn.sender);
+        }
+        txn_group[group_index].logs = avm.logs.clone();
+        group_scratch[group_index] = avm.scratch.clone();
+        evaluated.push(Some(avm));
+    }
+    Ok(evaluated)
+}
+
+// applies the changes that f makes to the ledger only if it succeeds
+pub(super) fn atomically<T>(
+    ledger: &Rc<RefCell<dyn Ledger>>,
+    f: impl FnOnce() -> Result<T, AvmError>,
+) -> Result<T, AvmError> {
+    ledger.borrow_mut().checkpoint();
+    let result = f();
+    match result {
+        Ok(_) => ledger.borrow_mut().commit(),
+        Err(_) => ledger.borrow_mut().rollback(),
+    }
+    result
+}
+
+// a program approves if it leaves a single non-zero uint64 on the stack
+fn approved(avm: &Avm) -> bool {
+    matches!(avm.data_stack[..], [AvmData::Uint64(result)] if result != 0)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RunMode {
+    Signature,
+    Application,
+}
+
 #[derive(Debug, PartialEq, PartialOrd)]
 pub enum AvmVersion {
     V1,
@@ -1,21 +1,32 @@
 use crate::{
-    encoding::{self, VarBytes, VarUint64},
+    encoding::{self, Base64Alphabet, VarBytes, VarUint64},
     AvmError,
 };
 
-use super::{Avm, AvmData, AvmVersion, FALSE, TRUE};
+use super::ec::{self, EcGroup};
+use super::inner;
+use super::json::{self, JsonValue};
+use super::vrf::{self, VrfStandard, VRF_PROOF_LEN, VRF_PUBLIC_KEY_LEN};
+use super::{
+    application_address, AcctParamsField, Address, AppParamsField, AssetHoldingField,
+    AssetParamsField, Avm, AvmData, AvmVersion, BlockField, GlobalField, RunMode, StateSchema,
+    TxType, TxnField, BOX_REFERENCE_QUOTA, FALSE, LABEL_BYTES, LABEL_UINT64, MAX_APP_KEY_LEN,
+    MAX_APP_KEY_VALUE_LEN, MAX_BOX_NAME_LEN, MAX_BOX_SIZE, MAX_BYTES_LEN, MAX_LOG_CALLS,
+    MAX_LOG_SIZE, TRUE,
+};
 
 type OpcodeEvalFunc = fn(&mut Avm) -> Result<(), AvmError>;
 
 pub struct OpSpec {
     pub opcode: u8,
+    #[allow(dead_code)]
     pub name: &'static str,
     pub cost: u64,
     pub eval: OpcodeEvalFunc,
     pub version: AvmVersion,
 }
 
-pub const OP_SPECS: [OpSpec; 62] = [
+pub const OP_SPECS: [OpSpec; 150] = [
     OpSpec {
         opcode: 0x00,
         name: "err",
@@ -275,6 +286,62 @@ pub const OP_SPECS: [OpSpec; 62] = [
         cost: 1,
         eval: op_bytec_3,
     },
+    OpSpec {
+        opcode: 0x2c,
+        name: "arg",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_arg,
+    },
+    OpSpec {
+        opcode: 0x2d,
+        name: "arg_0",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_arg_0,
+    },
+    OpSpec {
+        opcode: 0x2e,
+        name: "arg_1",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_arg_1,
+    },
+    OpSpec {
+        opcode: 0x2f,
+        name: "arg_2",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_arg_2,
+    },
+    OpSpec {
+        opcode: 0x30,
+        name: "arg_3",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_arg_3,
+    },
+    OpSpec {
+        opcode: 0x31,
+        name: "txn",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_txn,
+    },
+    OpSpec {
+        opcode: 0x32,
+        name: "global",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_global,
+    },
+    OpSpec {
+        opcode: 0x33,
+        name: "gtxn",
+        version: AvmVersion::V1,
+        cost: 1,
+        eval: op_gtxn,
+    },
     OpSpec {
         opcode: 0x34,
         name: "load",
@@ -289,6 +356,62 @@ pub const OP_SPECS: [OpSpec; 62] = [
         cost: 1,
         eval: op_store,
     },
+    OpSpec {
+        opcode: 0x36,
+        name: "txna",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_txna,
+    },
+    OpSpec {
+        opcode: 0x37,
+        name: "gtxna",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_gtxna,
+    },
+    OpSpec {
+        opcode: 0x38,
+        name: "gtxns",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_gtxns,
+    },
+    OpSpec {
+        opcode: 0x39,
+        name: "gtxnsa",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_gtxnsa,
+    },
+    OpSpec {
+        opcode: 0x3a,
+        name: "gload",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_gload,
+    },
+    OpSpec {
+        opcode: 0x3b,
+        name: "gloads",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_gloads,
+    },
+    OpSpec {
+        opcode: 0x3c,
+        name: "gaid",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_gaid,
+    },
+    OpSpec {
+        opcode: 0x3d,
+        name: "gaids",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_gaids,
+    },
     OpSpec {
         opcode: 0x3e,
         name: "loads",
@@ -436,6 +559,202 @@ pub const OP_SPECS: [OpSpec; 62] = [
         cost: 1,
         eval: op_substring3,
     },
+    OpSpec {
+        opcode: 0x53,
+        name: "getbit",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_getbit,
+    },
+    OpSpec {
+        opcode: 0x54,
+        name: "setbit",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_setbit,
+    },
+    OpSpec {
+        opcode: 0x55,
+        name: "getbyte",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_getbyte,
+    },
+    OpSpec {
+        opcode: 0x56,
+        name: "setbyte",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_setbyte,
+    },
+    OpSpec {
+        opcode: 0x57,
+        name: "extract",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_extract,
+    },
+    OpSpec {
+        opcode: 0x58,
+        name: "extract3",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_extract3,
+    },
+    OpSpec {
+        opcode: 0x59,
+        name: "extract_uint16",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_extract_uint16,
+    },
+    OpSpec {
+        opcode: 0x5a,
+        name: "extract_uint32",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_extract_uint32,
+    },
+    OpSpec {
+        opcode: 0x5b,
+        name: "extract_uint64",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_extract_uint64,
+    },
+    OpSpec {
+        opcode: 0x5c,
+        name: "replace2",
+        version: AvmVersion::V7,
+        cost: 1,
+        eval: op_replace2,
+    },
+    OpSpec {
+        opcode: 0x5d,
+        name: "replace3",
+        version: AvmVersion::V7,
+        cost: 1,
+        eval: op_replace3,
+    },
+    OpSpec {
+        opcode: 0x5e,
+        name: "base64_decode",
+        version: AvmVersion::V7,
+        cost: 1,
+        eval: op_base64_decode,
+    },
+    OpSpec {
+        opcode: 0x5f,
+        name: "json_ref",
+        version: AvmVersion::V7,
+        cost: 25,
+        eval: op_json_ref,
+    },
+    OpSpec {
+        opcode: 0x60,
+        name: "balance",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_balance,
+    },
+    OpSpec {
+        opcode: 0x61,
+        name: "app_opted_in",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_opted_in,
+    },
+    OpSpec {
+        opcode: 0x62,
+        name: "app_local_get",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_local_get,
+    },
+    OpSpec {
+        opcode: 0x63,
+        name: "app_local_get_ex",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_local_get_ex,
+    },
+    OpSpec {
+        opcode: 0x64,
+        name: "app_global_get",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_global_get,
+    },
+    OpSpec {
+        opcode: 0x65,
+        name: "app_global_get_ex",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_global_get_ex,
+    },
+    OpSpec {
+        opcode: 0x66,
+        name: "app_local_put",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_local_put,
+    },
+    OpSpec {
+        opcode: 0x67,
+        name: "app_global_put",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_global_put,
+    },
+    OpSpec {
+        opcode: 0x68,
+        name: "app_local_del",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_local_del,
+    },
+    OpSpec {
+        opcode: 0x69,
+        name: "app_global_del",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_app_global_del,
+    },
+    OpSpec {
+        opcode: 0x70,
+        name: "asset_holding_get",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_asset_holding_get,
+    },
+    OpSpec {
+        opcode: 0x71,
+        name: "asset_params_get",
+        version: AvmVersion::V2,
+        cost: 1,
+        eval: op_asset_params_get,
+    },
+    OpSpec {
+        opcode: 0x72,
+        name: "app_params_get",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_app_params_get,
+    },
+    OpSpec {
+        opcode: 0x73,
+        name: "acct_params_get",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_acct_params_get,
+    },
+    OpSpec {
+        opcode: 0x78,
+        name: "min_balance",
+        version: AvmVersion::V3,
+        cost: 1,
+        eval: op_min_balance,
+    },
     OpSpec {
         opcode: 0x80,
         name: "pushbytes",
@@ -450,91 +769,401 @@ pub const OP_SPECS: [OpSpec; 62] = [
         cost: 1,
         eval: op_pushint,
     },
-];
-
-fn op_err(_avm: &mut Avm) -> Result<(), AvmError> {
-    Err(AvmError::ErrOpCode)
-}
-
-fn op_plus(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    match lhs.checked_add(rhs) {
-        Some(res) => {
-            avm.data_stack.push(res.into());
-            Ok(())
-        }
-        None => Err(AvmError::IntegerOverflow),
-    }
-}
-
-fn op_minus(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    match lhs.checked_sub(rhs) {
-        Some(res) => {
-            avm.data_stack.push(res.into());
-            Ok(())
-        }
-        None => Err(AvmError::IntegerUnderflow),
-    }
-}
-
-fn op_div(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    match lhs.checked_div(rhs) {
-        Some(res) => {
-            avm.data_stack.push(res.into());
-            Ok(())
-        }
-        None => Err(AvmError::DivisionByZero),
-    }
-}
-
-fn op_mul(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    match lhs.checked_mul(rhs) {
-        Some(res) => {
-            avm.data_stack.push(res.into());
-            Ok(())
-        }
-        None => Err(AvmError::IntegerOverflow),
-    }
-}
-
-fn op_lt(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    avm.data_stack.push((lhs < rhs).into());
-    Ok(())
-}
-
-fn op_gt(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    avm.data_stack.push((lhs > rhs).into());
-    Ok(())
-}
-
-fn op_leq(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    avm.data_stack.push((lhs <= rhs).into());
-    Ok(())
-}
-
-fn op_geq(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
-    avm.data_stack.push((lhs >= rhs).into());
-    Ok(())
-}
-
-fn op_and(avm: &mut Avm) -> Result<(), AvmError> {
-    let rhs = avm.pop_uint64()?;
-    let lhs = avm.pop_uint64()?;
+    OpSpec {
+        opcode: 0x82,
+        name: "pushbytess",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_pushbytess,
+    },
+    OpSpec {
+        opcode: 0x83,
+        name: "pushints",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_pushints,
+    },
+    OpSpec {
+        opcode: 0x8d,
+        name: "switch",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_switch,
+    },
+    OpSpec {
+        opcode: 0x8e,
+        name: "match",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_match,
+    },
+    OpSpec {
+        opcode: 0x90,
+        name: "shl",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_shl,
+    },
+    OpSpec {
+        opcode: 0x91,
+        name: "shr",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_shr,
+    },
+    OpSpec {
+        opcode: 0x92,
+        name: "sqrt",
+        version: AvmVersion::V4,
+        cost: 4,
+        eval: op_sqrt,
+    },
+    OpSpec {
+        opcode: 0x93,
+        name: "bitlen",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_bitlen,
+    },
+    OpSpec {
+        opcode: 0x94,
+        name: "exp",
+        version: AvmVersion::V4,
+        cost: 1,
+        eval: op_exp,
+    },
+    OpSpec {
+        opcode: 0x95,
+        name: "expw",
+        version: AvmVersion::V4,
+        cost: 10,
+        eval: op_expw,
+    },
+    OpSpec {
+        opcode: 0x97,
+        name: "divw",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_divw,
+    },
+    OpSpec {
+        opcode: 0xb0,
+        name: "log",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_log,
+    },
+    OpSpec {
+        opcode: 0xb1,
+        name: "itxn_begin",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_itxn_begin,
+    },
+    OpSpec {
+        opcode: 0xb2,
+        name: "itxn_field",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_itxn_field,
+    },
+    OpSpec {
+        opcode: 0xb3,
+        name: "itxn_submit",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_itxn_submit,
+    },
+    OpSpec {
+        opcode: 0xb4,
+        name: "itxn",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_itxn,
+    },
+    OpSpec {
+        opcode: 0xb5,
+        name: "itxna",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_itxna,
+    },
+    OpSpec {
+        opcode: 0xb6,
+        name: "itxn_next",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_itxn_next,
+    },
+    OpSpec {
+        opcode: 0xb7,
+        name: "gitxn",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_gitxn,
+    },
+    OpSpec {
+        opcode: 0xb8,
+        name: "gitxna",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_gitxna,
+    },
+    OpSpec {
+        opcode: 0xb9,
+        name: "box_create",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_create,
+    },
+    OpSpec {
+        opcode: 0xba,
+        name: "box_extract",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_extract,
+    },
+    OpSpec {
+        opcode: 0xbb,
+        name: "box_replace",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_replace,
+    },
+    OpSpec {
+        opcode: 0xbc,
+        name: "box_del",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_del,
+    },
+    OpSpec {
+        opcode: 0xbd,
+        name: "box_len",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_len,
+    },
+    OpSpec {
+        opcode: 0xbe,
+        name: "box_get",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_get,
+    },
+    OpSpec {
+        opcode: 0xbf,
+        name: "box_put",
+        version: AvmVersion::V8,
+        cost: 1,
+        eval: op_box_put,
+    },
+    OpSpec {
+        opcode: 0xc0,
+        name: "txnas",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_txnas,
+    },
+    OpSpec {
+        opcode: 0xc1,
+        name: "gtxnas",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_gtxnas,
+    },
+    OpSpec {
+        opcode: 0xc2,
+        name: "args",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_args,
+    },
+    OpSpec {
+        opcode: 0xc3,
+        name: "gtxnsas",
+        version: AvmVersion::V5,
+        cost: 1,
+        eval: op_gtxnsas,
+    },
+    OpSpec {
+        opcode: 0xc4,
+        name: "gloadss",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_gloadss,
+    },
+    OpSpec {
+        opcode: 0xc5,
+        name: "itxnas",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_itxnas,
+    },
+    OpSpec {
+        opcode: 0xc6,
+        name: "gitxnas",
+        version: AvmVersion::V6,
+        cost: 1,
+        eval: op_gitxnas,
+    },
+    OpSpec {
+        opcode: 0xd0,
+        name: "vrf_verify",
+        version: AvmVersion::V7,
+        cost: 5700,
+        eval: op_vrf_verify,
+    },
+    OpSpec {
+        opcode: 0xd1,
+        name: "block",
+        version: AvmVersion::V7,
+        cost: 1,
+        eval: op_block,
+    },
+    OpSpec {
+        opcode: 0xd2,
+        name: "box_splice",
+        version: AvmVersion::V10,
+        cost: 1,
+        eval: op_box_splice,
+    },
+    OpSpec {
+        opcode: 0xd3,
+        name: "box_resize",
+        version: AvmVersion::V10,
+        cost: 1,
+        eval: op_box_resize,
+    },
+    // the elliptic curve opcodes cost more or less depending on their
+    // group and arguments, so their cost is added when they are evaluated
+    OpSpec {
+        opcode: 0xe0,
+        name: "ec_add",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_add,
+    },
+    OpSpec {
+        opcode: 0xe1,
+        name: "ec_scalar_mul",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_scalar_mul,
+    },
+    OpSpec {
+        opcode: 0xe2,
+        name: "ec_pairing_check",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_pairing_check,
+    },
+    OpSpec {
+        opcode: 0xe3,
+        name: "ec_multi_scalar_mul",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_multi_scalar_mul,
+    },
+    OpSpec {
+        opcode: 0xe4,
+        name: "ec_subgroup_check",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_subgroup_check,
+    },
+    OpSpec {
+        opcode: 0xe5,
+        name: "ec_map_to",
+        version: AvmVersion::V10,
+        cost: 0, // dynamic
+        eval: op_ec_map_to,
+    },
+];
+
+fn op_err(_avm: &mut Avm) -> Result<(), AvmError> {
+    Err(AvmError::ErrOpCode)
+}
+
+fn op_plus(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    match lhs.checked_add(rhs) {
+        Some(res) => {
+            avm.data_stack.push(res.into());
+            Ok(())
+        }
+        None => Err(AvmError::IntegerOverflow),
+    }
+}
+
+fn op_minus(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    match lhs.checked_sub(rhs) {
+        Some(res) => {
+            avm.data_stack.push(res.into());
+            Ok(())
+        }
+        None => Err(AvmError::IntegerUnderflow),
+    }
+}
+
+fn op_div(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    match lhs.checked_div(rhs) {
+        Some(res) => {
+            avm.data_stack.push(res.into());
+            Ok(())
+        }
+        None => Err(AvmError::DivisionByZero),
+    }
+}
+
+fn op_mul(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    match lhs.checked_mul(rhs) {
+        Some(res) => {
+            avm.data_stack.push(res.into());
+            Ok(())
+        }
+        None => Err(AvmError::IntegerOverflow),
+    }
+}
+
+fn op_lt(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    avm.data_stack.push((lhs < rhs).into());
+    Ok(())
+}
+
+fn op_gt(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    avm.data_stack.push((lhs > rhs).into());
+    Ok(())
+}
+
+fn op_leq(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    avm.data_stack.push((lhs <= rhs).into());
+    Ok(())
+}
+
+fn op_geq(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
+    avm.data_stack.push((lhs >= rhs).into());
+    Ok(())
+}
+
+fn op_and(avm: &mut Avm) -> Result<(), AvmError> {
+    let rhs = avm.pop_uint64()?;
+    let lhs = avm.pop_uint64()?;
     avm.data_stack.push((lhs != 0 && rhs != 0).into());
     Ok(())
 }
@@ -674,7 +1303,7 @@ fn op_divmodw(avm: &mut Avm) -> Result<(), AvmError> {
     let ab = encoding::u64_tuple_to_u128(a, b);
     let cd = encoding::u64_tuple_to_u128(c, d);
     // compute result (division, modulo)
-    let wx = ab / cd;
+    let wx = ab.checked_div(cd).ok_or(AvmError::DivisionByZero)?;
     let yz = ab % cd;
     // split u128s into u64s
     let (w, x) = encoding::u128_to_u64_tuple(wx);
@@ -769,28 +1398,210 @@ fn op_bytec_3(avm: &mut Avm) -> Result<(), AvmError> {
     op_bytec_n(avm, 3)
 }
 
-fn op_load(avm: &mut Avm) -> Result<(), AvmError> {
-    // we do not need range checking since scratch has length 256
-    // and is indexed by a single byte
-    let pos = avm.read_byte()? as usize;
-    avm.data_stack.push(avm.scratch[pos].clone());
-    Ok(())
+fn op_arg_n(avm: &mut Avm, idx: usize) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Signature)?;
+    match avm.args.get(idx) {
+        Some(arg) => {
+            avm.data_stack.push(AvmData::Bytes(arg.clone()));
+            Ok(())
+        }
+        None => Err(AvmError::ArgOutOfRange(idx, avm.args.len())),
+    }
 }
 
-fn op_store(avm: &mut Avm) -> Result<(), AvmError> {
-    // we do not need range checking since scratch has length 256
-    // and is indexed by a single byte
-    let pos = avm.read_byte()? as usize;
-    avm.scratch[pos] = avm.pop_any()?;
-    Ok(())
+fn op_arg(avm: &mut Avm) -> Result<(), AvmError> {
+    let idx = avm.read_byte()?;
+    op_arg_n(avm, idx as usize)
 }
 
-fn op_loads(avm: &mut Avm) -> Result<(), AvmError> {
-    let pos = avm.pop_uint64()? as usize;
-    if pos >= avm.scratch.len() {
-        return Err(AvmError::ScratchAccessOutOfBounds(pos));
-    }
-    avm.data_stack.push(avm.scratch[pos].clone());
+fn op_arg_0(avm: &mut Avm) -> Result<(), AvmError> {
+    op_arg_n(avm, 0)
+}
+
+fn op_arg_1(avm: &mut Avm) -> Result<(), AvmError> {
+    op_arg_n(avm, 1)
+}
+
+fn op_arg_2(avm: &mut Avm) -> Result<(), AvmError> {
+    op_arg_n(avm, 2)
+}
+
+fn op_arg_3(avm: &mut Avm) -> Result<(), AvmError> {
+    op_arg_n(avm, 3)
+}
+
+fn read_txn_field(avm: &mut Avm) -> Result<TxnField, AvmError> {
+    let field = avm.read_byte()?;
+    TxnField::parse(field, &avm.version)
+}
+
+// pushes the field of the transaction at position group_index in the
+// group. array_idx selects the element of array fields.
+fn push_txn_field(
+    avm: &mut Avm,
+    group_index: usize,
+    field: TxnField,
+    array_idx: Option<usize>,
+) -> Result<(), AvmError> {
+    let txn = avm
+        .txn_group
+        .get(group_index)
+        .ok_or(AvmError::GroupIndexOutOfRange(
+            group_index,
+            avm.txn_group.len(),
+        ))?;
+    // the current and all subsequent transactions have not been evaluated yet
+    if field.is_effect() && group_index >= avm.group_index {
+        return Err(AvmError::TxnEffectsNotAvailable);
+    }
+    let value = match array_idx {
+        Some(idx) => txn.array_field(field, idx)?,
+        None => txn.field(field, group_index)?,
+    };
+    avm.data_stack.push(value);
+    Ok(())
+}
+
+fn op_txn(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    push_txn_field(avm, avm.group_index, field, None)
+}
+
+fn op_global(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = avm.read_byte()?;
+    let field = GlobalField::parse(field, &avm.version)?;
+    if let Some(mode) = field.mode() {
+        avm.ensure_mode(mode)?;
+    }
+    let value = avm.globals.field(
+        field,
+        avm.txn_group.len(),
+        avm.txn().current_application_id(),
+        avm.budget.saturating_sub(avm.cost),
+    );
+    avm.data_stack.push(value);
+    Ok(())
+}
+
+fn op_gtxn(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    push_txn_field(avm, group_index, field, None)
+}
+
+fn op_load(avm: &mut Avm) -> Result<(), AvmError> {
+    // we do not need range checking since scratch has length 256
+    // and is indexed by a single byte
+    let pos = avm.read_byte()? as usize;
+    avm.data_stack.push(avm.scratch[pos].clone());
+    Ok(())
+}
+
+fn op_store(avm: &mut Avm) -> Result<(), AvmError> {
+    // we do not need range checking since scratch has length 256
+    // and is indexed by a single byte
+    let pos = avm.read_byte()? as usize;
+    avm.scratch[pos] = avm.pop_any()?;
+    Ok(())
+}
+
+fn op_txna(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.read_byte()? as usize;
+    push_txn_field(avm, avm.group_index, field, Some(idx))
+}
+
+fn op_gtxna(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    let idx = avm.read_byte()? as usize;
+    push_txn_field(avm, group_index, field, Some(idx))
+}
+
+fn op_gtxns(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let group_index = avm.pop_uint64()? as usize;
+    push_txn_field(avm, group_index, field, None)
+}
+
+fn op_gtxnsa(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.read_byte()? as usize;
+    let group_index = avm.pop_uint64()? as usize;
+    push_txn_field(avm, group_index, field, Some(idx))
+}
+
+fn push_group_scratch(avm: &mut Avm, group_index: usize, pos: usize) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let txn = avm
+        .txn_group
+        .get(group_index)
+        .ok_or(AvmError::GroupIndexOutOfRange(
+            group_index,
+            avm.txn_group.len(),
+        ))?;
+    // only earlier app calls have a scratch space to read from
+    if txn.tx_type != TxType::ApplicationCall || group_index >= avm.group_index {
+        return Err(AvmError::ScratchNotAvailable(group_index));
+    }
+    let value = avm
+        .group_scratch
+        .get(group_index)
+        .and_then(|scratch| scratch.get(pos))
+        .ok_or(AvmError::ScratchNotAvailable(group_index))?;
+    avm.data_stack.push(value.clone());
+    Ok(())
+}
+
+fn op_gload(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let pos = avm.read_byte()? as usize;
+    push_group_scratch(avm, group_index, pos)
+}
+
+fn op_gloads(avm: &mut Avm) -> Result<(), AvmError> {
+    let pos = avm.read_byte()? as usize;
+    let group_index = avm.pop_uint64()? as usize;
+    push_group_scratch(avm, group_index, pos)
+}
+
+fn push_created_id(avm: &mut Avm, group_index: usize) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let txn = avm
+        .txn_group
+        .get(group_index)
+        .ok_or(AvmError::GroupIndexOutOfRange(
+            group_index,
+            avm.txn_group.len(),
+        ))?;
+    if group_index >= avm.group_index {
+        return Err(AvmError::TxnEffectsNotAvailable);
+    }
+    let id = match txn.tx_type {
+        TxType::ApplicationCall if txn.application_id == 0 => txn.created_application_id,
+        TxType::AssetConfig if txn.config_asset == 0 => txn.created_asset_id,
+        _ => return Err(AvmError::NothingCreated(group_index)),
+    };
+    avm.data_stack.push(id.into());
+    Ok(())
+}
+
+fn op_gaid(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    push_created_id(avm, group_index)
+}
+
+fn op_gaids(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.pop_uint64()? as usize;
+    push_created_id(avm, group_index)
+}
+
+fn op_loads(avm: &mut Avm) -> Result<(), AvmError> {
+    let pos = avm.pop_uint64()? as usize;
+    if pos >= avm.scratch.len() {
+        return Err(AvmError::ScratchAccessOutOfBounds(pos));
+    }
+    avm.data_stack.push(avm.scratch[pos].clone());
     Ok(())
 }
 
@@ -973,7 +1784,7 @@ fn op_uncover(avm: &mut Avm) -> Result<(), AvmError> {
 fn op_concat(avm: &mut Avm) -> Result<(), AvmError> {
     let rhs = avm.pop_bytes()?;
     let lhs = avm.pop_bytes()?;
-    if lhs.len() + rhs.len() > 4096 {
+    if lhs.len() + rhs.len() > MAX_BYTES_LEN {
         Err(AvmError::BytesTooLong)
     } else {
         avm.data_stack.push([lhs, rhs].concat().into());
@@ -996,1683 +1807,7000 @@ fn op_substring3(avm: &mut Avm) -> Result<(), AvmError> {
 }
 
 fn substring(avm: &mut Avm, bytes: &[u8], start: usize, end: usize) -> Result<(), AvmError> {
+    let substring = checked_substring(bytes, start, end)?.to_vec();
+    avm.data_stack.push(AvmData::Bytes(substring));
+    Ok(())
+}
+
+fn checked_substring(bytes: &[u8], start: usize, end: usize) -> Result<&[u8], AvmError> {
     if end < start || end > bytes.len() {
         Err(AvmError::InvalidSubstringAccess(start, end, bytes.len()))
     } else {
-        let substring = bytes[start..end].to_vec();
-        avm.data_stack.push(AvmData::Bytes(substring));
-        Ok(())
+        Ok(&bytes[start..end])
     }
 }
 
-fn op_pushbytes(avm: &mut Avm) -> Result<(), AvmError> {
-    let val: VarBytes = avm.read_varbytes()?;
-    avm.data_stack.push(val.into());
+// for uint64s bit 0 is the least-significant bit, whereas for byte
+// slices bit 0 is the most-significant (leftmost) bit of the first byte
+fn op_getbit(avm: &mut Avm) -> Result<(), AvmError> {
+    let idx = avm.pop_uint64()?;
+    let bit = match avm.pop_any()? {
+        AvmData::Uint64(value) => {
+            if idx >= 64 {
+                return Err(AvmError::BitIndexOutOfRange(idx, 64));
+            }
+            (value >> idx) & 1
+        }
+        AvmData::Bytes(bytes) => {
+            let nbits = bytes.len() as u64 * 8;
+            if idx >= nbits {
+                return Err(AvmError::BitIndexOutOfRange(idx, nbits));
+            }
+            let byte = bytes[(idx / 8) as usize];
+            ((byte >> (7 - idx % 8)) & 1) as u64
+        }
+    };
+    avm.data_stack.push(bit.into());
     Ok(())
 }
 
-fn op_pushint(avm: &mut Avm) -> Result<(), AvmError> {
-    let val: VarUint64 = avm.read_varint()?;
-    avm.data_stack.push(val.into());
+fn op_setbit(avm: &mut Avm) -> Result<(), AvmError> {
+    let bit = avm.pop_uint64()?;
+    let idx = avm.pop_uint64()?;
+    if bit > 1 {
+        return Err(AvmError::InvalidBitValue(bit));
+    }
+    let res = match avm.pop_any()? {
+        AvmData::Uint64(value) => {
+            if idx >= 64 {
+                return Err(AvmError::BitIndexOutOfRange(idx, 64));
+            }
+            let mask = 1 << idx;
+            AvmData::Uint64(if bit == 1 {
+                value | mask
+            } else {
+                value & !mask
+            })
+        }
+        AvmData::Bytes(mut bytes) => {
+            let nbits = bytes.len() as u64 * 8;
+            if idx >= nbits {
+                return Err(AvmError::BitIndexOutOfRange(idx, nbits));
+            }
+            let mask = 0x80 >> (idx % 8);
+            let byte = &mut bytes[(idx / 8) as usize];
+            *byte = if bit == 1 {
+                *byte | mask
+            } else {
+                *byte & !mask
+            };
+            AvmData::Bytes(bytes)
+        }
+    };
+    avm.data_stack.push(res);
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use std::vec;
+fn op_getbyte(avm: &mut Avm) -> Result<(), AvmError> {
+    let idx = avm.pop_uint64()?;
+    let bytes = avm.pop_bytes()?;
+    match bytes.get(idx as usize) {
+        Some(&byte) => {
+            avm.data_stack.push((byte as u64).into());
+            Ok(())
+        }
+        None => Err(AvmError::ByteIndexOutOfRange(idx, bytes.len())),
+    }
+}
 
-    use crate::avm::execute_program;
+fn op_setbyte(avm: &mut Avm) -> Result<(), AvmError> {
+    let value = avm.pop_uint64()?;
+    let idx = avm.pop_uint64()?;
+    let mut bytes = avm.pop_bytes()?;
+    if value > u8::MAX as u64 {
+        return Err(AvmError::InvalidByteValue(value));
+    }
+    let len = bytes.len();
+    match bytes.get_mut(idx as usize) {
+        Some(byte) => {
+            *byte = value as u8;
+            avm.data_stack.push(bytes.into());
+            Ok(())
+        }
+        None => Err(AvmError::ByteIndexOutOfRange(idx, len)),
+    }
+}
 
-    use super::*;
+fn op_extract(avm: &mut Avm) -> Result<(), AvmError> {
+    let bytes = avm.pop_bytes()?;
+    let start = avm.read_byte()? as usize;
+    let length = avm.read_byte()? as usize;
+    // a length of 0 extracts everything up to the end of the byte slice
+    let end = if length == 0 {
+        bytes.len()
+    } else {
+        start + length
+    };
+    substring(avm, &bytes, start, end)
+}
 
-    #[test]
-    fn test_plus() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // +
-        let program = vec![0x09, 0x81, 0x01, 0x81, 0x02, 0x08];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+fn op_extract3(avm: &mut Avm) -> Result<(), AvmError> {
+    let length = avm.pop_uint64()? as usize;
+    let start = avm.pop_uint64()? as usize;
+    let bytes = avm.pop_bytes()?;
+    substring(avm, &bytes, start, start.saturating_add(length))
+}
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        Ok(())
-    }
+fn extract_uint<const N: usize>(avm: &mut Avm) -> Result<(), AvmError> {
+    let start = avm.pop_uint64()? as usize;
+    let bytes = avm.pop_bytes()?;
+    let substring = checked_substring(&bytes, start, start.saturating_add(N))?;
+    let mut buffer = [0u8; 8];
+    buffer[(8 - N)..].copy_from_slice(substring);
+    avm.data_stack.push(u64::from_be_bytes(buffer).into());
+    Ok(())
+}
 
-    #[test]
-    fn test_plus_overflow() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 2**64 - 1
-        // pushint 1
-        // +
-        let program = [
-            vec![0x09], // #pragma version 9
-            vec![
-                0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01,
-            ], // pushint 2**64 - 1
-            vec![0x81, 0x01], // pushint 1
-            vec![0x08], // +
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
+fn op_extract_uint16(avm: &mut Avm) -> Result<(), AvmError> {
+    extract_uint::<2>(avm)
+}
 
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::IntegerOverflow, err);
+fn op_extract_uint32(avm: &mut Avm) -> Result<(), AvmError> {
+    extract_uint::<4>(avm)
+}
 
-        Ok(())
-    }
+fn op_extract_uint64(avm: &mut Avm) -> Result<(), AvmError> {
+    extract_uint::<8>(avm)
+}
 
-    #[test]
-    fn test_minus() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 5
-        // pushint 3
-        // -
-        let program = vec![0x09, 0x81, 0x05, 0x81, 0x03, 0x09];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+fn op_replace2(avm: &mut Avm) -> Result<(), AvmError> {
+    let start = avm.read_byte()? as usize;
+    let replacement = avm.pop_bytes()?;
+    let bytes = avm.pop_bytes()?;
+    replace(avm, bytes, start, &replacement)
+}
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        Ok(())
+fn op_replace3(avm: &mut Avm) -> Result<(), AvmError> {
+    let replacement = avm.pop_bytes()?;
+    let start = avm.pop_uint64()? as usize;
+    let bytes = avm.pop_bytes()?;
+    replace(avm, bytes, start, &replacement)
+}
+
+// the popped byte slice is owned by us, hence we can overwrite it
+// in place without affecting any other value on the stack
+fn replace(
+    avm: &mut Avm,
+    mut bytes: Vec<u8>,
+    start: usize,
+    replacement: &[u8],
+) -> Result<(), AvmError> {
+    match start.checked_add(replacement.len()) {
+        Some(end) if end <= bytes.len() => {
+            bytes[start..end].copy_from_slice(replacement);
+            avm.data_stack.push(bytes.into());
+            Ok(())
+        }
+        _ => Err(AvmError::InvalidReplacement(
+            start,
+            replacement.len(),
+            bytes.len(),
+        )),
     }
+}
 
-    #[test]
-    fn test_minus_underflow() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 5
-        // pushint 7
-        // -
-        let program = vec![0x09, 0x81, 0x05, 0x81, 0x07, 0x09];
-        let mut avm = Avm::for_program(&program)?;
+// cost of opcodes whose cost depends on the length of their input
+fn cost_by_length(len: usize, cost_per_chunk: u64, chunk_size: usize) -> u64 {
+    len.div_ceil(chunk_size) as u64 * cost_per_chunk
+}
 
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::IntegerUnderflow, err);
+fn op_base64_decode(avm: &mut Avm) -> Result<(), AvmError> {
+    let alphabet = match avm.read_byte()? {
+        0 => Base64Alphabet::Url,
+        1 => Base64Alphabet::Std,
+        field => return Err(AvmError::UnknownField("base64 encoding", field)),
+    };
+    let encoded = avm.pop_bytes()?;
+    avm.cost += cost_by_length(encoded.len(), 1, 16);
+    let decoded = encoding::base64_decode(&encoded, alphabet)?;
+    avm.data_stack.push(decoded.into());
+    Ok(())
+}
 
-        Ok(())
+fn op_json_ref(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = avm.read_byte()?;
+    let key = avm.pop_bytes()?;
+    let text = avm.pop_bytes()?;
+    avm.cost += cost_by_length(text.len(), 2, 7);
+    let object = json::parse_object(&text)?;
+    let key_label = || String::from_utf8_lossy(&key).into_owned();
+    let json = object
+        .get(&key)
+        .ok_or_else(|| AvmError::JsonKeyNotFound(key_label()))?;
+    let value = match (field, &json.value) {
+        // JSONString
+        (0, JsonValue::String(value)) => AvmData::Bytes(value.clone()),
+        (0, _) => return Err(AvmError::InvalidJsonValue(key_label(), "string")),
+        // JSONUint64, which must consist of digits only (no sign, fraction or exponent)
+        (1, JsonValue::Number(number)) if number.iter().all(u8::is_ascii_digit) => {
+            std::str::from_utf8(number)
+                .ok()
+                .and_then(|number| number.parse::<u64>().ok())
+                .ok_or_else(|| AvmError::InvalidJsonValue(key_label(), "uint64"))?
+                .into()
+        }
+        (1, _) => return Err(AvmError::InvalidJsonValue(key_label(), "uint64")),
+        // JSONObject
+        (2, JsonValue::Object(_)) => AvmData::Bytes(json.raw.to_vec()),
+        (2, _) => return Err(AvmError::InvalidJsonValue(key_label(), "object")),
+        _ => return Err(AvmError::UnknownField("json_ref type", field)),
+    };
+    avm.data_stack.push(value);
+    Ok(())
+}
+
+// resolves a reference to an application, which is an index into the
+// Applications array (0 being the current application) or, starting
+// with v4, the id of the current or one of the foreign applications
+fn resolve_app(avm: &Avm, reference: u64) -> Result<u64, AvmError> {
+    let txn = avm.txn();
+    if reference == 0 {
+        return Ok(txn.current_application_id());
     }
+    if avm.version >= AvmVersion::V4
+        && (reference == txn.current_application_id() || txn.applications.contains(&reference))
+    {
+        return Ok(reference);
+    }
+    usize::try_from(reference - 1)
+        .ok()
+        .and_then(|idx| txn.applications.get(idx))
+        .copied()
+        .ok_or(AvmError::AppNotAvailable(reference))
+}
 
-    #[test]
-    fn test_div() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
-        // pushint 2
-        // /
-        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x02, 0x0a];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+// resolves a reference to an account, which is an index into the
+// Accounts array (0 being the sender) or, starting with v4, the address
+// of the sender or one of the accounts. Starting with v7, the addresses
+// of the current and the foreign applications are available as well.
+fn resolve_account(avm: &Avm, reference: AvmData) -> Result<Address, AvmError> {
+    let txn = avm.txn();
+    let address = match reference {
+        AvmData::Uint64(0) => return Ok(txn.sender),
+        AvmData::Uint64(idx) => {
+            return usize::try_from(idx - 1)
+                .ok()
+                .and_then(|idx| txn.accounts.get(idx))
+                .copied()
+                .ok_or(AvmError::AccountNotAvailable)
+        }
+        AvmData::Bytes(_) if avm.version < AvmVersion::V4 => {
+            return Err(AvmError::IncompatibleTypes(LABEL_BYTES, LABEL_UINT64))
+        }
+        AvmData::Bytes(bytes) => {
+            Address::try_from(bytes).map_err(|_| AvmError::AccountNotAvailable)?
+        }
+    };
+    if address == txn.sender || txn.accounts.contains(&address) {
+        return Ok(address);
+    }
+    if avm.version >= AvmVersion::V7
+        && std::iter::once(&txn.current_application_id())
+            .chain(&txn.applications)
+            .any(|&app_id| application_address(app_id) == address)
+    {
+        return Ok(address);
+    }
+    Err(AvmError::AccountNotAvailable)
+}
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
-        Ok(())
+fn check_state_value(key: &[u8], value: &AvmData) -> Result<(), AvmError> {
+    if key.len() > MAX_APP_KEY_LEN {
+        return Err(AvmError::StateKeyTooLong(key.len()));
     }
+    let len = match value {
+        AvmData::Bytes(bytes) => key.len() + bytes.len(),
+        AvmData::Uint64(_) => key.len(),
+    };
+    if len > MAX_APP_KEY_VALUE_LEN {
+        return Err(AvmError::StateValueTooLong(len));
+    }
+    Ok(())
+}
 
-    #[test]
-    fn test_div_by_zero() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
-        // pushint 0
-        // /
-        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x00, 0x0a];
-        let mut avm = Avm::for_program(&program)?;
+// checks that replacing the old value of a key by the new one
+// keeps the number of values within the schema
+fn check_state_schema(
+    schema: StateSchema,
+    mut usage: StateSchema,
+    old: Option<&AvmData>,
+    new: &AvmData,
+) -> Result<(), AvmError> {
+    match old {
+        Some(AvmData::Uint64(_)) => usage.num_uint -= 1,
+        Some(AvmData::Bytes(_)) => usage.num_byte_slice -= 1,
+        None => {}
+    }
+    match new {
+        AvmData::Uint64(_) => usage.num_uint += 1,
+        AvmData::Bytes(_) => usage.num_byte_slice += 1,
+    }
+    if usage.num_uint > schema.num_uint {
+        return Err(AvmError::StateSchemaExceeded(LABEL_UINT64, schema.num_uint));
+    }
+    if usage.num_byte_slice > schema.num_byte_slice {
+        return Err(AvmError::StateSchemaExceeded(
+            LABEL_BYTES,
+            schema.num_byte_slice,
+        ));
+    }
+    Ok(())
+}
 
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::DivisionByZero, err);
+fn op_balance(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let params = avm.ledger.borrow().account_params(&account);
+    avm.data_stack.push(params.balance.into());
+    Ok(())
+}
 
-        Ok(())
-    }
+fn op_app_opted_in(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let app_reference = avm.pop_uint64()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let app_id = resolve_app(avm, app_reference)?;
+    let opted_in = avm.ledger.borrow().app_opted_in(app_id, &account);
+    avm.data_stack.push(opted_in.into());
+    Ok(())
+}
 
-    #[test]
-    fn test_mul() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
-        // pushint 2
-        // *
-        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x2, 0x0b];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+fn op_app_local_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let app_id = avm.txn().current_application_id();
+    let value = avm.ledger.borrow().app_local_get(app_id, &account, &key);
+    avm.data_stack.push(value.unwrap_or(FALSE));
+    Ok(())
+}
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
-        Ok(())
-    }
+fn op_app_local_get_ex(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let app_reference = avm.pop_uint64()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let app_id = resolve_app(avm, app_reference)?;
+    let value = avm.ledger.borrow().app_local_get(app_id, &account, &key);
+    avm.data_stack.push(value.clone().unwrap_or(FALSE));
+    avm.data_stack.push(value.is_some().into());
+    Ok(())
+}
 
-    #[test]
-    fn test_mul_overflow() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
+fn op_app_global_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let app_id = avm.txn().current_application_id();
+    let value = avm.ledger.borrow().app_global_get(app_id, &key);
+    avm.data_stack.push(value.unwrap_or(FALSE));
+    Ok(())
+}
+
+fn op_app_global_get_ex(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let reference = avm.pop_uint64()?;
+    let app_id = resolve_app(avm, reference)?;
+    let value = avm.ledger.borrow().app_global_get(app_id, &key);
+    avm.data_stack.push(value.clone().unwrap_or(FALSE));
+    avm.data_stack.push(value.is_some().into());
+    Ok(())
+}
+
+fn op_app_local_put(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let value = avm.pop_any()?;
+    let key = avm.pop_bytes()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    check_state_value(&key, &value)?;
+    let app_id = avm.txn().current_application_id();
+    let mut ledger = avm.ledger.borrow_mut();
+    if !ledger.app_opted_in(app_id, &account) {
+        return Err(AvmError::NotOptedIn(app_id));
+    }
+    let old = ledger.app_local_get(app_id, &account, &key);
+    check_state_schema(
+        ledger.app_local_schema(app_id),
+        ledger.app_local_usage(app_id, &account),
+        old.as_ref(),
+        &value,
+    )?;
+    ledger.app_local_put(app_id, &account, &key, value);
+    Ok(())
+}
+
+fn op_app_global_put(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let value = avm.pop_any()?;
+    let key = avm.pop_bytes()?;
+    check_state_value(&key, &value)?;
+    let app_id = avm.txn().current_application_id();
+    let mut ledger = avm.ledger.borrow_mut();
+    let old = ledger.app_global_get(app_id, &key);
+    check_state_schema(
+        ledger.app_global_schema(app_id),
+        ledger.app_global_usage(app_id),
+        old.as_ref(),
+        &value,
+    )?;
+    ledger.app_global_put(app_id, &key, value);
+    Ok(())
+}
+
+fn op_app_local_del(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let app_id = avm.txn().current_application_id();
+    let mut ledger = avm.ledger.borrow_mut();
+    if !ledger.app_opted_in(app_id, &account) {
+        return Err(AvmError::NotOptedIn(app_id));
+    }
+    ledger.app_local_del(app_id, &account, &key);
+    Ok(())
+}
+
+fn op_app_global_del(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let key = avm.pop_bytes()?;
+    let app_id = avm.txn().current_application_id();
+    avm.ledger.borrow_mut().app_global_del(app_id, &key);
+    Ok(())
+}
+
+// resolves a reference to an asset, which is an index into the Assets
+// array or, starting with v4, the id of one of the foreign assets
+fn resolve_asset(avm: &Avm, reference: u64) -> Result<u64, AvmError> {
+    let assets = &avm.txn().assets;
+    if avm.version >= AvmVersion::V4 && assets.contains(&reference) {
+        return Ok(reference);
+    }
+    usize::try_from(reference)
+        .ok()
+        .and_then(|idx| assets.get(idx))
+        .copied()
+        .ok_or(AvmError::AssetNotAvailable(reference))
+}
+
+fn op_asset_holding_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let field = AssetHoldingField::parse(avm.read_byte()?)?;
+    let asset_reference = avm.pop_uint64()?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let asset_id = resolve_asset(avm, asset_reference)?;
+    let holding = avm.ledger.borrow().asset_holding(&account, asset_id);
+    let value = holding.map_or(FALSE, |holding| holding.field(field));
+    avm.data_stack.push(value);
+    avm.data_stack.push(holding.is_some().into());
+    Ok(())
+}
+
+fn op_asset_params_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let field = avm.read_byte()?;
+    let field = AssetParamsField::parse(field, &avm.version)?;
+    let asset_reference = avm.pop_uint64()?;
+    let asset_id = resolve_asset(avm, asset_reference)?;
+    let params = avm.ledger.borrow().asset_params(asset_id);
+    let value = params.as_ref().map_or(FALSE, |params| params.field(field));
+    avm.data_stack.push(value);
+    avm.data_stack.push(params.is_some().into());
+    Ok(())
+}
+
+fn op_app_params_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let field = AppParamsField::parse(avm.read_byte()?)?;
+    let app_reference = avm.pop_uint64()?;
+    let app_id = resolve_app(avm, app_reference)?;
+    let params = avm.ledger.borrow().app_params(app_id);
+    let value = params
+        .as_ref()
+        .map_or(FALSE, |params| params.field(field, app_id));
+    avm.data_stack.push(value);
+    avm.data_stack.push(params.is_some().into());
+    Ok(())
+}
+
+// accounts exist if they have a positive balance
+fn op_acct_params_get(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let field = avm.read_byte()?;
+    let field = AcctParamsField::parse(field, &avm.version)?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let params = avm.ledger.borrow().account_params(&account);
+    avm.data_stack
+        .push(params.field(field, avm.globals.min_balance));
+    avm.data_stack.push((params.balance > 0).into());
+    Ok(())
+}
+
+fn op_min_balance(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let account_reference = avm.pop_any()?;
+    let account = resolve_account(avm, account_reference)?;
+    let params = avm.ledger.borrow().account_params(&account);
+    avm.data_stack
+        .push(params.min_balance(avm.globals.min_balance).into());
+    Ok(())
+}
+
+fn op_pushbytes(avm: &mut Avm) -> Result<(), AvmError> {
+    let val: VarBytes = avm.read_varbytes()?;
+    avm.data_stack.push(val.into());
+    Ok(())
+}
+
+fn op_pushint(avm: &mut Avm) -> Result<(), AvmError> {
+    let val: VarUint64 = avm.read_varint()?;
+    avm.data_stack.push(val.into());
+    Ok(())
+}
+
+fn op_pushbytess(avm: &mut Avm) -> Result<(), AvmError> {
+    let nbytes = avm.read_varint()?;
+    for _ in 0..nbytes.value {
+        let val: VarBytes = avm.read_varbytes()?;
+        if val.value.len() > MAX_BYTES_LEN {
+            return Err(AvmError::BytesTooLong);
+        }
+        avm.data_stack.push(val.into());
+    }
+    Ok(())
+}
+
+fn op_pushints(avm: &mut Avm) -> Result<(), AvmError> {
+    let nintegers = avm.read_varint()?;
+    for _ in 0..nintegers.value {
+        let val: VarUint64 = avm.read_varint()?;
+        avm.data_stack.push(val.into());
+    }
+    Ok(())
+}
+
+fn read_labels(avm: &mut Avm) -> Result<Vec<i16>, AvmError> {
+    let nlabels = avm.read_byte()?;
+    (0..nlabels).map(|_| avm.read_i16()).collect()
+}
+
+fn op_switch(avm: &mut Avm) -> Result<(), AvmError> {
+    // the labels have to be read first since offsets are
+    // relative to the end of the switch instruction
+    let labels = read_labels(avm)?;
+    let idx = avm.pop_uint64()?;
+    match labels.get(idx as usize) {
+        Some(&offset) => branch_to_offset(avm, offset),
+        // fall through to the next instruction if idx is out of range
+        None => Ok(()),
+    }
+}
+
+fn op_match(avm: &mut Avm) -> Result<(), AvmError> {
+    let labels = read_labels(avm)?;
+    let value = avm.pop_any()?;
+    if labels.len() > avm.data_stack.len() {
+        return Err(AvmError::StackUnderflow);
+    }
+    let cases = avm
+        .data_stack
+        .split_off(avm.data_stack.len() - labels.len());
+    // values of a different type than the match value never match
+    match cases.iter().position(|case| *case == value) {
+        Some(idx) => branch_to_offset(avm, labels[idx]),
+        None => Ok(()),
+    }
+}
+
+fn op_shl(avm: &mut Avm) -> Result<(), AvmError> {
+    let b = avm.pop_uint64()?;
+    let a = avm.pop_uint64()?;
+    if b >= 64 {
+        return Err(AvmError::ShiftOutOfRange(b));
+    }
+    avm.data_stack.push((a << b).into());
+    Ok(())
+}
+
+fn op_shr(avm: &mut Avm) -> Result<(), AvmError> {
+    let b = avm.pop_uint64()?;
+    let a = avm.pop_uint64()?;
+    if b >= 64 {
+        return Err(AvmError::ShiftOutOfRange(b));
+    }
+    avm.data_stack.push((a >> b).into());
+    Ok(())
+}
+
+fn op_sqrt(avm: &mut Avm) -> Result<(), AvmError> {
+    let value = avm.pop_uint64()?;
+    avm.data_stack.push(value.isqrt().into());
+    Ok(())
+}
+
+fn op_bitlen(avm: &mut Avm) -> Result<(), AvmError> {
+    let nbits = match avm.pop_any()? {
+        AvmData::Uint64(value) => 64 - value.leading_zeros() as u64,
+        // byte slices are interpreted as big-endian unsigned integers
+        AvmData::Bytes(bytes) => match bytes.iter().position(|&byte| byte != 0) {
+            Some(idx) => {
+                let remaining_bytes = (bytes.len() - idx - 1) as u64;
+                remaining_bytes * 8 + (8 - bytes[idx].leading_zeros() as u64)
+            }
+            None => 0,
+        },
+    };
+    avm.data_stack.push(nbits.into());
+    Ok(())
+}
+
+fn op_exp(avm: &mut Avm) -> Result<(), AvmError> {
+    let b = avm.pop_uint64()?;
+    let a = avm.pop_uint64()?;
+    let res = match (a, b) {
+        (0, 0) => return Err(AvmError::ZeroToThePowerOfZero),
+        (0, _) | (1, _) => a,
+        // any base >= 2 overflows a u64 for exponents that do not fit into a u32
+        _ => u32::try_from(b)
+            .ok()
+            .and_then(|b| a.checked_pow(b))
+            .ok_or(AvmError::IntegerOverflow)?,
+    };
+    avm.data_stack.push(res.into());
+    Ok(())
+}
+
+fn op_expw(avm: &mut Avm) -> Result<(), AvmError> {
+    let b = avm.pop_uint64()?;
+    let a = avm.pop_uint64()?;
+    let res = match (a, b) {
+        (0, 0) => return Err(AvmError::ZeroToThePowerOfZero),
+        (0, _) | (1, _) => a as u128,
+        _ => u32::try_from(b)
+            .ok()
+            .and_then(|b| (a as u128).checked_pow(b))
+            .ok_or(AvmError::IntegerOverflow)?,
+    };
+    let (hi, lo) = encoding::u128_to_u64_tuple(res);
+    avm.data_stack.push(hi.into());
+    avm.data_stack.push(lo.into());
+    Ok(())
+}
+
+fn op_divw(avm: &mut Avm) -> Result<(), AvmError> {
+    let c = avm.pop_uint64()?;
+    let b = avm.pop_uint64()?;
+    let a = avm.pop_uint64()?;
+    let ab = encoding::u64_tuple_to_u128(a, b);
+    let res = ab.checked_div(c as u128).ok_or(AvmError::DivisionByZero)?;
+    // unlike divmodw, the quotient must fit into a single uint64
+    let res = u64::try_from(res).map_err(|_| AvmError::IntegerOverflow)?;
+    avm.data_stack.push(res.into());
+    Ok(())
+}
+
+fn op_log(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let value = avm.pop_bytes()?;
+    if avm.logs.len() >= MAX_LOG_CALLS {
+        return Err(AvmError::TooManyLogCalls(MAX_LOG_CALLS));
+    }
+    let total_size: usize = avm.logs.iter().map(Vec::len).sum();
+    if total_size + value.len() > MAX_LOG_SIZE {
+        return Err(AvmError::LogsTooLarge(MAX_LOG_SIZE));
+    }
+    avm.logs.push(value);
+    Ok(())
+}
+
+fn op_itxn_begin(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    if !avm.pending_inner_txns.is_empty() {
+        return Err(AvmError::InnerTxnNotSubmitted);
+    }
+    inner::begin(avm)
+}
+
+// inner transactions may only refer to the accounts, assets and
+// applications that are available to the program
+fn check_inner_reference(avm: &Avm, field: TxnField, value: &AvmData) -> Result<(), AvmError> {
+    let txn = avm.txn();
+    match (field, value) {
+        (
+            TxnField::Receiver
+            | TxnField::CloseRemainderTo
+            | TxnField::AssetSender
+            | TxnField::AssetReceiver
+            | TxnField::AssetCloseTo
+            | TxnField::FreezeAssetAccount
+            | TxnField::Accounts
+            | TxnField::RekeyTo,
+            AvmData::Bytes(address),
+        ) if address.iter().any(|&byte| byte != 0) => {
+            resolve_account(avm, value.clone()).map(|_| ())
+        }
+        (
+            TxnField::XferAsset | TxnField::ConfigAsset | TxnField::FreezeAsset | TxnField::Assets,
+            AvmData::Uint64(asset_id),
+        ) if *asset_id != 0 && !txn.assets.contains(asset_id) => {
+            Err(AvmError::AssetNotAvailable(*asset_id))
+        }
+        (TxnField::ApplicationID | TxnField::Applications, AvmData::Uint64(app_id))
+            if *app_id != 0
+                && *app_id != txn.current_application_id()
+                && !txn.applications.contains(app_id) =>
+        {
+            Err(AvmError::AppNotAvailable(*app_id))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn op_itxn_field(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let field = read_txn_field(avm)?;
+    match field.inner_version() {
+        Some(version) if avm.version >= version => {}
+        _ => return Err(AvmError::InvalidTxnFieldUse(field)),
+    }
+    let value = avm.pop_any()?;
+    check_inner_reference(avm, field, &value)?;
+    avm.pending_inner_txns
+        .last_mut()
+        .ok_or(AvmError::InnerTxnNotStarted)?
+        .set_field(field, value)
+}
+
+fn op_itxn_submit(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    avm.last_inner_group = inner::submit(avm)?;
+    Ok(())
+}
+
+// pushes the field of the transaction at position group_index in the
+// inner group that was submitted last, or of its last transaction if no
+// position is given. All effects of the group are available.
+fn push_inner_txn_field(
+    avm: &mut Avm,
+    group_index: Option<usize>,
+    field: TxnField,
+    array_idx: Option<usize>,
+) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    let group = &avm.last_inner_group;
+    if group.is_empty() {
+        return Err(AvmError::NoInnerTxns);
+    }
+    let group_index = group_index.unwrap_or(group.len() - 1);
+    let txn = group
+        .get(group_index)
+        .ok_or(AvmError::GroupIndexOutOfRange(group_index, group.len()))?;
+    let value = match array_idx {
+        Some(idx) => txn.array_field(field, idx)?,
+        None => txn.field(field, group_index)?,
+    };
+    avm.data_stack.push(value);
+    Ok(())
+}
+
+fn op_itxn(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    push_inner_txn_field(avm, None, field, None)
+}
+
+fn op_itxna(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.read_byte()? as usize;
+    push_inner_txn_field(avm, None, field, Some(idx))
+}
+
+fn op_itxn_next(avm: &mut Avm) -> Result<(), AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    if avm.pending_inner_txns.is_empty() {
+        return Err(AvmError::InnerTxnNotStarted);
+    }
+    inner::begin(avm)
+}
+
+fn op_gitxn(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    push_inner_txn_field(avm, Some(group_index), field, None)
+}
+
+fn op_gitxna(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    let idx = avm.read_byte()? as usize;
+    push_inner_txn_field(avm, Some(group_index), field, Some(idx))
+}
+
+// the boxes referenced by the app calls of the group, all of
+// which are available to every program in the group
+fn referenced_boxes<'a>(avm: &'a Avm) -> Vec<(u64, &'a [u8])> {
+    avm.txn_group
+        .iter()
+        .filter(|txn| txn.tx_type == TxType::ApplicationCall)
+        .flat_map(|txn| {
+            txn.boxes.iter().filter_map(move |reference| {
+                let app_id = match reference.app_index {
+                    0 => txn.current_application_id(),
+                    idx => *txn.applications.get(idx as usize - 1)?,
+                };
+                Some((app_id, &reference.name[..]))
+            })
+        })
+        .collect()
+}
+
+// returns the id of the current application after checking
+// that its box with the given name may be accessed
+fn box_app_id(avm: &Avm, name: &[u8]) -> Result<u64, AvmError> {
+    avm.ensure_mode(RunMode::Application)?;
+    if name.is_empty() || name.len() > MAX_BOX_NAME_LEN {
+        return Err(AvmError::InvalidBoxName(name.len()));
+    }
+    let app_id = avm.txn().current_application_id();
+    if !referenced_boxes(avm).contains(&(app_id, name)) {
+        return Err(AvmError::BoxNotAvailable(name.to_vec()));
+    }
+    Ok(app_id)
+}
+
+fn read_box(avm: &Avm, app_id: u64, name: &[u8]) -> Result<Vec<u8>, AvmError> {
+    avm.ledger
+        .borrow()
+        .box_get(app_id, name)
+        .ok_or_else(|| AvmError::BoxNotFound(name.to_vec()))
+}
+
+// each box reference of the group adds to a quota that limits
+// the total size of all boxes that are referenced by the group
+fn write_box(avm: &Avm, app_id: u64, name: &[u8], contents: Vec<u8>) -> Result<(), AvmError> {
+    let mut boxes = referenced_boxes(avm);
+    let quota = boxes.len() as u64 * BOX_REFERENCE_QUOTA;
+    boxes.sort();
+    boxes.dedup();
+    let mut ledger = avm.ledger.borrow_mut();
+    let total_size: u64 = boxes
+        .into_iter()
+        .map(|(box_app_id, box_name)| {
+            if (box_app_id, box_name) == (app_id, name) {
+                contents.len() as u64
+            } else {
+                ledger
+                    .box_get(box_app_id, box_name)
+                    .map_or(0, |contents| contents.len() as u64)
+            }
+        })
+        .sum();
+    if total_size > quota {
+        return Err(AvmError::BoxQuotaExceeded(total_size, quota));
+    }
+    ledger.box_put(app_id, name, contents);
+    Ok(())
+}
+
+fn op_box_create(avm: &mut Avm) -> Result<(), AvmError> {
+    let size = avm.pop_uint64()?;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    if size > MAX_BOX_SIZE {
+        return Err(AvmError::BoxTooLarge(size));
+    }
+    let existing = avm.ledger.borrow().box_get(app_id, &name);
+    let created = match existing {
+        Some(contents) if contents.len() as u64 != size => {
+            return Err(AvmError::BoxSizeMismatch(contents.len() as u64, size))
+        }
+        Some(_) => false,
+        None => {
+            write_box(avm, app_id, &name, vec![0; size as usize])?;
+            true
+        }
+    };
+    avm.data_stack.push(created.into());
+    Ok(())
+}
+
+fn op_box_extract(avm: &mut Avm) -> Result<(), AvmError> {
+    let length = avm.pop_uint64()? as usize;
+    let start = avm.pop_uint64()? as usize;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let contents = read_box(avm, app_id, &name)?;
+    let bytes = checked_substring(&contents, start, start.saturating_add(length))?;
+    if bytes.len() > MAX_BYTES_LEN {
+        return Err(AvmError::BytesTooLong);
+    }
+    avm.data_stack.push(bytes.to_vec().into());
+    Ok(())
+}
+
+fn op_box_replace(avm: &mut Avm) -> Result<(), AvmError> {
+    let replacement = avm.pop_bytes()?;
+    let start = avm.pop_uint64()? as usize;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let mut contents = read_box(avm, app_id, &name)?;
+    match start.checked_add(replacement.len()) {
+        Some(end) if end <= contents.len() => contents[start..end].copy_from_slice(&replacement),
+        _ => {
+            return Err(AvmError::InvalidReplacement(
+                start,
+                replacement.len(),
+                contents.len(),
+            ))
+        }
+    }
+    write_box(avm, app_id, &name, contents)
+}
+
+fn op_box_del(avm: &mut Avm) -> Result<(), AvmError> {
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let deleted = avm.ledger.borrow_mut().box_del(app_id, &name);
+    avm.data_stack.push(deleted.into());
+    Ok(())
+}
+
+fn op_box_len(avm: &mut Avm) -> Result<(), AvmError> {
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let contents = avm.ledger.borrow().box_get(app_id, &name);
+    let len = contents
+        .as_ref()
+        .map_or(0, |contents| contents.len() as u64);
+    avm.data_stack.push(len.into());
+    avm.data_stack.push(contents.is_some().into());
+    Ok(())
+}
+
+fn op_box_get(avm: &mut Avm) -> Result<(), AvmError> {
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let contents = avm.ledger.borrow().box_get(app_id, &name);
+    if contents
+        .as_ref()
+        .is_some_and(|contents| contents.len() > MAX_BYTES_LEN)
+    {
+        return Err(AvmError::BytesTooLong);
+    }
+    avm.data_stack
+        .push(contents.clone().unwrap_or_default().into());
+    avm.data_stack.push(contents.is_some().into());
+    Ok(())
+}
+
+fn op_box_put(avm: &mut Avm) -> Result<(), AvmError> {
+    let contents = avm.pop_bytes()?;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let existing = avm.ledger.borrow().box_get(app_id, &name);
+    match existing {
+        Some(existing) if existing.len() != contents.len() => Err(AvmError::BoxSizeMismatch(
+            existing.len() as u64,
+            contents.len() as u64,
+        )),
+        _ => write_box(avm, app_id, &name, contents),
+    }
+}
+
+fn op_txnas(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.pop_uint64()? as usize;
+    push_txn_field(avm, avm.group_index, field, Some(idx))
+}
+
+fn op_gtxnas(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    let idx = avm.pop_uint64()? as usize;
+    push_txn_field(avm, group_index, field, Some(idx))
+}
+
+fn op_args(avm: &mut Avm) -> Result<(), AvmError> {
+    let idx = avm.pop_uint64()?;
+    op_arg_n(avm, idx as usize)
+}
+
+fn op_gtxnsas(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.pop_uint64()? as usize;
+    let group_index = avm.pop_uint64()? as usize;
+    push_txn_field(avm, group_index, field, Some(idx))
+}
+
+fn op_gloadss(avm: &mut Avm) -> Result<(), AvmError> {
+    let pos = avm.pop_uint64()?;
+    let group_index = avm.pop_uint64()? as usize;
+    if pos >= avm.scratch.len() as u64 {
+        return Err(AvmError::ScratchAccessOutOfBounds(pos as usize));
+    }
+    push_group_scratch(avm, group_index, pos as usize)
+}
+
+fn op_itxnas(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = read_txn_field(avm)?;
+    let idx = avm.pop_uint64()? as usize;
+    push_inner_txn_field(avm, None, field, Some(idx))
+}
+
+fn op_gitxnas(avm: &mut Avm) -> Result<(), AvmError> {
+    let group_index = avm.read_byte()? as usize;
+    let field = read_txn_field(avm)?;
+    let idx = avm.pop_uint64()? as usize;
+    push_inner_txn_field(avm, Some(group_index), field, Some(idx))
+}
+
+// pushes the VRF output, which is all zeros for invalid
+// proofs, and whether the proof is valid
+fn op_vrf_verify(avm: &mut Avm) -> Result<(), AvmError> {
+    let VrfStandard::VrfAlgorand = VrfStandard::parse(avm.read_byte()?)?;
+    let public_key = avm.pop_bytes()?;
+    let proof = avm.pop_bytes()?;
+    let message = avm.pop_bytes()?;
+    let public_key: [u8; VRF_PUBLIC_KEY_LEN] = public_key.try_into().map_err(|key: Vec<u8>| {
+        AvmError::InvalidVrfInput("public key", key.len(), VRF_PUBLIC_KEY_LEN)
+    })?;
+    let proof: [u8; VRF_PROOF_LEN] = proof
+        .try_into()
+        .map_err(|proof: Vec<u8>| AvmError::InvalidVrfInput("proof", proof.len(), VRF_PROOF_LEN))?;
+    let output = vrf::verify(&public_key, &proof, &message);
+    avm.data_stack
+        .push(output.unwrap_or([0; 64]).to_vec().into());
+    avm.data_stack.push(output.is_some().into());
+    Ok(())
+}
+
+// blocks are available from the last MaxTxnLife rounds before the
+// transaction's LastValid up to the round before its FirstValid, so
+// that the result does not depend on the round the transaction is in
+fn op_block(avm: &mut Avm) -> Result<(), AvmError> {
+    let field = BlockField::parse(avm.read_byte()?)?;
+    let round = avm.pop_uint64()?;
+    let txn = avm.txn();
+    let first_available = txn
+        .last_valid
+        .saturating_sub(avm.globals.max_txn_life + 1)
+        .max(1);
+    let last_available = match txn.first_valid {
+        // nothing is available
+        0 => 0,
+        first_valid => first_valid - 1,
+    };
+    if round < first_available || round > last_available {
+        return Err(AvmError::RoundNotAvailable(
+            round,
+            first_available,
+            last_available,
+        ));
+    }
+    let header = avm
+        .blocks
+        .block_header(round)
+        .ok_or(AvmError::BlockNotFound(round))?;
+    avm.data_stack.push(header.field(field));
+    Ok(())
+}
+
+// boxes keep their size, so the spliced contents are
+// either truncated or padded with zero bytes
+fn op_box_splice(avm: &mut Avm) -> Result<(), AvmError> {
+    let replacement = avm.pop_bytes()?;
+    let delete = avm.pop_uint64()? as usize;
+    let start = avm.pop_uint64()? as usize;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    let contents = read_box(avm, app_id, &name)?;
+    let size = contents.len();
+    let end = match start.checked_add(delete) {
+        Some(end) if end <= size => end,
+        _ => return Err(AvmError::InvalidReplacement(start, delete, size)),
+    };
+    let mut spliced = [&contents[..start], &replacement, &contents[end..]].concat();
+    spliced.resize(size, 0);
+    write_box(avm, app_id, &name, spliced)
+}
+
+fn op_box_resize(avm: &mut Avm) -> Result<(), AvmError> {
+    let size = avm.pop_uint64()?;
+    let name = avm.pop_bytes()?;
+    let app_id = box_app_id(avm, &name)?;
+    if size > MAX_BOX_SIZE {
+        return Err(AvmError::BoxTooLarge(size));
+    }
+    let mut contents = read_box(avm, app_id, &name)?;
+    contents.resize(size as usize, 0);
+    write_box(avm, app_id, &name, contents)
+}
+
+// the cost of the elliptic curve opcodes depends on the group
+fn op_ec_add(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let b = avm.pop_bytes()?;
+    let a = avm.pop_bytes()?;
+    avm.cost += match group {
+        EcGroup::BN254g1 => 125,
+        EcGroup::BN254g2 => 170,
+        EcGroup::BLS12_381g1 => 205,
+        EcGroup::BLS12_381g2 => 290,
+    };
+    avm.data_stack.push(ec::add(group, &a, &b)?.into());
+    Ok(())
+}
+
+fn op_ec_scalar_mul(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let scalar = avm.pop_bytes()?;
+    let point = avm.pop_bytes()?;
+    avm.cost += match group {
+        EcGroup::BN254g1 => 1810,
+        EcGroup::BN254g2 => 3430,
+        EcGroup::BLS12_381g1 => 2950,
+        EcGroup::BLS12_381g2 => 6530,
+    };
+    avm.data_stack
+        .push(ec::scalar_mul(group, &point, &scalar)?.into());
+    Ok(())
+}
+
+// the points of B are in the group that is paired with the group of A
+fn op_ec_pairing_check(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let b = avm.pop_bytes()?;
+    let a = avm.pop_bytes()?;
+    // charged per point of the named group that B could hold,
+    // although B holds points of the other group of the pairing
+    let point_len = group.point_len();
+    avm.cost += match group {
+        EcGroup::BN254g1 | EcGroup::BN254g2 => 8000 + cost_by_length(b.len(), 7400, point_len),
+        EcGroup::BLS12_381g1 | EcGroup::BLS12_381g2 => {
+            13000 + cost_by_length(b.len(), 10000, point_len)
+        }
+    };
+    avm.data_stack
+        .push(ec::pairing_check(group, &a, &b)?.into());
+    Ok(())
+}
+
+fn op_ec_multi_scalar_mul(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let scalars = avm.pop_bytes()?;
+    let points = avm.pop_bytes()?;
+    let (base_cost, cost_per_scalar) = match group {
+        EcGroup::BN254g1 => (3600, 90),
+        EcGroup::BN254g2 => (7200, 270),
+        EcGroup::BLS12_381g1 => (6500, 95),
+        EcGroup::BLS12_381g2 => (14850, 485),
+    };
+    avm.cost += base_cost + cost_by_length(scalars.len(), cost_per_scalar, ec::MAX_SCALAR_LEN);
+    avm.data_stack
+        .push(ec::multi_scalar_mul(group, &points, &scalars)?.into());
+    Ok(())
+}
+
+fn op_ec_subgroup_check(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let point = avm.pop_bytes()?;
+    avm.cost += match group {
+        EcGroup::BN254g1 => 20,
+        EcGroup::BN254g2 => 3100,
+        EcGroup::BLS12_381g1 => 1850,
+        EcGroup::BLS12_381g2 => 2340,
+    };
+    avm.data_stack
+        .push(ec::subgroup_check(group, &point)?.into());
+    Ok(())
+}
+
+fn op_ec_map_to(avm: &mut Avm) -> Result<(), AvmError> {
+    let group = EcGroup::parse(avm.read_byte()?)?;
+    let element = avm.pop_bytes()?;
+    avm.cost += match group {
+        EcGroup::BN254g1 => 630,
+        EcGroup::BN254g2 => 3300,
+        EcGroup::BLS12_381g1 => 1950,
+        EcGroup::BLS12_381g2 => 8150,
+    };
+    avm.data_stack.push(ec::map_to(group, &element)?.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+
+    use crate::avm::{
+        execute_group, execute_program, Account, Application, AssetHolding, AssetParams,
+        BlockHeader, BoxReference, Globals, Ledger, MemoryBlockHistory, MemoryLedger, OnCompletion,
+        Transaction, TxType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_plus() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
         // pushint 2
-        // *
+        // +
+        let program = vec![0x09, 0x81, 0x01, 0x81, 0x02, 0x08];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plus_overflow() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 2**64 - 1
+        // pushint 1
+        // +
+        let program = [
+            vec![0x09], // #pragma version 9
+            vec![
+                0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01,
+            ], // pushint 2**64 - 1
+            vec![0x81, 0x01], // pushint 1
+            vec![0x08], // +
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerOverflow, err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_minus() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 5
+        // pushint 3
+        // -
+        let program = vec![0x09, 0x81, 0x05, 0x81, 0x03, 0x09];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_minus_underflow() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 5
+        // pushint 7
+        // -
+        let program = vec![0x09, 0x81, 0x05, 0x81, 0x07, 0x09];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerUnderflow, err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_div() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 2
+        // /
+        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x02, 0x0a];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_div_by_zero() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 0
+        // /
+        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x00, 0x0a];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::DivisionByZero, err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mul() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 2
+        // *
+        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x2, 0x0b];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mul_overflow() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 2
+        // *
+        let program = [
+            vec![0x09],                               // #pragma version 9
+            vec![0x81, 0x80, 0x80, 0x80, 0x80, 0x10], // pushint 2**32
+            vec![0x81, 0x80, 0x80, 0x80, 0x80, 0x10], // pushint 2**32
+            vec![0x0b],                               // *
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerOverflow, err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concat() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushbytes 0xdead
+        // pushbytes 0xbeef
+        // concat
+        let program = vec![0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x50];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_concat_overflow() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushbytes (4000 bytes)
+        // pushbytes (100 bytes)
+        // concat
+        let program = [
+            vec![0x09],       // #pragma version 9
+            vec![0x80],       // pushbytes
+            vec![0xA0, 0x1F], // 4000 as varint
+            vec![0; 4000],    // 4000 0-bytes
+            vec![0x80],       // pushbytes
+            vec![0x64],       // 100 as varint
+            vec![0; 100],     // 4000 0-bytes
+            vec![0x50],       // concat
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BytesTooLong, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_err() -> Result<(), AvmError> {
+        // #pragma version 9
+        // err
+        let program = [0x09, 0x00];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ErrOpCode, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_intcblock() -> Result<(), AvmError> {
+        // #pragma version 9
+        // intcblock 10 20 30 40
+        // intc_1
+        // intc_3
+        let program = [0x09, 0x20, 0x04, 0x0a, 0x14, 0x1e, 0x28, 0x23, 0x25];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(40)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecblock() -> Result<(), AvmError> {
+        // #pragma version 9
+        // bytecblock 0xdead 0xbe 0xef
+        // bytec_0
+        // bytec_2
+        let program = [
+            0x09, 0x26, 0x03, 0x02, 0xde, 0xad, 0x01, 0xbe, 0x01, 0xef, 0x28, 0x2a,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xef])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde, 0xad])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lt() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // <
+        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0c];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 2
+        // pushint 1
+        // <
+        let program = [0x09, 0x81, 0x02, 0x81, 0x01, 0x0c];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gt() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // >
+        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0d];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // >
+        let program = [0x09, 0x81, 0x02, 0x81, 0x01, 0x0d];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_leq() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // <=
+        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0e];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 2
+        // pushint 2
+        // <=
+        let program = [0x09, 0x81, 0x02, 0x81, 0x02, 0x0e];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 3
+        // pushint 2
+        // <=
+        let program = [0x09, 0x81, 0x03, 0x81, 0x02, 0x0e];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geq() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // >=
+        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0f];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 2
+        // pushint 2
+        // >=
+        let program = [0x09, 0x81, 0x02, 0x81, 0x02, 0x0f];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 3
+        // pushint 2
+        // >=
+        let program = [0x09, 0x81, 0x03, 0x81, 0x02, 0x0f];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_and() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 1
+        // pushint 2
+        // &&
+        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x10];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 0
+        // pushint 2
+        // &&
+        let program = [0x09, 0x81, 0x00, 0x81, 0x02, 0x10];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_or() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // pushint 2
+        // ||
+        let program = [0x09, 0x81, 0x00, 0x81, 0x02, 0x11];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 0
+        // pushint 0
+        // ||
+        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x11];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_eq() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // pushint 0
+        // ==
+        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x12];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 0
+        // pushint 1
+        // ==
+        let program = [0x09, 0x81, 0x00, 0x81, 0x01, 0x12];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdead
+        // pushbytes 0xdead
+        // ==
+        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xde, 0xad, 0x12];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdead
+        // pushbytes 0xbeef
+        // ==
+        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x12];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_eq_on_incompatible_types() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // pushbytes 0xdead
+        // ==
+        let program = [0x09, 0x81, 0x00, 0x80, 0x02, 0xde, 0xad, 0x12];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(
+            AvmError::IncompatibleTypes("(uint64,bytes)", "(uint64,uint64) or (bytes,bytes)"),
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_neq() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // pushint 0
+        // !=
+        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x13];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 0
+        // pushint 1
+        // !=
+        let program = [0x09, 0x81, 0x00, 0x81, 0x01, 0x13];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdead
+        // pushbytes 0xdead
+        // !=
+        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xde, 0xad, 0x13];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdead
+        // pushbytes 0xbeef
+        // !=
+        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x13];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_neq_on_incompatible_types() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // pushbytes 0xdead
+        // !=
+        let program = [0x09, 0x81, 0x00, 0x80, 0x02, 0xde, 0xad, 0x13];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(
+            AvmError::IncompatibleTypes("(uint64,bytes)", "(uint64,uint64) or (bytes,bytes)"),
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_neg() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // !
+        let program = [0x09, 0x81, 0x00, 0x14];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(TRUE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 1
+        // !
+        let program = [0x09, 0x81, 0x01, 0x14];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushint 2
+        // !
+        let program = [0x09, 0x81, 0x02, 0x14];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(FALSE), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_len() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushbytes (empty string)
+        // len
+        let program = [0x09, 0x80, 0x00, 0x15];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xde
+        // len
+        let program = [0x09, 0x80, 0x01, 0xde, 0x15];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdead
+        // len
+        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x15];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_itob() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0
+        // itob
+        let program = [0x09, 0x81, 0x00, 0x16];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+            ])),
+            avm.data_stack.pop()
+        );
+
+        // #pragma version 9
+        // pushint 45647561321464
+        // itob
+        let program = [0x09, 0x81, 0xF8, 0xD7, 0xB5, 0xB5, 0xC2, 0xB0, 0x0A, 0x16];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![
+                0x00, 0x00, 0x29, 0x84, 0x26, 0xAD, 0x6B, 0xF8,
+            ])),
+            avm.data_stack.pop()
+        );
+
+        // #pragma version 9
+        // pushint
+        // itob
+        let program = [
+            0x09, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x16,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+            ])),
+            avm.data_stack.pop()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_btoi() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushbytes (empty string)
+        // btoi
+        let program = [0x09, 0x80, 0x00, 0x17];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xde
+        // btoi
+        let program = [0x09, 0x80, 0x01, 0xde, 0x17];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(222)), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdeadbeef
+        // btoi
+        let program = [0x09, 0x80, 0x04, 0xde, 0xad, 0xbe, 0xef, 0x17];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(3735928559)), avm.data_stack.pop());
+
+        // #pragma version 9
+        // pushbytes 0xdeadbeefdeadbeefaa
+        // btoi
+        let program = [
+            0x09, 0x80, 0x09, 0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef, 0xaa, 0x17,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BtoiTooLong(9), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mod() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 4
+        // %
+        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x04, 0x18];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mod_by_zero() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 10
+        // pushint 0
+        // /
+        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x00, 0x18];
+        let mut avm = Avm::for_program(&program)?;
+
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::DivisionByZero, err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bit_or() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0b000000001000100110111101 (0xBD9302)
+        // pushint 0b110010011011101100100011 (0xA3F6A606)
+        // |
+        let program = vec![
+            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x19,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // result:
+        // 0b110010011011101110111111
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(13220799)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bit_and() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0b000000001000100110111101 (0xBD9302)
+        // pushint 0b110010011011101100100011 (0xA3F6A606)
+        // &
+        let program = vec![
+            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x1a,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // result:
+        // 0b1000100100100001
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(35105)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bit_xor() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0b000000001000100110111101 (0xBD9302)
+        // pushint 0b110010011011101100100011 (0xA3F6A606)
+        // ^
+        let program = vec![
+            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x1b,
+        ];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // result:
+        // 0b110010010011001010011110
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(13185694)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bit_not() -> Result<(), AvmError> {
+        // #pragma version 9
+        // pushint 0b000000001000100110111101 (0xBD9302)
+        // !
+        let program = vec![0x09, 0x81, 0xBD, 0x93, 0x02, 0x1c];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // result:
+        // 0b1111111111111111111111111111111111111111111111110111011001000010
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Uint64(18446744073709516354)),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mulw() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![
+                0x81, 0xb4, 0xce, 0xb6, 0xf4, 0x81, 0xe3, 0xca, 0xff, 0xff, 0x01,
+            ], // pushint 18446509981324650292
+            vec![
+                0x81, 0xa4, 0xca, 0xc4, 0x90, 0xee, 0xe2, 0xd8, 0xfd, 0xff, 0x01,
+            ], // pushint 18445445648759203108
+            vec![0x1d], // mulw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // the result (in hex 0xfffa8e32d6bd7f742c811b4ac78a0750)
+        // is pushed onto the stack as two 64-bit values
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(
+            // low
+            Some(AvmData::Uint64(0x2c811b4ac78a0750)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            // high
+            Some(AvmData::Uint64(0xfffa8e32d6bd7f74)),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_addw() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![
+                0x81, 0xb4, 0xce, 0xb6, 0xf4, 0x81, 0xe3, 0xca, 0xff, 0xff, 0x01,
+            ], // pushint 18446509981324650292
+            vec![
+                0x81, 0xa4, 0xca, 0xc4, 0x90, 0xee, 0xe2, 0xd8, 0xfd, 0xff, 0x01,
+            ], // pushint 18445445648759203108
+            vec![0x1e], // addw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // the result (in hex 0x0000000000000001fffa8e2f009ecc58)
+        // is pushed onto the stack as two 64-bit values
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(
+            // low
+            Some(AvmData::Uint64(0xfffa8e2f009ecc58)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            // high
+            Some(AvmData::Uint64(0x0000000000000001)),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_divmodw() -> Result<(), AvmError> {
+        // ab = 0xfffa8e32d6bd7f742c811b4ac78a0750
+        // cd = 0x00000000002ccd18797b150ba583e510
+        //  a = 0xfffa8e32d6bd7f74 as varint: f4fef5b5adc6a3fdff01
+        //  b = 0x2c811b4ac78a0750 as varint: d08ea8bcace9c6c02c
+        //  c = 0x00000000002ccd18 as varint: 989ab301
+        //  d = 0x797b150ba583e510 as varint: 90ca8facbaa1c5bd79
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![
+                0x81, 0xf4, 0xfe, 0xf5, 0xb5, 0xad, 0xc6, 0xa3, 0xfd, 0xff, 0x01,
+            ], // pushint a
+            vec![0x81, 0xd0, 0x8e, 0xa8, 0xbc, 0xac, 0xe9, 0xc6, 0xc0, 0x2c], // pushint b
+            vec![0x81, 0x98, 0x9a, 0xb3, 0x01], // pushint c
+            vec![0x81, 0x90, 0xca, 0x8f, 0xac, 0xba, 0xa1, 0xc5, 0xbd, 0x79], // pushint d
+            vec![0x1f], // divmodw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // the result are two u128s (split into four u64s):
+        // result of division: 0x0000000000000000000005b6b2aa6607 = 6282239698439
+        // result of modulo:   0x000000000029b2807db11bcb770a63e0 = 50408910078442914141856736
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(
+            // modulo low
+            Some(AvmData::Uint64(0x7db11bcb770a63e0)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            // modulo high
+            Some(AvmData::Uint64(0x000000000029b280)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            // division low
+            Some(AvmData::Uint64(0x000005b6b2aa6607)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            // division high
+            Some(AvmData::Uint64(0x0000000000000000)),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_store() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x05], // pushint 5
+            vec![0x35, 0x02], // store 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(0, avm.data_stack.len());
+        assert_eq!(AvmData::Uint64(5), avm.scratch[2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_unset_location() -> Result<(), AvmError> {
+        // since position 2 wasn't written before, it is the
+        // default value: 0 as uint64
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x34, 0x02], // load 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_and_load() -> Result<(), AvmError> {
+        // since position 2 wasn't written before, it is the
+        // default value: 0 as uint64
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x35, 0x05],                         // store 5
+            vec![0x35, 0x03],                         // store 3
+            vec![0x34, 0x05],                         // load 5
+            vec![0x34, 0x03],                         // load 3
+            vec![0x34, 0x05],                         // load 5
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_loads() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x35, 0x09],                         // store 9
+            vec![0x81, 0x09],                         // pushint 9
+            vec![0x3e],                               // loads
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_loads_out_of_bounds() -> Result<(), AvmError> {
+        // index 256 is the first position that is no longer
+        // within the scratch space's bounds
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x80, 0x02], // pushint 256
+            vec![0x3e],             // loads
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ScratchAccessOutOfBounds(256), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stores() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x3f],                               // stores
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(0, avm.data_stack.len());
+        assert_eq!(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef]), avm.scratch[5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stores_out_of_bounds() -> Result<(), AvmError> {
+        // index 256 is the first position that is no longer
+        // within the scratch space's bounds
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x80, 0x02],                   // pushint 256
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x3f],                               // stores
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ScratchAccessOutOfBounds(256), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bz() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x03],       // pushint 3
+            vec![0x81, 0x02],       // pushint 2
+            vec![0x0c],             // <
+            vec![0x41, 0x00, 0x06], // bz 0x0006
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x43],             // return
+            vec![0x42, 0x00, 0x03], // b 0x0003
+            vec![0x81, 0x00],       // pushint 0
+            vec![0x43],             // return
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bnz() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x02],       // pushint 2
+            vec![0x81, 0x03],       // pushint 3
+            vec![0x0c],             // <
+            vec![0x40, 0x00, 0x06], // bnz 0x0006
+            vec![0x81, 0x00],       // pushint 0
+            vec![0x43],             // return
+            vec![0x42, 0x00, 0x03], // b 0x0003
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x43],             // return
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x42, 0x00, 0x04], // bnz 0x0004
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x81, 0x02],       // pushint 2
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x43],             // return
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_return() -> Result<(), AvmError> {
+        // #pragma version 10
+        // pushint 1
+        // pushint 2
+        // pushint 3
+        // return
+        let program = vec![0x0a, 0x81, 0x01, 0x81, 0x02, 0x81, 0x03, 0x43];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x05], // pushint 5
+            vec![0x44],       // assert
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(0, avm.data_stack.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_negative() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x44],       // assert
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::AssertionFailed(3), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bury() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x81, 0x05], // pushint 5
+            vec![0x45, 0x04], // bury 4
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bury_zero() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x45, 0x00], // bury 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidStackAccess, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bury_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x45, 0x03], // bury 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidStackAccess, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_popn() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x81, 0x05], // pushint 5
+            vec![0x46, 0x03], // popn 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_popn_underflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x46, 0x03], // popn 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackUnderflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dupn() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x47, 0x03], // dupn 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dup() -> Result<(), AvmError> {
+        // #pragma version 10
+        // pushint 1
+        // dup
+        let program = vec![0x0a, 0x81, 0x01, 0x49];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dup_on_empty_stack() -> Result<(), AvmError> {
+        // #pragma version 10
+        // dup
+        let program = vec![0x0a, 0x49];
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackUnderflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dup2() -> Result<(), AvmError> {
+        // #pragma version 10
+        // pushint 1
+        // pushbytes 0xde
+        // dup2
+        let program = vec![0x0a, 0x81, 0x01, 0x80, 0x01, 0xde, 0x4a];
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dup2_on_too_short_stack() -> Result<(), AvmError> {
+        // #pragma version 10
+        // pushbytes 0xde
+        // dup2
+        let program = vec![0x0a, 0x80, 0x01, 0xde, 0x4a];
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackUnderflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dig() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x81, 0x05], // pushint 5
+            vec![0x4b, 0x03], // dig 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(6, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dig_zero() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x4b, 0x00], // dig 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dig_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x4b, 0x02], // dig 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidStackAccess, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_swap() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x4c],       // swap
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_swap_with_too_few_elements() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x4c],       // swap
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackUnderflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_choose_first() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x00], // pushint 0
+            vec![0x4d],       // select
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_choose_second() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x01], // pushint 1
+            vec![0x4d],       // select
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cover_at_bottom() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x4e, 0x03], // cover 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cover_at_top() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x4e, 0x00], // cover 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cover_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x4e, 0x02], // cover 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidStackAccess, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncover_at_bottom() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x4f, 0x03], // uncover 3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncover_at_top() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x4f, 0x00], // uncover 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncover_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x02], // pushint 2
+            vec![0x4f, 0x02], // uncover 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidStackAccess, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x51, 0x01, 0x04],                   // substring 1 4
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring_empty() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x51, 0x00, 0x00],                   // substring 0 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x51, 0x01, 0x05],                   // substring 1 5
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidSubstringAccess(1, 5, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring_end_before_start() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x51, 0x03, 0x02],                   // substring 3 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidSubstringAccess(3, 2, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring3() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x52],                               // substring3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xad])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_loop_with_bnz() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x00],       // pushint 0
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x08],             // +
+            vec![0x49],             // dup
+            vec![0x81, 0x05],       // pushint 5
+            vec![0x0c],             // <
+            vec![0x40, 0xff, 0xf6], // bnz 0xfff6 (-10 in two's complement)
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shl() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x3f], // pushint 63
+            vec![0x90],       // shl
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1 << 63)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shl_out_of_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x40], // pushint 64
+            vec![0x90],       // shl
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ShiftOutOfRange(64), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shr() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x64], // pushint 100
+            vec![0x81, 0x02], // pushint 2
+            vec![0x91],       // shr
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(25)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shr_out_of_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x41], // pushint 65
+            vec![0x91],       // shr
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ShiftOutOfRange(65), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqrt() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x63], // pushint 99
+            vec![0x92],       // sqrt
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(9)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitlen() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                         // #pragma version 10
+            vec![0x81, 0x00],                   // pushint 0
+            vec![0x93],                         // bitlen
+            vec![0x81, 0x08],                   // pushint 8
+            vec![0x93],                         // bitlen
+            vec![0x80, 0x03, 0x00, 0x01, 0xff], // pushbytes 0x0001ff
+            vec![0x93],                         // bitlen
+            vec![0x80, 0x02, 0x00, 0x00],       // pushbytes 0x0000
+            vec![0x93],                         // bitlen
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(9)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_exp() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x04], // pushint 4
+            vec![0x94],       // exp
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x05], // pushint 5
+            vec![0x94],       // exp
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(81)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_exp_zero_to_the_power_of_zero() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x00], // pushint 0
+            vec![0x94],       // exp
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ZeroToThePowerOfZero, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_exp_overflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x40], // pushint 64
+            vec![0x94],       // exp
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerOverflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expw() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x7f], // pushint 127
+            vec![0x95],       // expw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1 << 63)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expw_overflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x02],       // pushint 2
+            vec![0x81, 0x80, 0x01], // pushint 128
+            vec![0x95],             // expw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerOverflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_divmodw_by_zero() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x00], // pushint 0
+            vec![0x1f],       // divmodw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::DivisionByZero, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_divw() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x04], // pushint 4
+            vec![0x97],       // divw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // 2^64 / 4 = 2^62
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1 << 62)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_divw_by_zero() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x00], // pushint 0
+            vec![0x97],       // divw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::DivisionByZero, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_divw_overflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x01], // pushint 1
+            vec![0x97],       // divw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::IntegerOverflow, err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_divw_before_v6() -> Result<(), AvmError> {
+        let program = [
+            vec![0x05],       // #pragma version 5
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x01], // pushint 1
+            vec![0x97],       // divw
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownOpcode(0x97), err);
+        Ok(())
+    }
+
+    // the varint encoding of the AVM, seven bits per byte
+    fn varuint(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        while value >= 0x80 {
+            bytes.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    // encodes `pushint value`
+    fn pushint(value: u64) -> Vec<u8> {
+        [vec![0x81], varuint(value)].concat()
+    }
+
+    // runs a program that pushes `args` and then executes `opcode`
+    fn eval_wide_math(opcode: u8, args: &[u64]) -> Result<Vec<AvmData>, AvmError> {
+        let mut program = vec![0x0a];
+        for &arg in args {
+            program.extend(pushint(arg));
+        }
+        program.push(opcode);
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+        Ok(avm.data_stack.clone())
+    }
+
+    fn u128_to_stack(value: u128) -> Vec<AvmData> {
+        let (hi, lo) = encoding::u128_to_u64_tuple(value);
+        vec![hi.into(), lo.into()]
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_mulw(a: u64, b: u64) {
+            let expected = u128_to_stack(a as u128 * b as u128);
+            proptest::prop_assert_eq!(Ok(expected), eval_wide_math(0x1d, &[a, b]));
+        }
+
+        #[test]
+        fn prop_addw(a: u64, b: u64) {
+            let expected = u128_to_stack(a as u128 + b as u128);
+            proptest::prop_assert_eq!(Ok(expected), eval_wide_math(0x1e, &[a, b]));
+        }
+
+        #[test]
+        fn prop_divmodw(a: u64, b: u64, c: u64, d: u64) {
+            let ab = encoding::u64_tuple_to_u128(a, b);
+            let cd = encoding::u64_tuple_to_u128(c, d);
+            let expected = match (ab.checked_div(cd), ab.checked_rem(cd)) {
+                (Some(quot), Some(rem)) => Ok([u128_to_stack(quot), u128_to_stack(rem)].concat()),
+                _ => Err(AvmError::DivisionByZero),
+            };
+            proptest::prop_assert_eq!(expected, eval_wide_math(0x1f, &[a, b, c, d]));
+        }
+
+        #[test]
+        fn prop_divmodw_small_divisor(a: u64, b: u64, d in 0..=u8::MAX as u64) {
+            let ab = encoding::u64_tuple_to_u128(a, b);
+            let expected = match (ab.checked_div(d as u128), ab.checked_rem(d as u128)) {
+                (Some(quot), Some(rem)) => Ok([u128_to_stack(quot), u128_to_stack(rem)].concat()),
+                _ => Err(AvmError::DivisionByZero),
+            };
+            proptest::prop_assert_eq!(expected, eval_wide_math(0x1f, &[a, b, 0, d]));
+        }
+
+        #[test]
+        fn prop_divw(a: u64, b: u64, c: u64) {
+            let ab = encoding::u64_tuple_to_u128(a, b);
+            let expected = match ab.checked_div(c as u128) {
+                Some(quot) => u64::try_from(quot)
+                    .map(|quot| vec![quot.into()])
+                    .map_err(|_| AvmError::IntegerOverflow),
+                None => Err(AvmError::DivisionByZero),
+            };
+            proptest::prop_assert_eq!(expected, eval_wide_math(0x97, &[a, b, c]));
+        }
+
+        #[test]
+        fn prop_divw_no_overflow(c in 1..=u64::MAX, a: u64, b: u64) {
+            // a < c guarantees that the quotient fits into a uint64
+            let a = a % c;
+            let ab = encoding::u64_tuple_to_u128(a, b);
+            let expected = vec![((ab / c as u128) as u64).into()];
+            proptest::prop_assert_eq!(Ok(expected), eval_wide_math(0x97, &[a, b, c]));
+        }
+
+        #[test]
+        fn prop_expw(a: u64, b in 0..=130u64) {
+            let expected = match (a, b) {
+                (0, 0) => Err(AvmError::ZeroToThePowerOfZero),
+                _ => (a as u128)
+                    .checked_pow(b as u32)
+                    .map(u128_to_stack)
+                    .ok_or(AvmError::IntegerOverflow),
+            };
+            proptest::prop_assert_eq!(expected, eval_wide_math(0x95, &[a, b]));
+        }
+    }
+
+    #[test]
+    fn test_getbit_uint64() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x04], // pushint 4
+            vec![0x81, 0x02], // pushint 2
+            vec![0x53],       // getbit
+            vec![0x81, 0x04], // pushint 4
+            vec![0x81, 0x03], // pushint 3
+            vec![0x53],       // getbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_getbit_bytes() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x80, 0x02, 0x40, 0x01], // pushbytes 0x4001
+            vec![0x81, 0x01],             // pushint 1
+            vec![0x53],                   // getbit
+            vec![0x80, 0x02, 0x40, 0x01], // pushbytes 0x4001
+            vec![0x81, 0x0f],             // pushint 15
+            vec![0x53],                   // getbit
+            vec![0x80, 0x02, 0x40, 0x01], // pushbytes 0x4001
+            vec![0x81, 0x02],             // pushint 2
+            vec![0x53],                   // getbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_getbit_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x80, 0x02, 0x40, 0x01], // pushbytes 0x4001
+            vec![0x81, 0x10],             // pushint 16
+            vec![0x53],                   // getbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BitIndexOutOfRange(16, 16), err);
+
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x40], // pushint 64
+            vec![0x53],       // getbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BitIndexOutOfRange(64, 64), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_uint64() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x01], // pushint 1
+            vec![0x54],       // setbit
+            vec![0x81, 0x01], // pushint 1
+            vec![0x81, 0x00], // pushint 0
+            vec![0x54],       // setbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(8)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_bytes() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x80, 0x02, 0x00, 0xff], // pushbytes 0x00ff
+            vec![0x81, 0x00],             // pushint 0
+            vec![0x81, 0x01],             // pushint 1
+            vec![0x54],                   // setbit
+            vec![0x81, 0x0f],             // pushint 15
+            vec![0x81, 0x00],             // pushint 0
+            vec![0x54],                   // setbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0x80, 0xfe])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_invalid_value() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x81, 0x03], // pushint 3
+            vec![0x81, 0x02], // pushint 2
+            vec![0x54],       // setbit
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidBitValue(2), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_outside_range() -> Result<(), AvmError> {
         let program = [
-            vec![0x09],                               // #pragma version 9
-            vec![0x81, 0x80, 0x80, 0x80, 0x80, 0x10], // pushint 2**32
-            vec![0x81, 0x80, 0x80, 0x80, 0x80, 0x10], // pushint 2**32
-            vec![0x0b],                               // *
+            vec![0x0a],             // #pragma version 10
+            vec![0x80, 0x01, 0x00], // pushbytes 0x00
+            vec![0x81, 0x08],       // pushint 8
+            vec![0x81, 0x01],       // pushint 1
+            vec![0x54],             // setbit
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BitIndexOutOfRange(8, 8), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_getbyte() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x55],                               // getbyte
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0xbe)), avm.data_stack.pop());
+        Ok(())
+    }
 
+    #[test]
+    fn test_getbyte_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x04],                         // pushint 4
+            vec![0x55],                               // getbyte
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::IntegerOverflow, err);
+        assert_eq!(AvmError::ByteIndexOutOfRange(4, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbyte() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x56],                               // setbyte
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
 
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0x00, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_concat() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushbytes 0xdead
-        // pushbytes 0xbeef
-        // concat
-        let program = vec![0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x50];
+    fn test_setbyte_invalid_value() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x81, 0x80, 0x02],                   // pushint 256
+            vec![0x56],                               // setbyte
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidByteValue(256), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbyte_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x04],                         // pushint 4
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x56],                               // setbyte
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ByteIndexOutOfRange(4, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x57, 0x01, 0x02],                   // extract 1 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xad, 0xbe])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_to_end() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x57, 0x01, 0x00],                   // extract 1 0
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
         assert_eq!(1, avm.data_stack.len());
         assert_eq!(
-            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            Some(AvmData::Bytes(vec![0xad, 0xbe, 0xef])),
             avm.data_stack.pop()
         );
         Ok(())
     }
 
     #[test]
-    fn test_concat_overflow() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushbytes (4000 bytes)
-        // pushbytes (100 bytes)
-        // concat
+    fn test_extract_outside_range() -> Result<(), AvmError> {
         let program = [
-            vec![0x09],       // #pragma version 9
-            vec![0x80],       // pushbytes
-            vec![0xA0, 0x1F], // 4000 as varint
-            vec![0; 4000],    // 4000 0-bytes
-            vec![0x80],       // pushbytes
-            vec![0x64],       // 100 as varint
-            vec![0; 100],     // 4000 0-bytes
-            vec![0x50],       // concat
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x57, 0x03, 0x02],                   // extract 3 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidSubstringAccess(3, 5, 4), err);
+
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x57, 0x05, 0x00],                   // extract 5 0
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidSubstringAccess(5, 4, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract3() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x58],                               // extract3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbe, 0xef])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract3_zero_length() -> Result<(), AvmError> {
+        // unlike extract, a length of 0 yields an empty byte slice
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x58],                               // extract3
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![])), avm.data_stack.pop());
+        Ok(())
+    }
 
+    #[test]
+    fn test_extract3_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x81, 0x03],                         // pushint 3
+            vec![0x58],                               // extract3
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::BytesTooLong, err);
+        assert_eq!(AvmError::InvalidSubstringAccess(2, 5, 4), err);
         Ok(())
     }
 
     #[test]
-    fn test_err() -> Result<(), AvmError> {
-        // #pragma version 9
-        // err
-        let program = [0x09, 0x00];
+    fn test_extract_uint16() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x59],                               // extract_uint16
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0xadbe)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_uint32() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x5a],                               // extract_uint32
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0xdeadbeef)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_uint64() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![
+                0x80, 0x09, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+            ], // pushbytes 0x000102030405060708
+            vec![0x81, 0x01], // pushint 1
+            vec![0x5b], // extract_uint64
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Uint64(0x0102030405060708)),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_uint64_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x5b],                               // extract_uint64
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidSubstringAccess(0, 8, 4), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace2() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x49],                               // dup
+            vec![0x80, 0x02, 0x00, 0x11],             // pushbytes 0x0011
+            vec![0x5c, 0x02],                         // replace2 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let avm = execute_program(&mut avm)?;
+
+        // the duplicated original value must not be modified
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0x00, 0x11])),
+            avm.data_stack.pop()
+        );
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace2_past_end() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x80, 0x02, 0x00, 0x11],             // pushbytes 0x0011
+            vec![0x5c, 0x03],                         // replace2 3
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::ErrOpCode, err);
+        assert_eq!(AvmError::InvalidReplacement(3, 2, 4), err);
         Ok(())
     }
 
     #[test]
-    fn test_intcblock() -> Result<(), AvmError> {
-        // #pragma version 9
-        // intcblock 10 20 30 40
-        // intc_1
-        // intc_3
-        let program = [0x09, 0x20, 0x04, 0x0a, 0x14, 0x1e, 0x28, 0x23, 0x25];
+    fn test_replace2_before_v7() -> Result<(), AvmError> {
+        let program = [
+            vec![0x06],                               // #pragma version 6
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x80, 0x02, 0x00, 0x11],             // pushbytes 0x0011
+            vec![0x5c, 0x00],                         // replace2 0
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(40)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownOpcode(0x5c), err);
         Ok(())
     }
 
     #[test]
-    fn test_bytecblock() -> Result<(), AvmError> {
-        // #pragma version 9
-        // bytecblock 0xdead 0xbe 0xef
-        // bytec_0
-        // bytec_2
+    fn test_replace3() -> Result<(), AvmError> {
         let program = [
-            0x09, 0x26, 0x03, 0x02, 0xde, 0xad, 0x01, 0xbe, 0x01, 0xef, 0x28, 0x2a,
-        ];
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x00],                         // pushint 0
+            vec![0x80, 0x03, 0x01, 0x02, 0x03],       // pushbytes 0x010203
+            vec![0x5d],                               // replace3
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Bytes(vec![0xef])), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Bytes(vec![0xde, 0xad])), avm.data_stack.pop());
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0x01, 0x02, 0x03, 0xef])),
+            avm.data_stack.pop()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_lt() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // <
-        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0c];
+    fn test_replace3_past_end() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x80, 0x00],                         // pushbytes 0x
+            vec![0x5d],                               // replace3
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidReplacement(5, 0, 4), err);
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 2
-        // pushint 1
-        // <
-        let program = [0x09, 0x81, 0x02, 0x81, 0x01, 0x0c];
+    #[test]
+    fn test_switch() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x01],                         // pushint 1
+            vec![0x8d, 0x02, 0x00, 0x02, 0x00, 0x04], // switch label0 label1
+            vec![0x81, 0x63],                         // pushint 99
+            vec![0x81, 0x0a],                         // label0: pushint 10
+            vec![0x81, 0x14],                         // label1: pushint 20
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_gt() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // >
-        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0d];
+    fn test_switch_fall_through() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x02],                         // pushint 2
+            vec![0x8d, 0x02, 0x00, 0x02, 0x00, 0x04], // switch label0 label1
+            vec![0x81, 0x63],                         // pushint 99
+            vec![0x81, 0x0a],                         // label0: pushint 10
+            vec![0x81, 0x14],                         // label1: pushint 20
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(10)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(99)), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // >
-        let program = [0x09, 0x81, 0x02, 0x81, 0x01, 0x0d];
+    #[test]
+    fn test_switch_target_outside_program() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x81, 0x00],             // pushint 0
+            vec![0x8d, 0x01, 0x00, 0x10], // switch +16
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::PcOutOfBounds, err);
         Ok(())
     }
 
     #[test]
-    fn test_leq() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // <=
-        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0e];
+    fn test_match() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x80, 0x01, 0xaa],                   // pushbytes 0xaa
+            vec![0x80, 0x01, 0xaa],                   // pushbytes 0xaa
+            vec![0x8e, 0x02, 0x00, 0x02, 0x00, 0x04], // match label0 label1
+            vec![0x81, 0x63],                         // pushint 99
+            vec![0x81, 0x0a],                         // label0: pushint 10
+            vec![0x81, 0x14],                         // label1: pushint 20
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
+        // the match value 0xaa does not match the uint64 5
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 2
-        // pushint 2
-        // <=
-        let program = [0x09, 0x81, 0x02, 0x81, 0x02, 0x0e];
+    #[test]
+    fn test_match_fall_through() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x81, 0x06],                         // pushint 6
+            vec![0x81, 0x07],                         // pushint 7
+            vec![0x8e, 0x02, 0x00, 0x02, 0x00, 0x04], // match label0 label1
+            vec![0x81, 0x63],                         // pushint 99
+            vec![0x81, 0x0a],                         // label0: pushint 10
+            vec![0x81, 0x14],                         // label1: pushint 20
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(20)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(10)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(99)), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 3
-        // pushint 2
-        // <=
-        let program = [0x09, 0x81, 0x03, 0x81, 0x02, 0x0e];
+    #[test]
+    fn test_match_stack_underflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x81, 0x05],                         // pushint 5
+            vec![0x8e, 0x02, 0x00, 0x00, 0x00, 0x00], // match label0 label1
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackUnderflow, err);
         Ok(())
     }
 
     #[test]
-    fn test_geq() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // >=
-        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x0f];
+    fn test_pushbytess() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x82, 0x03],       // pushbytess
+            vec![0x02, 0xde, 0xad], //   0xdead
+            vec![0x00],             //   0x
+            vec![0x02, 0xbe, 0xef], //   0xbeef
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbe, 0xef])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde, 0xad])), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 2
-        // pushint 2
-        // >=
-        let program = [0x09, 0x81, 0x02, 0x81, 0x02, 0x0f];
+    #[test]
+    fn test_pushbytess_too_long() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x82, 0x01], // pushbytess
+            vec![0x81, 0x20], //   4097 as varint
+            vec![0; 4097],    //   4097 0-bytes
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::BytesTooLong, err);
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 3
-        // pushint 2
-        // >=
-        let program = [0x09, 0x81, 0x03, 0x81, 0x02, 0x0f];
+    #[test]
+    fn test_pushbytess_truncated() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x82, 0x02],       // pushbytess
+            vec![0x02, 0xde, 0xad], //   0xdead
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidVarUint64, err);
         Ok(())
     }
 
     #[test]
-    fn test_and() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 1
-        // pushint 2
-        // &&
-        let program = [0x09, 0x81, 0x01, 0x81, 0x02, 0x10];
+    fn test_pushints() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x83, 0x03], // pushints
+            vec![0x01],       //   1
+            vec![0x80, 0x01], //   128
+            vec![0x00],       //   0
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(128)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 0
-        // pushint 2
-        // &&
-        let program = [0x09, 0x81, 0x00, 0x81, 0x02, 0x10];
+    #[test]
+    fn test_pushints_stack_overflow() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x83],       // pushints
+            vec![0xe9, 0x07], //   1001 as varint
+            vec![0x00; 1001], //   1001 zeros
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
-
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StackOverflow(1000), err);
         Ok(())
     }
 
     #[test]
-    fn test_or() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // pushint 2
-        // ||
-        let program = [0x09, 0x81, 0x00, 0x81, 0x02, 0x11];
+    fn test_base64_decode() -> Result<(), AvmError> {
+        let mut program = vec![
+            0x0a, // #pragma version 10
+            0x80, 0x0c, // pushbytes "aGVsbG8gd29y" ("hello wor")
+        ];
+        program.extend_from_slice(b"aGVsbG8gd29y");
+        program.extend_from_slice(&[0x5e, 0x01]); // base64_decode StdEncoding
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
-
-        // #pragma version 9
-        // pushint 0
-        // pushint 0
-        // ||
-        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x11];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        assert_eq!(
+            Some(AvmData::Bytes(b"hello wor".to_vec())),
+            avm.data_stack.pop()
+        );
+        // pushbytes + base64_decode + 1 per 16 bytes
+        assert_eq!(3, avm.cost);
+        Ok(())
+    }
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+    #[test]
+    fn test_base64_decode_invalid() -> Result<(), AvmError> {
+        let mut program = vec![
+            0x0a, // #pragma version 10
+            0x80, 0x07, // pushbytes "aGVsbG8"
+        ];
+        program.extend_from_slice(b"aGVsbG8");
+        program.extend_from_slice(&[0x5e, 0x00]); // base64_decode URLEncoding
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidBase64, err);
+        Ok(())
+    }
 
+    #[test]
+    fn test_base64_decode_unknown_encoding() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x80, 0x00], // pushbytes 0x
+            vec![0x5e, 0x02], // base64_decode 2
+        ]
+        .concat();
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownField("base64 encoding", 2), err);
         Ok(())
     }
 
+    // pushes a JSON text and a key, then runs json_ref with the given type
+    fn json_ref_program(text: &str, key: &str, field: u8) -> Vec<u8> {
+        let mut program = vec![0x0a]; // #pragma version 10
+        for bytes in [text.as_bytes(), key.as_bytes()] {
+            program.push(0x80); // pushbytes
+            program.extend(varuint(bytes.len() as u64));
+            program.extend_from_slice(bytes);
+        }
+        program.extend_from_slice(&[0x5f, field]); // json_ref
+        program
+    }
+
     #[test]
-    fn test_eq() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // pushint 0
-        // ==
-        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x12];
+    fn test_json_ref() -> Result<(), AvmError> {
+        let text = r#"{"key0": 0, "key1": "é", "key2": {"key3": [1, 2]}}"#;
+
+        let program = json_ref_program(text, "key0", 1);
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        // 2 * pushbytes + json_ref + 2 per 7 bytes
+        assert_eq!(2 + 25 + 16, avm.cost);
 
-        // #pragma version 9
-        // pushint 0
-        // pushint 1
-        // ==
-        let program = [0x09, 0x81, 0x00, 0x81, 0x01, 0x12];
+        let program = json_ref_program(text, "key1", 0);
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes("é".as_bytes().to_vec())),
+            avm.data_stack.pop()
+        );
 
-        // #pragma version 9
-        // pushbytes 0xdead
-        // pushbytes 0xdead
-        // ==
-        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xde, 0xad, 0x12];
+        let program = json_ref_program(text, "key2", 2);
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(br#"{"key3": [1, 2]}"#.to_vec())),
+            avm.data_stack.pop()
+        );
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushbytes 0xdead
-        // pushbytes 0xbeef
-        // ==
-        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x12];
+    #[test]
+    fn test_json_ref_key_not_found() -> Result<(), AvmError> {
+        let program = json_ref_program(r#"{"key0": {"key1": 1}}"#, "key1", 1);
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::JsonKeyNotFound("key1".to_string()), err);
+        Ok(())
+    }
 
+    #[test]
+    fn test_json_ref_nesting_too_deep() -> Result<(), AvmError> {
+        // as long as json_ref allows, yet fails without exhausting the stack
+        let text = format!(r#"{{"a":{}{}}}"#, "[".repeat(2040), "]".repeat(2040));
+        assert_eq!(4086, text.len());
+        let program = json_ref_program(&text, "a", 2);
+        let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidJson("nesting too deep"), err);
         Ok(())
     }
 
     #[test]
-    fn test_eq_on_incompatible_types() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // pushbytes 0xdead
-        // ==
-        let program = [0x09, 0x81, 0x00, 0x80, 0x02, 0xde, 0xad, 0x12];
+    fn test_json_ref_invalid_uint64() -> Result<(), AvmError> {
+        for number in ["-1", "1.0", "1e2", "18446744073709551616"] {
+            let text = format!(r#"{{"key0": {number}}}"#);
+            let program = json_ref_program(&text, "key0", 1);
+            let mut avm = Avm::for_program(&program)?;
+            let err = execute_program(&mut avm).unwrap_err();
+            assert_eq!(
+                AvmError::InvalidJsonValue("key0".to_string(), "uint64"),
+                err
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_ref_type_mismatch() -> Result<(), AvmError> {
+        let program = json_ref_program(r#"{"key0": 1}"#, "key0", 0);
         let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(
+            AvmError::InvalidJsonValue("key0".to_string(), "string"),
+            err
+        );
 
+        let program = json_ref_program(r#"{"key0": [1]}"#, "key0", 2);
+        let mut avm = Avm::for_program(&program)?;
         let err = execute_program(&mut avm).unwrap_err();
         assert_eq!(
-            AvmError::IncompatibleTypes("(uint64,bytes)", "(uint64,uint64) or (bytes,bytes)"),
+            AvmError::InvalidJsonValue("key0".to_string(), "object"),
             err
         );
         Ok(())
     }
 
     #[test]
-    fn test_neq() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // pushint 0
-        // !=
-        let program = [0x09, 0x81, 0x00, 0x81, 0x00, 0x13];
+    fn test_json_ref_duplicate_keys() -> Result<(), AvmError> {
+        let program = json_ref_program(r#"{"key0": 1, "key0": 2}"#, "key0", 1);
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidJson("duplicate keys are not allowed"), err);
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 0
-        // pushint 1
-        // !=
-        let program = [0x09, 0x81, 0x00, 0x81, 0x01, 0x13];
+    #[test]
+    fn test_log() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                               // #pragma version 10
+            vec![0x80, 0x02, 0xde, 0xad],             // pushbytes 0xdead
+            vec![0xb0],                               // log
+            vec![0x80, 0x05, 0x15, 0x1f, 0x7c, 0x75], // pushbytes 0x151f7c752a
+            vec![0x2a],                               //
+            vec![0xb0],                               // log
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
 
-        // #pragma version 9
-        // pushbytes 0xdead
-        // pushbytes 0xdead
-        // !=
-        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xde, 0xad, 0x13];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(0, avm.data_stack.len());
+        assert_eq!(
+            vec![vec![0xde, 0xad], vec![0x15, 0x1f, 0x7c, 0x75, 0x2a]],
+            avm.logs
+        );
+        assert_eq!(Some(&[0x2a][..]), avm.abi_return_value());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushbytes 0xdead
-        // pushbytes 0xbeef
-        // !=
-        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x80, 0x02, 0xbe, 0xef, 0x13];
+    #[test]
+    fn test_log_too_many_calls() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x80, 0x00], // pushbytes 0x
+            vec![0x47, 0x20], // dupn 32
+            vec![0xb0; 33],   // log (33 times)
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
-
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::TooManyLogCalls(32), err);
+        assert_eq!(32, avm.logs.len());
         Ok(())
     }
 
     #[test]
-    fn test_neq_on_incompatible_types() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // pushbytes 0xdead
-        // !=
-        let program = [0x09, 0x81, 0x00, 0x80, 0x02, 0xde, 0xad, 0x13];
+    fn test_log_too_large() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x80, 0x80, 0x04], // pushbytes (512 bytes)
+            vec![0; 512],           //
+            vec![0x47, 0x02],       // dupn 2
+            vec![0xb0; 3],          // log (3 times)
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
-
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(
-            AvmError::IncompatibleTypes("(uint64,bytes)", "(uint64,uint64) or (bytes,bytes)"),
-            err
-        );
+        assert_eq!(AvmError::LogsTooLarge(1024), err);
+        assert_eq!(2, avm.logs.len());
         Ok(())
     }
 
     #[test]
-    fn test_neg() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // !
-        let program = [0x09, 0x81, 0x00, 0x14];
+    fn test_log_in_signature_mode() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x80, 0x02, 0xde, 0xad], // pushbytes 0xdead
+            vec![0xb0],                   // log
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
+        avm.mode = RunMode::Signature;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidRunMode(RunMode::Application), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arg() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x2c, 0x04], // arg 4
+            vec![0x2d],       // arg_0
+            vec![0x2e],       // arg_1
+            vec![0x2f],       // arg_2
+            vec![0x30],       // arg_3
+        ]
+        .concat();
+        let args = vec![vec![0x00], vec![0x01], vec![0x02], vec![0x03], vec![0x04]];
+        let mut avm = Avm::for_logicsig(&program, args)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(TRUE), avm.data_stack.pop());
 
-        // #pragma version 9
-        // pushint 1
-        // !
-        let program = [0x09, 0x81, 0x01, 0x14];
+        assert_eq!(5, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0x03])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x02])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x01])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x00])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x04])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_arg_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![0x2e], // arg_1
+        ]
+        .concat();
+        let mut avm = Avm::for_logicsig(&program, vec![vec![0x00]])?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::ArgOutOfRange(1, 1), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arg_in_application_mode() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a], // #pragma version 10
+            vec![0x2d], // arg_0
+        ]
+        .concat();
         let mut avm = Avm::for_program(&program)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidRunMode(RunMode::Signature), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_args() -> Result<(), AvmError> {
+        let program = [
+            vec![0x05],       // #pragma version 5
+            vec![0x81, 0x01], // pushint 1
+            vec![0xc2],       // args
+        ]
+        .concat();
+        let mut avm = Avm::for_logicsig(&program, vec![vec![0xde], vec![0xad]])?;
         let avm = execute_program(&mut avm)?;
+
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xad])), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 2
-        // !
-        let program = [0x09, 0x81, 0x02, 0x14];
-        let mut avm = Avm::for_program(&program)?;
+    #[test]
+    fn test_args_before_v5() -> Result<(), AvmError> {
+        let program = [
+            vec![0x04],       // #pragma version 4
+            vec![0x81, 0x00], // pushint 0
+            vec![0xc2],       // args
+        ]
+        .concat();
+        let mut avm = Avm::for_logicsig(&program, vec![vec![0xde]])?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownOpcode(0xc2), err);
+        Ok(())
+    }
+
+    fn test_transaction() -> Transaction {
+        Transaction {
+            tx_type: TxType::ApplicationCall,
+            sender: [0x01; 32],
+            fee: 1000,
+            application_id: 42,
+            on_completion: OnCompletion::OptIn,
+            application_args: vec![vec![0xde, 0xad], vec![0xbe, 0xef]],
+            accounts: vec![[0x02; 32]],
+            applications: vec![7],
+            approval_program: vec![0xff; 5000],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_txn() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x31, 0x00], // txn Sender
+            vec![0x31, 0x01], // txn Fee
+            vec![0x31, 0x0f], // txn Type
+            vec![0x31, 0x10], // txn TypeEnum
+            vec![0x31, 0x16], // txn GroupIndex
+            vec![0x31, 0x19], // txn OnCompletion
+            vec![0x31, 0x1b], // txn NumAppArgs
+            vec![0x31, 0x1d], // txn NumAccounts
+            vec![0x31, 0x41], // txn NumApprovalProgramPages
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(FALSE), avm.data_stack.pop());
 
+        assert_eq!(9, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(6)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(b"appl".to_vec())), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1000)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x01; 32])), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_txn_field_not_in_version() -> Result<(), AvmError> {
+        let program = [
+            vec![0x01],       // #pragma version 1
+            vec![0x31, 0x18], // txn ApplicationID
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownField("txn", 0x18), err);
+
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x31, 0x44], // txn 68
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownField("txn", 0x44), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_txn_array_field() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x31, 0x1a], // txn ApplicationArgs
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidTxnFieldUse(TxnField::ApplicationArgs), err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_txn_effects() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x31, 0x3d], // txn CreatedApplicationID
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::TxnEffectsNotAvailable, err);
         Ok(())
     }
 
     #[test]
-    fn test_len() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushbytes (empty string)
-        // len
-        let program = [0x09, 0x80, 0x00, 0x15];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
-
-        // #pragma version 9
-        // pushbytes 0xde
-        // len
-        let program = [0x09, 0x80, 0x01, 0xde, 0x15];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-
-        // #pragma version 9
-        // pushbytes 0xdead
-        // len
-        let program = [0x09, 0x80, 0x02, 0xde, 0xad, 0x15];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_txna() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x36, 0x1a, 0x01], // txna ApplicationArgs 1
+            vec![0x36, 0x1c, 0x00], // txna Accounts 0
+            vec![0x36, 0x1c, 0x01], // txna Accounts 1
+            vec![0x36, 0x32, 0x00], // txna Applications 0
+            vec![0x36, 0x32, 0x01], // txna Applications 1
+            vec![0x36, 0x40, 0x01], // txna ApprovalProgramPages 1
+            vec![0x15],             // len
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
 
+        assert_eq!(6, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(5000 - 4096)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(7)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(42)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x02; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x01; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbe, 0xef])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_itob() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0
-        // itob
-        let program = [0x09, 0x81, 0x00, 0x16];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
+    fn test_txna_outside_range() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x36, 0x1c, 0x02], // txna Accounts 2
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
         assert_eq!(
-            Some(AvmData::Bytes(vec![
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
-            ])),
-            avm.data_stack.pop()
+            AvmError::TxnArrayIndexOutOfRange(TxnField::Accounts, 2, 2),
+            err
         );
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint 45647561321464
-        // itob
-        let program = [0x09, 0x81, 0xF8, 0xD7, 0xB5, 0xB5, 0xC2, 0xB0, 0x0A, 0x16];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(
-            Some(AvmData::Bytes(vec![
-                0x00, 0x00, 0x29, 0x84, 0x26, 0xAD, 0x6B, 0xF8,
-            ])),
-            avm.data_stack.pop()
-        );
+    #[test]
+    fn test_txna_scalar_field() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x36, 0x01, 0x00], // txna Fee 0
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidTxnFieldUse(TxnField::Fee), err);
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushint
-        // itob
+    #[test]
+    fn test_txnas() -> Result<(), AvmError> {
         let program = [
-            0x09, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x16,
-        ];
-        let mut avm = Avm::for_program(&program)?;
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0xc0, 0x1a], // txnas ApplicationArgs
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(
-            Some(AvmData::Bytes(vec![
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
-            ])),
-            avm.data_stack.pop()
-        );
 
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde, 0xad])), avm.data_stack.pop());
         Ok(())
     }
 
+    fn test_group() -> Vec<Transaction> {
+        let payment = Transaction {
+            tx_type: TxType::Payment,
+            amount: 5000,
+            ..Default::default()
+        };
+        let app_create = Transaction {
+            tx_type: TxType::ApplicationCall,
+            logs: vec![vec![0xaa], vec![0xbb]],
+            ..Default::default()
+        };
+        vec![payment, app_create, test_transaction()]
+    }
+
     #[test]
-    fn test_btoi() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushbytes (empty string)
-        // btoi
-        let program = [0x09, 0x80, 0x00, 0x17];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxn() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x33, 0x00, 0x08], // gtxn 0 Amount
+            vec![0x33, 0x01, 0x3d], // gtxn 1 CreatedApplicationID
+            vec![0x33, 0x02, 0x16], // gtxn 2 GroupIndex
+            vec![0x31, 0x16],       // txn GroupIndex
+        ]
+        .concat();
+        let mut txn_group = test_group();
+        txn_group[1].created_application_id = 99;
+        let mut avm = Avm::for_group(&program, txn_group, 2)?;
         let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
 
-        // #pragma version 9
-        // pushbytes 0xde
-        // btoi
-        let program = [0x09, 0x80, 0x01, 0xde, 0x17];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(222)), avm.data_stack.pop());
+        assert_eq!(4, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(99)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(5000)), avm.data_stack.pop());
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushbytes 0xdeadbeef
-        // btoi
-        let program = [0x09, 0x80, 0x04, 0xde, 0xad, 0xbe, 0xef, 0x17];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(3735928559)), avm.data_stack.pop());
+    #[test]
+    fn test_gtxn_outside_group() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x33, 0x03, 0x08], // gtxn 3 Amount
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::GroupIndexOutOfRange(3, 3), err);
+        Ok(())
+    }
 
-        // #pragma version 9
-        // pushbytes 0xdeadbeefdeadbeefaa
-        // btoi
+    #[test]
+    fn test_gtxn_effects_of_future_transaction() -> Result<(), AvmError> {
         let program = [
-            0x09, 0x80, 0x09, 0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef, 0xaa, 0x17,
-        ];
-        let mut avm = Avm::for_program(&program)?;
+            vec![0x0a],             // #pragma version 10
+            vec![0x33, 0x01, 0x3b], // gtxn 1 NumLogs
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 0)?;
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::BtoiTooLong(9), err);
+        assert_eq!(AvmError::TxnEffectsNotAvailable, err);
         Ok(())
     }
 
     #[test]
-    fn test_mod() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
-        // pushint 4
-        // %
-        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x04, 0x18];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxna() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x37, 0x01, 0x3a, 0x01], // gtxna 1 Logs 1
+            vec![0x37, 0x02, 0x1a, 0x00], // gtxna 2 ApplicationArgs 0
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xde, 0xad])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbb])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_mod_by_zero() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 10
-        // pushint 0
-        // /
-        let program = vec![0x09, 0x81, 0x0a, 0x81, 0x00, 0x18];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxns() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x00], // pushint 0
+            vec![0x38, 0x10], // gtxns TypeEnum
+            vec![0x81, 0x01], // pushint 1
+            vec![0x38, 0x3e], // gtxns LastLog
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
+        let avm = execute_program(&mut avm)?;
 
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::DivisionByZero, err);
+        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbb])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
 
+    #[test]
+    fn test_gtxns_outside_group() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x10], // pushint 16
+            vec![0x38, 0x10], // gtxns TypeEnum
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::GroupIndexOutOfRange(16, 3), err);
         Ok(())
     }
 
     #[test]
-    fn test_bit_or() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0b000000001000100110111101 (0xBD9302)
-        // pushint 0b110010011011101100100011 (0xA3F6A606)
-        // |
-        let program = vec![
-            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x19,
-        ];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxnsa() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x02],       // pushint 2
+            vec![0x39, 0x1c, 0x01], // gtxnsa Accounts 1
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 1)?;
         let avm = execute_program(&mut avm)?;
 
-        // result:
-        // 0b110010011011101110111111
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(13220799)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x02; 32])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bit_and() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0b000000001000100110111101 (0xBD9302)
-        // pushint 0b110010011011101100100011 (0xA3F6A606)
-        // &
-        let program = vec![
-            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x1a,
-        ];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxnas() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x00],       // pushint 0
+            vec![0xc1, 0x01, 0x3a], // gtxnas 1 Logs
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
         let avm = execute_program(&mut avm)?;
 
-        // result:
-        // 0b1000100100100001
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(35105)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xaa])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bit_xor() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0b000000001000100110111101 (0xBD9302)
-        // pushint 0b110010011011101100100011 (0xA3F6A606)
-        // ^
-        let program = vec![
-            0x09, 0x81, 0xBD, 0x93, 0x02, 0x81, 0xA3, 0xF6, 0xA6, 0x06, 0x1b,
-        ];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_gtxnsas() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x02], // pushint 2
+            vec![0x81, 0x01], // pushint 1
+            vec![0xc3, 0x1a], // gtxnsas ApplicationArgs
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 0)?;
         let avm = execute_program(&mut avm)?;
 
-        // result:
-        // 0b110010010011001010011110
         assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(13185694)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xbe, 0xef])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bit_not() -> Result<(), AvmError> {
-        // #pragma version 9
-        // pushint 0b000000001000100110111101 (0xBD9302)
-        // !
-        let program = vec![0x09, 0x81, 0xBD, 0x93, 0x02, 0x1c];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_for_group_invalid_index() {
+        let program = [0x0a];
+        let err = Avm::for_group(&program, test_group(), 3).unwrap_err();
+        assert_eq!(AvmError::GroupIndexOutOfRange(3, 3), err);
+
+        let err = Avm::for_group(&program, vec![Transaction::default(); 17], 0).unwrap_err();
+        assert_eq!(AvmError::TxnGroupTooLarge(17), err);
+    }
+
+    #[test]
+    fn test_global() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x32, 0x00], // global MinTxnFee
+            vec![0x32, 0x03], // global ZeroAddress
+            vec![0x32, 0x04], // global GroupSize
+            vec![0x32, 0x05], // global LogicSigVersion
+            vec![0x32, 0x06], // global Round
+            vec![0x32, 0x07], // global LatestTimestamp
+            vec![0x32, 0x08], // global CurrentApplicationID
+            vec![0x32, 0x0d], // global CallerApplicationID
+            vec![0x32, 0x11], // global GenesisHash
+        ]
+        .concat();
+        let mut avm = Avm::for_group(&program, test_group(), 2)?;
+        avm.globals = Globals {
+            round: 1234,
+            latest_timestamp: 1700000000,
+            genesis_hash: [0x0f; 32],
+            ..Default::default()
+        };
         let avm = execute_program(&mut avm)?;
 
-        // result:
-        // 0b1111111111111111111111111111111111111111111111110111011001000010
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(
-            Some(AvmData::Uint64(18446744073709516354)),
-            avm.data_stack.pop()
-        );
+        assert_eq!(9, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0x0f; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(42)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1700000000)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1234)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(10)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x00; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1000)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_mulw() -> Result<(), AvmError> {
+    fn test_global_application_addresses() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a], // #pragma version 10
-            vec![
-                0x81, 0xb4, 0xce, 0xb6, 0xf4, 0x81, 0xe3, 0xca, 0xff, 0xff, 0x01,
-            ], // pushint 18446509981324650292
-            vec![
-                0x81, 0xa4, 0xca, 0xc4, 0x90, 0xee, 0xe2, 0xd8, 0xfd, 0xff, 0x01,
-            ], // pushint 18445445648759203108
-            vec![0x1d], // mulw
+            vec![0x0a],       // #pragma version 10
+            vec![0x32, 0x0a], // global CurrentApplicationAddress
+            vec![0x32, 0x0e], // global CallerApplicationAddress
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let txn = Transaction {
+            application_id: 1,
+            ..Default::default()
+        };
+        let mut avm = Avm::for_transaction(&program, txn)?;
         let avm = execute_program(&mut avm)?;
 
-        // the result (in hex 0xfffa8e32d6bd7f742c811b4ac78a0750)
-        // is pushed onto the stack as two 64-bit values
+        // WCS6TVPJRBSARHLN2326LRU5BYVJZUKI2VJ53CAWKYYHDE455ZGKANWMGM
+        let app_address = [
+            0xb0, 0xa5, 0xe9, 0xd5, 0xe9, 0x88, 0x64, 0x08, 0x9d, 0x6d, 0xd6, 0xf5, 0xe5, 0xc6,
+            0x9d, 0x0e, 0x2a, 0x9c, 0xd1, 0x48, 0xd5, 0x53, 0xdd, 0x88, 0x16, 0x56, 0x30, 0x71,
+            0x93, 0x9d, 0xee, 0x4c,
+        ];
         assert_eq!(2, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0x00; 32])), avm.data_stack.pop());
         assert_eq!(
-            // low
-            Some(AvmData::Uint64(0x2c811b4ac78a0750)),
-            avm.data_stack.pop()
-        );
-        assert_eq!(
-            // high
-            Some(AvmData::Uint64(0xfffa8e32d6bd7f74)),
+            Some(AvmData::Bytes(app_address.to_vec())),
             avm.data_stack.pop()
         );
         Ok(())
     }
 
     #[test]
-    fn test_addw() -> Result<(), AvmError> {
+    fn test_global_opcode_budget() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a], // #pragma version 10
-            vec![
-                0x81, 0xb4, 0xce, 0xb6, 0xf4, 0x81, 0xe3, 0xca, 0xff, 0xff, 0x01,
-            ], // pushint 18446509981324650292
-            vec![
-                0x81, 0xa4, 0xca, 0xc4, 0x90, 0xee, 0xe2, 0xd8, 0xfd, 0xff, 0x01,
-            ], // pushint 18445445648759203108
-            vec![0x1e], // addw
+            vec![0x0a],       // #pragma version 10
+            vec![0x81, 0x01], // pushint 1
+            vec![0x48],       // pop
+            vec![0x32, 0x0c], // global OpcodeBudget
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        // the result (in hex 0x0000000000000001fffa8e2f009ecc58)
-        // is pushed onto the stack as two 64-bit values
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(
-            // low
-            Some(AvmData::Uint64(0xfffa8e2f009ecc58)),
-            avm.data_stack.pop()
-        );
-        assert_eq!(
-            // high
-            Some(AvmData::Uint64(0x0000000000000001)),
-            avm.data_stack.pop()
-        );
+        // the cost of global is charged before it reads the budget
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(697)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_divmodw() -> Result<(), AvmError> {
-        // ab = 0xfffa8e32d6bd7f742c811b4ac78a0750
-        // cd = 0x00000000002ccd18797b150ba583e510
-        //  a = 0xfffa8e32d6bd7f74 as varint: f4fef5b5adc6a3fdff01
-        //  b = 0x2c811b4ac78a0750 as varint: d08ea8bcace9c6c02c
-        //  c = 0x00000000002ccd18 as varint: 989ab301
-        //  d = 0x797b150ba583e510 as varint: 90ca8facbaa1c5bd79
+    fn test_cost_budget_exceeded() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a], // #pragma version 10
-            vec![
-                0x81, 0xf4, 0xfe, 0xf5, 0xb5, 0xad, 0xc6, 0xa3, 0xfd, 0xff, 0x01,
-            ], // pushint a
-            vec![0x81, 0xd0, 0x8e, 0xa8, 0xbc, 0xac, 0xe9, 0xc6, 0xc0, 0x2c], // pushint b
-            vec![0x81, 0x98, 0x9a, 0xb3, 0x01], // pushint c
-            vec![0x81, 0x90, 0xca, 0x8f, 0xac, 0xba, 0xa1, 0xc5, 0xbd, 0x79], // pushint d
-            vec![0x1f], // divmodw
+            vec![0x0a],             // #pragma version 10
+            vec![0x42, 0xff, 0xfd], // loop: b loop
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        // the result are two u128s (split into four u64s):
-        // result of division: 0x0000000000000000000005b6b2aa6607 = 6282239698439
-        // result of modulo:   0x000000000029b2807db11bcb770a63e0 = 50408910078442914141856736
-        assert_eq!(4, avm.data_stack.len());
         assert_eq!(
-            // modulo low
-            Some(AvmData::Uint64(0x7db11bcb770a63e0)),
-            avm.data_stack.pop()
+            Err(AvmError::CostBudgetExceeded(701, 700)),
+            execute_program(&mut avm).map(|_| ())
         );
+        let mut avm = Avm::for_logicsig(&program, vec![])?;
         assert_eq!(
-            // modulo high
-            Some(AvmData::Uint64(0x000000000029b280)),
-            avm.data_stack.pop()
+            Err(AvmError::CostBudgetExceeded(20001, 20000)),
+            execute_program(&mut avm).map(|_| ())
         );
-        assert_eq!(
-            // division low
-            Some(AvmData::Uint64(0x000005b6b2aa6607)),
-            avm.data_stack.pop()
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_group_budget() -> Result<(), AvmError> {
+        // costs 901, more than a single app call may spend
+        let spender = [
+            vec![0x0a],                     // #pragma version 10
+            [0x81, 0x01, 0x48].repeat(450), // (pushint 1; pop) * 450
+            vec![0x81, 0x01],               // pushint 1
+        ]
+        .concat();
+        let checker = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x32, 0x0c], // global OpcodeBudget
+            pushint(498),     // pushint 498
+            vec![0x12],       // ==
+        ]
+        .concat();
+        let payment = Transaction {
+            tx_type: TxType::Payment,
+            ..Default::default()
+        };
+
+        // the app calls pool their budgets
+        let evaluated = execute_group(
+            &[Some(&spender[..]), Some(&checker[..])],
+            vec![test_transaction(), test_transaction()],
+            Globals::default(),
+            test_ledger(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+        assert_eq!(901, evaluated[0].as_ref().unwrap().cost);
+
+        // a logic signature neither adds to nor takes from the pool
+        let logicsig = [0x06, 0x81, 0x01]; // pushint 1
+        let result = execute_group(
+            &[Some(&logicsig[..]), Some(&spender[..])],
+            vec![payment.clone(), test_transaction()],
+            Globals::default(),
+            test_ledger(),
+            Rc::new(MemoryBlockHistory::default()),
         );
         assert_eq!(
-            // division high
-            Some(AvmData::Uint64(0x0000000000000000)),
-            avm.data_stack.pop()
+            AvmError::CostBudgetExceeded(701, 700),
+            result.err().unwrap()
         );
+
+        let evaluated = execute_group(
+            &[Some(&logicsig[..])],
+            vec![payment],
+            Globals::default(),
+            test_ledger(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+        let avm = evaluated[0].as_ref().unwrap();
+        assert_eq!(RunMode::Signature, avm.mode);
+        assert_eq!(20000, avm.budget);
         Ok(())
     }
 
     #[test]
-    fn test_store() -> Result<(), AvmError> {
+    fn test_global_field_not_in_version() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x05], // pushint 5
-            vec![0x35, 0x02], // store 2
+            vec![0x09],       // #pragma version 9
+            vec![0x32, 0x11], // global GenesisHash
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(0, avm.data_stack.len());
-        assert_eq!(AvmData::Uint64(5), avm.scratch[2]);
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::UnknownField("global", 0x11), err);
         Ok(())
     }
 
     #[test]
-    fn test_load_unset_location() -> Result<(), AvmError> {
-        // since position 2 wasn't written before, it is the
-        // default value: 0 as uint64
+    fn test_global_in_signature_mode() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x34, 0x02], // load 2
+            vec![0x32, 0x00], // global MinTxnFee
+            vec![0x32, 0x06], // global Round
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        let mut avm = Avm::for_logicsig(&program, vec![])?;
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::InvalidRunMode(RunMode::Application), err);
         Ok(())
     }
 
     #[test]
-    fn test_store_and_load() -> Result<(), AvmError> {
-        // since position 2 wasn't written before, it is the
-        // default value: 0 as uint64
-        let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x81, 0x01],                         // pushint 1
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x35, 0x05],                         // store 5
-            vec![0x35, 0x03],                         // store 3
-            vec![0x34, 0x05],                         // load 5
-            vec![0x34, 0x03],                         // load 3
-            vec![0x34, 0x05],                         // load 5
+    fn test_gload_and_gaid() -> Result<(), AvmError> {
+        let creator = [
+            vec![0x0a],             // #pragma version 10
+            vec![0x81, 0x07],       // pushint 7
+            vec![0x35, 0x03],       // store 3
+            vec![0x80, 0x01, 0xcc], // pushbytes 0xcc
+            vec![0x35, 0x04],       // store 4
+            vec![0x80, 0x01, 0xdd], // pushbytes 0xdd
+            vec![0xb0],             // log
+            vec![0x81, 0x01],       // pushint 1
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(3, avm.data_stack.len());
+        let caller = [
+            vec![0x0a],                   // #pragma version 10
+            vec![0x3a, 0x01, 0x03],       // gload 1 3
+            vec![0x81, 0x01],             // pushint 1
+            vec![0x3b, 0x04],             // gloads 4
+            vec![0x81, 0x01],             // pushint 1
+            vec![0x81, 0x05],             // pushint 5
+            vec![0xc4],                   // gloadss
+            vec![0x3c, 0x01],             // gaid 1
+            vec![0x81, 0x01],             // pushint 1
+            vec![0x3d],                   // gaids
+            vec![0x37, 0x01, 0x3a, 0x00], // gtxna 1 Logs 0
+            vec![0x35, 0x0f],             // store 15
+            vec![0x35, 0x0e],             // store 14
+            vec![0x35, 0x0d],             // store 13
+            vec![0x35, 0x0c],             // store 12
+            vec![0x35, 0x0b],             // store 11
+            vec![0x35, 0x0a],             // store 10
+            vec![0x81, 0x01],             // pushint 1
+        ]
+        .concat();
+        let programs = [None, Some(&creator[..]), Some(&caller[..])];
+        let ledger = test_ledger();
+        let evaluated = execute_group(
+            &programs,
+            test_group(),
+            Globals::default(),
+            ledger.clone(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+
+        // the created app gets the id after the highest id in the ledger
+        assert!(ledger.borrow().apps.contains_key(&43));
+        assert_eq!(3, evaluated.len());
+        assert!(evaluated[0].is_none());
+        let avm = evaluated[2].as_ref().unwrap();
         assert_eq!(
-            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
-            avm.data_stack.pop()
+            [
+                AvmData::Uint64(7),
+                AvmData::Bytes(vec![0xcc]),
+                AvmData::Uint64(0),
+                AvmData::Uint64(43),
+                AvmData::Uint64(43),
+                AvmData::Bytes(vec![0xdd]),
+            ],
+            avm.scratch[10..16]
         );
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gload_from_invalid_transactions() -> Result<(), AvmError> {
+        for (group_index, err) in [
+            (0, AvmError::ScratchNotAvailable(0)),
+            (2, AvmError::ScratchNotAvailable(2)),
+            (3, AvmError::GroupIndexOutOfRange(3, 3)),
+        ] {
+            let program = [
+                vec![0x0a],                    // #pragma version 10
+                vec![0x3a, group_index, 0x00], // gload group_index 0
+            ]
+            .concat();
+            let mut avm = Avm::for_group(&program, test_group(), 2)?;
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_gaid_from_invalid_transactions() -> Result<(), AvmError> {
+        for (group_index, err) in [
+            (0, AvmError::NothingCreated(0)),
+            (2, AvmError::TxnEffectsNotAvailable),
+        ] {
+            let program = [
+                vec![0x0a],              // #pragma version 10
+                vec![0x3c, group_index], // gaid group_index
+            ]
+            .concat();
+            let mut avm = Avm::for_group(&program, test_group(), 2)?;
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_group_creates_asset() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            vec![0x3c, 0x00], // gaid 0
+        ]
+        .concat();
+        let asset_create = Transaction {
+            tx_type: TxType::AssetConfig,
+            sender: [0x01; 32],
+            config_asset_total: 100,
+            ..Default::default()
+        };
+        let ledger = test_ledger();
+        let mut evaluated = execute_group(
+            &[None, Some(&program[..])],
+            vec![asset_create, test_transaction()],
+            Globals::default(),
+            ledger.clone(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+
+        let avm = evaluated[1].as_mut().unwrap();
+        assert_eq!(Some(AvmData::Uint64(43)), avm.data_stack.pop());
         assert_eq!(
-            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
-            avm.data_stack.pop()
+            Some(100),
+            ledger
+                .borrow()
+                .asset_holding(&[0x01; 32], 43)
+                .map(|holding| holding.amount)
         );
         Ok(())
     }
 
     #[test]
-    fn test_loads() -> Result<(), AvmError> {
+    fn test_execute_group_program_count_mismatch() {
+        let program = [0x0a, 0x81, 0x01];
+        let err = execute_group(
+            &[Some(&program[..])],
+            test_group(),
+            Globals::default(),
+            test_ledger(),
+            Rc::new(MemoryBlockHistory::default()),
+        );
+        assert_eq!(AvmError::ProgramCountMismatch(1, 3), err.unwrap_err());
+    }
+
+    // encodes `pushbytes value`
+    fn pushbytes(value: &[u8]) -> Vec<u8> {
+        [vec![0x80], varuint(value.len() as u64), value.to_vec()].concat()
+    }
+
+    // app 42 is the application called by test_transaction, which
+    // its sender created and opted in to, and app 7 is in its
+    // Applications array. The sender also created and holds asset 5.
+    fn test_ledger() -> Rc<RefCell<MemoryLedger>> {
+        let mut ledger = MemoryLedger::default();
+        ledger.accounts.insert(
+            [0x01; 32],
+            Account {
+                balance: 500_000,
+                auth_addr: [0x03; 32],
+                assets: [(
+                    5,
+                    AssetHolding {
+                        amount: 250,
+                        frozen: true,
+                    },
+                )]
+                .into(),
+            },
+        );
+        ledger.accounts.insert(
+            application_address(42),
+            Account {
+                balance: 200_000,
+                ..Default::default()
+            },
+        );
+        ledger.assets.insert(
+            5,
+            AssetParams {
+                total: 1_000_000,
+                decimals: 6,
+                unit_name: b"GLD".to_vec(),
+                name: b"Gold".to_vec(),
+                manager: [0x04; 32],
+                creator: [0x01; 32],
+                ..Default::default()
+            },
+        );
+        ledger.apps.insert(
+            42,
+            Application {
+                creator: [0x01; 32],
+                approval_program: vec![0x0a, 0x81, 0x01],
+                clear_state_program: vec![0x0a, 0x81, 0x01],
+                extra_program_pages: 1,
+                global_schema: StateSchema {
+                    num_uint: 2,
+                    num_byte_slice: 1,
+                },
+                global_state: [(b"counter".to_vec(), AvmData::Uint64(5))].into(),
+                local_schema: StateSchema {
+                    num_uint: 1,
+                    num_byte_slice: 1,
+                },
+                local_states: [(
+                    [0x01; 32],
+                    [(b"points".to_vec(), AvmData::Uint64(10))].into(),
+                )]
+                .into(),
+                boxes: [(b"registry".to_vec(), vec![0xaa; 8])].into(),
+            },
+        );
+        ledger.apps.insert(
+            7,
+            Application {
+                global_state: [(b"name".to_vec(), AvmData::Bytes(b"seven".to_vec()))].into(),
+                ..Default::default()
+            },
+        );
+        Rc::new(RefCell::new(ledger))
+    }
+
+    #[test]
+    fn test_app_global_get() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x35, 0x09],                         // store 9
-            vec![0x81, 0x09],                         // pushint 9
-            vec![0x3e],                               // loads
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            vec![0x64],            // app_global_get
+            pushbytes(b"missing"), // pushbytes "missing"
+            vec![0x64],            // app_global_get
+            pushint(1),            // pushint 1
+            pushbytes(b"name"),    // pushbytes "name"
+            vec![0x65],            // app_global_get_ex
+            pushint(7),            // pushint 7
+            pushbytes(b"counter"), // pushbytes "counter"
+            vec![0x65],            // app_global_get_ex
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(6, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
         assert_eq!(
-            Some(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            Some(AvmData::Bytes(b"seven".to_vec())),
             avm.data_stack.pop()
         );
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_loads_out_of_bounds() -> Result<(), AvmError> {
-        // index 256 is the first position that is no longer
-        // within the scratch space's bounds
+    fn test_app_global_get_ex_unavailable_app() -> Result<(), AvmError> {
+        for (version, reference) in [(0x03, 7), (0x0a, 8), (0x0a, 2)] {
+            let program = [
+                vec![version],      // #pragma version
+                pushint(reference), // pushint reference
+                pushbytes(b"name"), // pushbytes "name"
+                vec![0x65],         // app_global_get_ex
+            ]
+            .concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = test_ledger();
+            let err = execute_program(&mut avm).unwrap_err();
+            assert_eq!(AvmError::AppNotAvailable(reference), err);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_global_put_and_del() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],             // #pragma version 10
-            vec![0x81, 0x80, 0x02], // pushint 256
-            vec![0x3e],             // loads
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            pushint(6),            // pushint 6
+            vec![0x67],            // app_global_put
+            pushbytes(b"owner"),   // pushbytes "owner"
+            pushbytes(b"alice"),   // pushbytes "alice"
+            vec![0x67],            // app_global_put
+            pushbytes(b"total"),   // pushbytes "total"
+            pushint(100),          // pushint 100
+            vec![0x67],            // app_global_put
+            pushbytes(b"counter"), // pushbytes "counter"
+            vec![0x69],            // app_global_del
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::ScratchAccessOutOfBounds(256), err);
+        let ledger = test_ledger();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger.clone();
+        execute_program(&mut avm)?;
+
+        let state = &ledger.borrow().apps[&42].global_state;
+        assert_eq!(2, state.len());
+        assert_eq!(
+            Some(&AvmData::Bytes(b"alice".to_vec())),
+            state.get(&b"owner"[..])
+        );
+        assert_eq!(Some(&AvmData::Uint64(100)), state.get(&b"total"[..]));
         Ok(())
     }
 
     #[test]
-    fn test_stores() -> Result<(), AvmError> {
+    fn test_app_global_put_exceeds_schema() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x81, 0x05],                         // pushint 5
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x3f],                               // stores
+            vec![0x0a],      // #pragma version 10
+            pushbytes(b"a"), // pushbytes "a"
+            pushbytes(b"a"), // pushbytes "a"
+            vec![0x67],      // app_global_put
+            pushbytes(b"b"), // pushbytes "b"
+            pushbytes(b"b"), // pushbytes "b"
+            vec![0x67],      // app_global_put
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::StateSchemaExceeded("bytes", 1), err);
+        Ok(())
+    }
 
-        assert_eq!(0, avm.data_stack.len());
-        assert_eq!(AvmData::Bytes(vec![0xde, 0xad, 0xbe, 0xef]), avm.scratch[5]);
+    #[test]
+    fn test_app_global_put_too_long() -> Result<(), AvmError> {
+        for (key, value, err) in [
+            (vec![0x01; 65], vec![], AvmError::StateKeyTooLong(65)),
+            (
+                vec![0x01; 64],
+                vec![0x02; 65],
+                AvmError::StateValueTooLong(129),
+            ),
+        ] {
+            let program = [
+                vec![0x0a],        // #pragma version 10
+                pushbytes(&key),   // pushbytes key
+                pushbytes(&value), // pushbytes value
+                vec![0x67],        // app_global_put
+            ]
+            .concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = test_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
         Ok(())
     }
 
     #[test]
-    fn test_stores_out_of_bounds() -> Result<(), AvmError> {
-        // index 256 is the first position that is no longer
-        // within the scratch space's bounds
-        let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x81, 0x80, 0x02],                   // pushint 256
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x3f],                               // stores
+    fn test_execute_group_shares_ledger() -> Result<(), AvmError> {
+        let writer = [
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            pushint(6),            // pushint 6
+            vec![0x67],            // app_global_put
+            pushint(1),            // pushint 1
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::ScratchAccessOutOfBounds(256), err);
+        let reader = [
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            vec![0x64],            // app_global_get
+        ]
+        .concat();
+        let programs = [Some(&writer[..]), Some(&reader[..])];
+        let txn_group = vec![test_transaction(), test_transaction()];
+        let mut evaluated = execute_group(
+            &programs,
+            txn_group,
+            Globals::default(),
+            test_ledger(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+
+        let avm = evaluated[1].as_mut().unwrap();
+        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(6)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bz() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],             // #pragma version 10
-            vec![0x81, 0x03],       // pushint 3
-            vec![0x81, 0x02],       // pushint 2
-            vec![0x0c],             // <
-            vec![0x41, 0x00, 0x06], // bz 0x0006
-            vec![0x81, 0x01],       // pushint 1
-            vec![0x43],             // return
-            vec![0x42, 0x00, 0x03], // b 0x0003
-            vec![0x81, 0x00],       // pushint 0
-            vec![0x43],             // return
+    fn test_execute_group_rejected() -> Result<(), AvmError> {
+        let writer = [
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            pushint(6),            // pushint 6
+            vec![0x67],            // app_global_put
+            pushint(1),            // pushint 1
+        ]
+        .concat();
+        for (rejecter, err) in [
+            (
+                [vec![0x0a], pushint(0)].concat(), // pushint 0
+                AvmError::ProgramRejected(1),
+            ),
+            (
+                [vec![0x0a], pushint(1), pushint(2)].concat(), // pushint 1; pushint 2
+                AvmError::ProgramRejected(1),
+            ),
+            (vec![0x0a, 0x00], AvmError::ErrOpCode), // err
+        ] {
+            let ledger = test_ledger();
+            let result = execute_group(
+                &[Some(&writer[..]), Some(&rejecter[..])],
+                vec![test_transaction(), test_transaction()],
+                Globals::default(),
+                ledger.clone(),
+                Rc::new(MemoryBlockHistory::default()),
+            );
+            assert_eq!(err, result.err().unwrap());
+            assert_eq!(*test_ledger().borrow(), *ledger.borrow());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_local_get() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],             // #pragma version 10
+            pushint(0),             // pushint 0
+            pushbytes(b"points"),   // pushbytes "points"
+            vec![0x62],             // app_local_get
+            pushbytes(&[0x01; 32]), // pushbytes sender
+            pushint(42),            // pushint 42
+            vec![0x61],             // app_opted_in
+            pushint(1),             // pushint 1
+            pushint(0),             // pushint 0
+            vec![0x61],             // app_opted_in
+            pushint(0),             // pushint 0
+            pushint(1),             // pushint 1
+            pushbytes(b"points"),   // pushbytes "points"
+            vec![0x63],             // app_local_get_ex
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
+        assert_eq!(5, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(10)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bnz() -> Result<(), AvmError> {
+    fn test_app_local_put_and_del() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],             // #pragma version 10
-            vec![0x81, 0x02],       // pushint 2
-            vec![0x81, 0x03],       // pushint 3
-            vec![0x0c],             // <
-            vec![0x40, 0x00, 0x06], // bnz 0x0006
-            vec![0x81, 0x00],       // pushint 0
-            vec![0x43],             // return
-            vec![0x42, 0x00, 0x03], // b 0x0003
-            vec![0x81, 0x01],       // pushint 1
-            vec![0x43],             // return
+            pushbytes(&[0x01; 32]), // pushbytes sender
+            pushbytes(b"level"),    // pushbytes "level"
+            pushbytes(b"gold"),     // pushbytes "gold"
+            vec![0x66],             // app_local_put
+            pushint(0),             // pushint 0
+            pushbytes(b"points"),   // pushbytes "points"
+            vec![0x68],             // app_local_del
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        let ledger = test_ledger();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger.clone();
+        execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        let state = &ledger.borrow().apps[&42].local_states[&[0x01; 32]];
+        assert_eq!(1, state.len());
+        assert_eq!(
+            Some(&AvmData::Bytes(b"gold".to_vec())),
+            state.get(&b"level"[..])
+        );
         Ok(())
     }
 
     #[test]
-    fn test_b() -> Result<(), AvmError> {
+    fn test_app_local_put_not_opted_in() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],             // #pragma version 10
-            vec![0x42, 0x00, 0x04], // bnz 0x0004
-            vec![0x81, 0x01],       // pushint 1
-            vec![0x81, 0x02],       // pushint 2
-            vec![0x81, 0x01],       // pushint 1
-            vec![0x43],             // return
+            vec![0x0a],          // #pragma version 10
+            pushint(1),          // pushint 1
+            pushbytes(b"level"), // pushbytes "level"
+            pushint(1),          // pushint 1
+            vec![0x66],          // app_local_put
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
+        let err = execute_program(&mut avm).unwrap_err();
+        assert_eq!(AvmError::NotOptedIn(42), err);
+        Ok(())
+    }
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+    #[test]
+    fn test_account_references() -> Result<(), AvmError> {
+        let app_address = application_address(7).to_vec();
+        for (version, account, result) in [
+            (0x03, pushint(1), Ok(FALSE)),
+            (0x03, pushint(2), Err(AvmError::AccountNotAvailable)),
+            (
+                0x03,
+                pushbytes(&[0x02; 32]),
+                Err(AvmError::IncompatibleTypes("bytes", "uint64")),
+            ),
+            (0x04, pushbytes(&[0x02; 32]), Ok(FALSE)),
+            (
+                0x04,
+                pushbytes(&[0x09; 32]),
+                Err(AvmError::AccountNotAvailable),
+            ),
+            (
+                0x06,
+                pushbytes(&app_address),
+                Err(AvmError::AccountNotAvailable),
+            ),
+            (0x07, pushbytes(&app_address), Ok(FALSE)),
+        ] {
+            let program = [
+                vec![version], // #pragma version
+                account,       // push account reference
+                pushint(0),    // pushint 0
+                vec![0x61],    // app_opted_in
+            ]
+            .concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = test_ledger();
+            match execute_program(&mut avm) {
+                Ok(avm) => assert_eq!(result, avm.pop_any()),
+                Err(err) => assert_eq!(result, Err(err)),
+            }
+        }
         Ok(())
     }
 
     #[test]
-    fn test_return() -> Result<(), AvmError> {
-        // #pragma version 10
-        // pushint 1
-        // pushint 2
-        // pushint 3
-        // return
-        let program = vec![0x0a, 0x81, 0x01, 0x81, 0x02, 0x81, 0x03, 0x43];
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+    fn test_execute_group_opt_in_and_close_out() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],           // #pragma version 10
+            pushint(0),           // pushint 0
+            pushbytes(b"points"), // pushbytes "points"
+            pushint(1),           // pushint 1
+            vec![0x66],           // app_local_put
+            pushint(1),           // pushint 1
+        ]
+        .concat();
+        let ledger = test_ledger();
+        let opt_in = Transaction {
+            sender: [0x02; 32],
+            ..test_transaction()
+        };
+        execute_group(
+            &[Some(&program[..])],
+            vec![opt_in],
+            Globals::default(),
+            ledger.clone(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+        assert!(ledger.borrow().app_opted_in(42, &[0x02; 32]));
+        assert_eq!(
+            Some(AvmData::Uint64(1)),
+            ledger.borrow().app_local_get(42, &[0x02; 32], b"points")
+        );
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        let close_out = Transaction {
+            sender: [0x02; 32],
+            on_completion: OnCompletion::CloseOut,
+            ..test_transaction()
+        };
+        execute_group(
+            &[Some(&program[..])],
+            vec![close_out],
+            Globals::default(),
+            ledger.clone(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+        assert!(!ledger.borrow().app_opted_in(42, &[0x02; 32]));
+        assert!(ledger.borrow().app_opted_in(42, &[0x01; 32]));
         Ok(())
     }
 
     #[test]
-    fn test_assert() -> Result<(), AvmError> {
+    fn test_execute_group_rejected_opt_in_and_close_out() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x05], // pushint 5
-            vec![0x44],       // assert
+            vec![0x0a],           // #pragma version 10
+            pushint(0),           // pushint 0
+            pushbytes(b"points"), // pushbytes "points"
+            pushint(1),           // pushint 1
+            vec![0x66],           // app_local_put
+            pushint(0),           // pushint 0
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        let ledger = test_ledger();
+        for (sender, on_completion) in [
+            ([0x02; 32], OnCompletion::OptIn),
+            ([0x01; 32], OnCompletion::CloseOut),
+        ] {
+            let txn = Transaction {
+                sender,
+                on_completion,
+                ..test_transaction()
+            };
+            let result = execute_group(
+                &[Some(&program[..])],
+                vec![txn],
+                Globals::default(),
+                ledger.clone(),
+                Rc::new(MemoryBlockHistory::default()),
+            );
+            assert_eq!(AvmError::ProgramRejected(0), result.err().unwrap());
+            assert_eq!(*test_ledger().borrow(), *ledger.borrow());
+        }
 
-        assert_eq!(0, avm.data_stack.len());
+        // clearing the state succeeds even if the program rejects,
+        // but the changes of the program are undone
+        let clear_state = Transaction {
+            on_completion: OnCompletion::ClearState,
+            ..test_transaction()
+        };
+        let put_global = [
+            vec![0x0a],            // #pragma version 10
+            pushbytes(b"counter"), // pushbytes "counter"
+            pushint(6),            // pushint 6
+            vec![0x67],            // app_global_put
+            pushint(0),            // pushint 0
+        ]
+        .concat();
+        execute_group(
+            &[Some(&put_global[..])],
+            vec![clear_state],
+            Globals::default(),
+            ledger.clone(),
+            Rc::new(MemoryBlockHistory::default()),
+        )?;
+        assert!(!ledger.borrow().app_opted_in(42, &[0x01; 32]));
+        assert_eq!(
+            Some(AvmData::Uint64(5)),
+            ledger.borrow().app_global_get(42, b"counter")
+        );
         Ok(())
     }
 
+    // references the boxes "registry" and "new" of app 42,
+    // which gives a box quota of 2048 bytes
+    fn box_transaction() -> Transaction {
+        let boxes = [b"registry".to_vec(), b"new".to_vec()]
+            .into_iter()
+            .map(|name| BoxReference { app_index: 0, name })
+            .collect();
+        Transaction {
+            boxes,
+            ..test_transaction()
+        }
+    }
+
     #[test]
-    fn test_assert_negative() -> Result<(), AvmError> {
+    fn test_box_get_and_len() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x00], // pushint 0
-            vec![0x44],       // assert
+            vec![0x0a],             // #pragma version 10
+            pushbytes(b"registry"), // pushbytes "registry"
+            vec![0xbe],             // box_get
+            pushbytes(b"new"),      // pushbytes "new"
+            vec![0xbd],             // box_len
+            pushbytes(b"registry"), // pushbytes "registry"
+            pushint(2),             // pushint 2
+            pushint(3),             // pushint 3
+            vec![0xba],             // box_extract
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::AssertionFailed(3), err);
+        let mut avm = Avm::for_transaction(&program, box_transaction())?;
+        avm.ledger = test_ledger();
+        let avm = execute_program(&mut avm)?;
+
+        assert_eq!(5, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Bytes(vec![0xaa; 3])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0xaa; 8])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_bury() -> Result<(), AvmError> {
+    fn test_box_create_replace_and_del() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x81, 0x05], // pushint 5
-            vec![0x45, 0x04], // bury 4
+            vec![0x0a],               // #pragma version 10
+            pushbytes(b"new"),        // pushbytes "new"
+            pushint(4),               // pushint 4
+            vec![0xb9],               // box_create
+            pushbytes(b"new"),        // pushbytes "new"
+            pushint(4),               // pushint 4
+            vec![0xb9],               // box_create
+            pushbytes(b"new"),        // pushbytes "new"
+            pushint(1),               // pushint 1
+            pushbytes(&[0x01, 0x02]), // pushbytes 0x0102
+            vec![0xbb],               // box_replace
+            pushbytes(b"registry"),   // pushbytes "registry"
+            vec![0xbc],               // box_del
+            pushbytes(b"registry"),   // pushbytes "registry"
+            vec![0xbc],               // box_del
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let ledger = test_ledger();
+        let mut avm = Avm::for_transaction(&program, box_transaction())?;
+        avm.ledger = ledger.clone();
         let avm = execute_program(&mut avm)?;
 
         assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        let boxes = &ledger.borrow().apps[&42].boxes;
+        assert_eq!(1, boxes.len());
+        assert_eq!(Some(&vec![0x00, 0x01, 0x02, 0x00]), boxes.get(&b"new"[..]));
         Ok(())
     }
 
     #[test]
-    fn test_bury_zero() -> Result<(), AvmError> {
+    fn test_box_put_splice_and_resize() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x45, 0x00], // bury 0
+            vec![0x0a],                  // #pragma version 10
+            pushbytes(b"new"),           // pushbytes "new"
+            pushbytes(&[1, 2, 3, 4, 5]), // pushbytes 0x0102030405
+            vec![0xbf],                  // box_put
+            pushbytes(b"new"),           // pushbytes "new"
+            pushint(1),                  // pushint 1
+            pushint(1),                  // pushint 1
+            pushbytes(&[6, 7]),          // pushbytes 0x0607
+            vec![0xd2],                  // box_splice
+            pushbytes(b"registry"),      // pushbytes "registry"
+            pushint(0),                  // pushint 0
+            pushint(6),                  // pushint 6
+            pushbytes(&[9]),             // pushbytes 0x09
+            vec![0xd2],                  // box_splice
+            pushbytes(b"registry"),      // pushbytes "registry"
+            pushint(4),                  // pushint 4
+            vec![0xd3],                  // box_resize
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidStackAccess, err);
+        let ledger = test_ledger();
+        let mut avm = Avm::for_transaction(&program, box_transaction())?;
+        avm.ledger = ledger.clone();
+        execute_program(&mut avm)?;
+
+        let boxes = &ledger.borrow().apps[&42].boxes;
+        assert_eq!(Some(&vec![1, 6, 7, 3, 4]), boxes.get(&b"new"[..]));
+        assert_eq!(Some(&vec![9, 0xaa, 0xaa, 0]), boxes.get(&b"registry"[..]));
         Ok(())
     }
 
     #[test]
-    fn test_bury_outside_range() -> Result<(), AvmError> {
+    fn test_box_errors() -> Result<(), AvmError> {
+        for (operation, err) in [
+            (
+                [pushbytes(b"other"), vec![0xbd]].concat(),
+                AvmError::BoxNotAvailable(b"other".to_vec()),
+            ),
+            (
+                [pushbytes(b""), vec![0xbd]].concat(),
+                AvmError::InvalidBoxName(0),
+            ),
+            (
+                [pushbytes(b"new"), pushint(32769), vec![0xb9]].concat(),
+                AvmError::BoxTooLarge(32769),
+            ),
+            (
+                [pushbytes(b"new"), pushint(2041), vec![0xb9]].concat(),
+                AvmError::BoxQuotaExceeded(2049, 2048),
+            ),
+            (
+                [pushbytes(b"registry"), pushint(9), vec![0xb9]].concat(),
+                AvmError::BoxSizeMismatch(8, 9),
+            ),
+            (
+                [pushbytes(b"registry"), pushbytes(&[0x01]), vec![0xbf]].concat(),
+                AvmError::BoxSizeMismatch(8, 1),
+            ),
+            (
+                [pushbytes(b"new"), pushint(0), pushint(1), vec![0xba]].concat(),
+                AvmError::BoxNotFound(b"new".to_vec()),
+            ),
+            (
+                [pushbytes(b"registry"), pushint(4), pushint(5), vec![0xba]].concat(),
+                AvmError::InvalidSubstringAccess(4, 9, 8),
+            ),
+            (
+                [
+                    pushbytes(b"registry"),
+                    pushint(7),
+                    pushbytes(&[1, 2]),
+                    vec![0xbb],
+                ]
+                .concat(),
+                AvmError::InvalidReplacement(7, 2, 8),
+            ),
+        ] {
+            let program = [vec![0x0a], operation].concat();
+            let mut avm = Avm::for_transaction(&program, box_transaction())?;
+            avm.ledger = test_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_balance_and_min_balance() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x45, 0x03], // bury 3
+            vec![0x0a], // #pragma version 10
+            pushint(0), // pushint 0
+            vec![0x60], // balance
+            pushint(0), // pushint 0
+            vec![0x78], // min_balance
+            pushint(1), // pushint 1
+            vec![0x78], // min_balance
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidStackAccess, err);
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
+        let avm = execute_program(&mut avm)?;
+
+        // the sender holds one asset, created app 42 with one extra page and
+        // a schema of 2 uints and 1 byte slice, and opted in to app 42 with a
+        // local schema of 1 uint and 1 byte slice
+        let min_balance =
+            100_000 + 100_000 + 2 * 100_000 + 100_000 + 5 * 25_000 + 3 * 3_500 + 2 * 25_000;
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(100_000)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(min_balance)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(500_000)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_popn() -> Result<(), AvmError> {
+    fn test_acct_params_get() -> Result<(), AvmError> {
+        let app_address = application_address(42);
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x81, 0x05], // pushint 5
-            vec![0x46, 0x03], // popn 3
+            vec![0x0a],              // #pragma version 10
+            pushint(0),              // pushint 0
+            vec![0x73, 0x02],        // acct_params_get AcctAuthAddr
+            pushbytes(&app_address), // pushbytes app address
+            vec![0x73, 0x0b],        // acct_params_get AcctTotalBoxBytes
+            pushbytes(&app_address), // pushbytes app address
+            vec![0x73, 0x01],        // acct_params_get AcctMinBalance
+            pushint(1),              // pushint 1
+            vec![0x73, 0x00],        // acct_params_get AcctBalance
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(8, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        // one box with a name and contents of 8 bytes each
+        assert_eq!(
+            Some(AvmData::Uint64(100_000 + 2_500 + 16 * 400)),
+            avm.data_stack.pop()
+        );
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(16)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x03; 32])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_popn_underflow() -> Result<(), AvmError> {
+    fn test_acct_params_get_field_not_in_version() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x46, 0x03], // popn 3
+            vec![0x07],       // #pragma version 7
+            pushint(0),       // pushint 0
+            vec![0x73, 0x03], // acct_params_get AcctTotalNumUint
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
         let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::StackUnderflow, err);
+        assert_eq!(AvmError::UnknownField("acct_params", 0x03), err);
         Ok(())
     }
 
     #[test]
-    fn test_dupn() -> Result<(), AvmError> {
+    fn test_asset_holding_get() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x47, 0x03], // dupn 3
+            pushint(0),       // pushint 0
+            pushint(5),       // pushint 5
+            vec![0x70, 0x00], // asset_holding_get AssetBalance
+            pushint(0),       // pushint 0
+            pushint(0),       // pushint 0
+            vec![0x70, 0x01], // asset_holding_get AssetFrozen
+            pushint(1),       // pushint 1
+            pushint(6),       // pushint 6
+            vec![0x70, 0x00], // asset_holding_get AssetBalance
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let txn = Transaction {
+            assets: vec![5, 6],
+            ..test_transaction()
+        };
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(6, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(250)), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_dup() -> Result<(), AvmError> {
-        // #pragma version 10
-        // pushint 1
-        // dup
-        let program = vec![0x0a, 0x81, 0x01, 0x49];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_asset_params_get() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            pushint(5),       // pushint 5
+            vec![0x71, 0x03], // asset_params_get AssetUnitName
+            pushint(1),       // pushint 1
+            vec![0x71, 0x00], // asset_params_get AssetTotal
+            pushint(0),       // pushint 0
+            vec![0x71, 0x0b], // asset_params_get AssetCreator
+        ]
+        .concat();
+        let txn = Transaction {
+            assets: vec![5, 6],
+            ..test_transaction()
+        };
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(2, avm.data_stack.len());
+        assert_eq!(6, avm.data_stack.len());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x01; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(b"GLD".to_vec())), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_dup_on_empty_stack() -> Result<(), AvmError> {
-        // #pragma version 10
-        // dup
-        let program = vec![0x0a, 0x49];
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::StackUnderflow, err);
+    fn test_asset_opcode_errors() -> Result<(), AvmError> {
+        for (version, operation, err) in [
+            (
+                0x03,
+                [pushint(5), vec![0x71, 0x00]].concat(),
+                AvmError::AssetNotAvailable(5),
+            ),
+            (
+                0x04,
+                [pushint(5), vec![0x71, 0x0b]].concat(),
+                AvmError::UnknownField("asset_params", 0x0b),
+            ),
+            (
+                0x0a,
+                [pushint(0), pushint(5), vec![0x70, 0x02]].concat(),
+                AvmError::UnknownField("asset_holding", 0x02),
+            ),
+        ] {
+            let program = [vec![version], operation].concat();
+            let txn = Transaction {
+                assets: vec![5, 6],
+                ..test_transaction()
+            };
+            let mut avm = Avm::for_transaction(&program, txn)?;
+            avm.ledger = test_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
         Ok(())
     }
 
     #[test]
-    fn test_dup2() -> Result<(), AvmError> {
-        // #pragma version 10
-        // pushint 1
-        // pushbytes 0xde
-        // dup2
-        let program = vec![0x0a, 0x81, 0x01, 0x80, 0x01, 0xde, 0x4a];
-        let mut avm = Avm::for_program(&program)?;
+    fn test_app_params_get() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],       // #pragma version 10
+            pushint(42),      // pushint 42
+            vec![0x72, 0x00], // app_params_get AppApprovalProgram
+            pushint(42),      // pushint 42
+            vec![0x72, 0x02], // app_params_get AppGlobalNumUint
+            pushint(0),       // pushint 0
+            vec![0x72, 0x08], // app_params_get AppAddress
+            pushint(1),       // pushint 1
+            vec![0x72, 0x07], // app_params_get AppCreator
+            pushint(9),       // pushint 9
+            vec![0x72, 0x06], // app_params_get AppExtraProgramPages
+        ]
+        .concat();
+        let txn = Transaction {
+            applications: vec![7, 9],
+            ..test_transaction()
+        };
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = test_ledger();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Bytes(vec![0xde])), avm.data_stack.pop());
+        assert_eq!(10, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Bytes(vec![0xde])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(vec![0x00; 32])), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(application_address(42).to_vec())),
+            avm.data_stack.pop()
+        );
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(vec![0x0a, 0x81, 0x01])),
+            avm.data_stack.pop()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_dup2_on_too_short_stack() -> Result<(), AvmError> {
-        // #pragma version 10
-        // pushbytes 0xde
-        // dup2
-        let program = vec![0x0a, 0x80, 0x01, 0xde, 0x4a];
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::StackUnderflow, err);
+    fn test_app_params_get_errors() -> Result<(), AvmError> {
+        for (version, operation, err) in [
+            (
+                0x0a,
+                [pushint(42), vec![0x72, 0x09]].concat(),
+                AvmError::UnknownField("app_params", 0x09),
+            ),
+            (
+                0x0a,
+                [pushint(8), vec![0x72, 0x00]].concat(),
+                AvmError::AppNotAvailable(8),
+            ),
+            (
+                0x04,
+                [pushint(42), vec![0x72, 0x00]].concat(),
+                AvmError::UnknownOpcode(0x72),
+            ),
+        ] {
+            let program = [vec![version], operation].concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = test_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
         Ok(())
     }
 
-    #[test]
-    fn test_dig() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x81, 0x05], // pushint 5
-            vec![0x4b, 0x03], // dig 3
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(6, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-        Ok(())
+    // the application account of test_transaction with enough
+    // balance to cover the minimum balance of new holdings
+    fn inner_ledger() -> Rc<RefCell<MemoryLedger>> {
+        let ledger = test_ledger();
+        ledger
+            .borrow_mut()
+            .set_balance(&application_address(42), 1_000_000);
+        ledger.borrow_mut().set_balance(&[0x02; 32], 100_000);
+        ledger
     }
 
     #[test]
-    fn test_dig_zero() -> Result<(), AvmError> {
+    fn test_itxn_payment() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x4b, 0x00], // dig 0
+            vec![0x0a],             // #pragma version 10
+            vec![0xb1],             // itxn_begin
+            pushint(1),             // pushint 1
+            vec![0xb2, 0x10],       // itxn_field TypeEnum
+            pushint(5000),          // pushint 5000
+            vec![0xb2, 0x08],       // itxn_field Amount
+            pushbytes(&[0x02; 32]), // pushbytes 0x0202...
+            vec![0xb2, 0x07],       // itxn_field Receiver
+            vec![0xb6],             // itxn_next
+            pushint(1),             // pushint 1
+            vec![0xb2, 0x10],       // itxn_field TypeEnum
+            pushint(2000),          // pushint 2000
+            vec![0xb2, 0x08],       // itxn_field Amount
+            pushbytes(&[0x02; 32]), // pushbytes 0x0202...
+            vec![0xb2, 0x07],       // itxn_field Receiver
+            vec![0xb3],             // itxn_submit
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        // the top-level transaction overpays by 1500, which
+        // covers the fee of the first inner transaction
+        let txn = Transaction {
+            fee: 2500,
+            ..test_transaction()
+        };
+        let ledger = inner_ledger();
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = ledger.clone();
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(3, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(2, avm.inner_txn_count);
+        assert_eq!(Some(0), avm.fee_credit);
+        let ledger = ledger.borrow();
+        assert_eq!(
+            1_000_000 - 7000 - 500,
+            ledger.account_params(&application_address(42)).balance
+        );
+        assert_eq!(107_000, ledger.account_params(&[0x02; 32]).balance);
         Ok(())
     }
 
     #[test]
-    fn test_dig_outside_range() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x4b, 0x02], // dig 2
+    fn test_itxn_assets() -> Result<(), AvmError> {
+        let program = [
+            vec![0x0a],        // #pragma version 10
+            vec![0xb1],        // itxn_begin
+            pushint(4),        // pushint 4
+            vec![0xb2, 0x10],  // itxn_field TypeEnum
+            pushint(5),        // pushint 5
+            vec![0xb2, 0x11],  // itxn_field XferAsset
+            vec![0x32, 0x0a],  // global CurrentApplicationAddress
+            vec![0xb2, 0x14],  // itxn_field AssetReceiver
+            vec![0xb6],        // itxn_next
+            pushint(3),        // pushint 3
+            vec![0xb2, 0x10],  // itxn_field TypeEnum
+            pushint(1000),     // pushint 1000
+            vec![0xb2, 0x22],  // itxn_field ConfigAssetTotal
+            pushbytes(b"TOK"), // pushbytes "TOK"
+            vec![0xb2, 0x25],  // itxn_field ConfigAssetUnitName
+            vec![0xb3],        // itxn_submit
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidStackAccess, err);
+        let txn = Transaction {
+            assets: vec![5],
+            ..test_transaction()
+        };
+        let ledger = inner_ledger();
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = ledger.clone();
+        execute_program(&mut avm)?;
+
+        let ledger = ledger.borrow();
+        let app_address = application_address(42);
+        assert_eq!(
+            Some(AssetHolding::default()),
+            ledger.asset_holding(&app_address, 5)
+        );
+        // the created asset takes the next free id
+        let params = ledger.asset_params(43).unwrap();
+        assert_eq!(1000, params.total);
+        assert_eq!(b"TOK".to_vec(), params.unit_name);
+        assert_eq!(app_address, params.creator);
+        assert_eq!(
+            Some(AssetHolding {
+                amount: 1000,
+                frozen: false
+            }),
+            ledger.asset_holding(&app_address, 43)
+        );
         Ok(())
     }
 
     #[test]
-    fn test_swap() -> Result<(), AvmError> {
+    fn test_itxn_app_call() -> Result<(), AvmError> {
         let program = [
+            vec![0x0a],                     // #pragma version 10
+            vec![0xb1],                     // itxn_begin
+            pushint(6),                     // pushint 6
+            vec![0xb2, 0x10],               // itxn_field TypeEnum
+            pushint(7),                     // pushint 7
+            vec![0xb2, 0x18],               // itxn_field ApplicationID
+            vec![0xb6],                     // itxn_next
+            pushint(6),                     // pushint 6
+            vec![0xb2, 0x10],               // itxn_field TypeEnum
+            pushbytes(&[0x0a, 0x81, 0x01]), // pushbytes 0x0a8101
+            vec![0xb2, 0x1e],               // itxn_field ApprovalProgram
+            pushbytes(&[0x0a, 0x81, 0x01]), // pushbytes 0x0a8101
+            vec![0xb2, 0x1f],               // itxn_field ClearStateProgram
+            vec![0xb3],                     // itxn_submit
+        ]
+        .concat();
+        let ledger = inner_ledger();
+        // app 7 approves calls from app 42 only
+        ledger
+            .borrow_mut()
+            .apps
+            .get_mut(&7)
+            .unwrap()
+            .approval_program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x4c],       // swap
+            vec![0x32, 0x0d], // global CallerApplicationID
+            pushint(42),      // pushint 42
+            vec![0x12],       // ==
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger.clone();
+        execute_program(&mut avm)?;
 
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        Ok(())
-    }
+        let params = ledger.borrow().app_params(43).unwrap();
+        assert_eq!(application_address(42), params.creator);
+        assert_eq!(vec![0x0a, 0x81, 0x01], params.approval_program);
 
-    #[test]
-    fn test_swap_with_too_few_elements() -> Result<(), AvmError> {
+        // called by any other application, app 7 rejects
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x4c],       // swap
+            vec![0xb1],       // itxn_begin
+            pushint(6),       // pushint 6
+            vec![0xb2, 0x10], // itxn_field TypeEnum
+            pushint(7),       // pushint 7
+            vec![0xb2, 0x18], // itxn_field ApplicationID
+            vec![0xb3],       // itxn_submit
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::StackUnderflow, err);
+        let txn = Transaction {
+            application_id: 43,
+            ..test_transaction()
+        };
+        ledger
+            .borrow_mut()
+            .set_balance(&application_address(43), 1_000_000);
+        let mut avm = Avm::for_transaction(&program, txn)?;
+        avm.ledger = ledger;
+        assert_eq!(
+            AvmError::InnerAppRejected(7),
+            execute_program(&mut avm).unwrap_err()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_select_choose_first() -> Result<(), AvmError> {
+    fn test_itxn_app_call_budget() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x00], // pushint 0
-            vec![0x4d],       // select
+            vec![0xb1],       // itxn_begin
+            pushint(6),       // pushint 6
+            vec![0xb2, 0x10], // itxn_field TypeEnum
+            pushint(7),       // pushint 7
+            vec![0xb2, 0x18], // itxn_field ApplicationID
+            vec![0xb3],       // itxn_submit
+            vec![0x32, 0x0c], // global OpcodeBudget
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        // app 7 spends more than a single app call may
+        let ledger = inner_ledger();
+        ledger
+            .borrow_mut()
+            .apps
+            .get_mut(&7)
+            .unwrap()
+            .approval_program = [
+            vec![0x0a],                     // #pragma version 10
+            [0x81, 0x01, 0x48].repeat(450), // (pushint 1; pop) * 450
+            vec![0x81, 0x01],               // pushint 1
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        // the inner app call adds 700 to the budget, and spends 901 of it
+        assert_eq!(vec![AvmData::Uint64(2 * 700 - 7 - 901)], avm.data_stack);
         Ok(())
     }
 
     #[test]
-    fn test_select_choose_second() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x01], // pushint 1
-            vec![0x4d],       // select
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+    fn test_itxn_app_call_close_out_and_clear_state() -> Result<(), AvmError> {
+        let close_app = |on_completion: OnCompletion| {
+            [
+                vec![0x0a],                    // #pragma version 10
+                vec![0xb1],                    // itxn_begin
+                pushint(6),                    // pushint 6
+                vec![0xb2, 0x10],              // itxn_field TypeEnum
+                pushint(7),                    // pushint 7
+                vec![0xb2, 0x18],              // itxn_field ApplicationID
+                pushint(on_completion as u64), // pushint on_completion
+                vec![0xb2, 0x19],              // itxn_field OnCompletion
+                vec![0xb3],                    // itxn_submit
+                pushint(1),                    // pushint 1
+            ]
+            .concat()
+        };
+        let ledger = inner_ledger();
+        {
+            let mut ledger = ledger.borrow_mut();
+            let app = ledger.apps.get_mut(&7).unwrap();
+            app.approval_program = vec![0x0a, 0x81, 0x01];
+            // deletes the name of app 7, and rejects
+            app.clear_state_program = [
+                vec![0x0a],         // #pragma version 10
+                pushbytes(b"name"), // pushbytes "name"
+                vec![0x69],         // app_global_del
+                pushint(0),         // pushint 0
+            ]
+            .concat();
+        }
+        for on_completion in [OnCompletion::CloseOut, OnCompletion::ClearState] {
+            ledger.borrow_mut().app_opt_in(7, &application_address(42));
+            let program = close_app(on_completion);
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = ledger.clone();
+            execute_program(&mut avm)?;
+
+            assert!(!ledger.borrow().app_opted_in(7, &application_address(42)));
+        }
+        // the local state is cleared although the clear state program
+        // rejects, but the changes of the program are undone
+        assert_eq!(
+            Some(AvmData::Bytes(b"seven".to_vec())),
+            ledger.borrow().app_global_get(7, b"name")
+        );
+        Ok(())
+    }
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+    #[test]
+    fn test_itxn_app_call_limits() -> Result<(), AvmError> {
+        let call_app = |app_id: u64| {
+            [
+                vec![0xb1],       // itxn_begin
+                pushint(6),       // pushint 6
+                vec![0xb2, 0x10], // itxn_field TypeEnum
+                pushint(app_id),  // pushint app_id
+                vec![0xb2, 0x18], // itxn_field ApplicationID
+                pushint(42),      // pushint 42
+                vec![0xb2, 0x32], // itxn_field Applications
+                vec![0xb3],       // itxn_submit
+                pushint(1),       // pushint 1
+            ]
+            .concat()
+        };
+        // app 7 calls back into app 42, which is still being evaluated
+        let ledger = inner_ledger();
+        ledger
+            .borrow_mut()
+            .apps
+            .get_mut(&7)
+            .unwrap()
+            .approval_program = [vec![0x0a], call_app(42)].concat();
+        ledger
+            .borrow_mut()
+            .set_balance(&application_address(7), 1_000_000);
+        let program = [vec![0x0a], call_app(7)].concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger.clone();
+        assert_eq!(
+            AvmError::AppReentrance(42),
+            execute_program(&mut avm).unwrap_err()
+        );
+
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger;
+        avm.app_call_stack = vec![100, 101, 102, 103, 104, 105, 106, 107];
+        assert_eq!(
+            AvmError::AppCallDepthExceeded(8),
+            execute_program(&mut avm).unwrap_err()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_cover_at_bottom() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x4e, 0x03], // cover 3
+    fn test_itxn_errors() -> Result<(), AvmError> {
+        let begin_payment = [
+            vec![0xb1],       // itxn_begin
+            pushint(1),       // pushint 1
+            vec![0xb2, 0x10], // itxn_field TypeEnum
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
+        for (version, operation, err) in [
+            (
+                0x0a,
+                [pushint(1), vec![0xb2, 0x10]].concat(),
+                AvmError::InnerTxnNotStarted,
+            ),
+            (0x0a, vec![0xb3], AvmError::InnerTxnNotStarted),
+            (0x0a, vec![0xb6], AvmError::InnerTxnNotStarted),
+            (0x0a, vec![0xb1, 0xb1], AvmError::InnerTxnNotSubmitted),
+            (
+                0x0a,
+                vec![0xb1, 0xb3],
+                AvmError::InvalidInnerTxnValue(TxnField::Type),
+            ),
+            (
+                0x0a,
+                [vec![0xb1], pushint(9), vec![0xb2, 0x10]].concat(),
+                AvmError::InvalidInnerTxnValue(TxnField::TypeEnum),
+            ),
+            (
+                0x0a,
+                [vec![0xb1], pushint(6), vec![0xb2, 0x19]].concat(),
+                AvmError::InvalidInnerTxnValue(TxnField::OnCompletion),
+            ),
+            (
+                0x0a,
+                [vec![0xb1], pushbytes(&[0xab; 32]), vec![0xb2, 0x17]].concat(),
+                AvmError::InvalidTxnFieldUse(TxnField::TxID),
+            ),
+            (
+                0x05,
+                [vec![0xb1], pushbytes(b"note"), vec![0xb2, 0x05]].concat(),
+                AvmError::InvalidTxnFieldUse(TxnField::Note),
+            ),
+            (
+                0x0a,
+                [vec![0xb1], pushbytes(&[0x09; 32]), vec![0xb2, 0x07]].concat(),
+                AvmError::AccountNotAvailable,
+            ),
+            (
+                0x0a,
+                [vec![0xb1], pushint(6), vec![0xb2, 0x11]].concat(),
+                AvmError::AssetNotAvailable(6),
+            ),
+            (
+                0x0a,
+                [
+                    begin_payment.clone(),
+                    pushint(10_000_000),
+                    vec![0xb2, 0x08], // itxn_field Amount
+                    vec![0xb3],
+                ]
+                .concat(),
+                AvmError::InsufficientBalance(199_000, 10_000_000),
+            ),
+            (
+                0x0a,
+                [
+                    begin_payment.clone(),
+                    pushint(150_000),
+                    vec![0xb2, 0x08], // itxn_field Amount
+                    pushbytes(&[0x02; 32]),
+                    vec![0xb2, 0x07], // itxn_field Receiver
+                    vec![0xb3],
+                ]
+                .concat(),
+                AvmError::BelowMinBalance(49_000, 108_900),
+            ),
+            (
+                0x0a,
+                [
+                    begin_payment.clone(),
+                    pushint(0),
+                    vec![0xb2, 0x01], // itxn_field Fee
+                    vec![0xb3],
+                ]
+                .concat(),
+                AvmError::InnerFeesTooLow(0, 1000),
+            ),
+            (
+                0x0a,
+                [
+                    begin_payment.clone(),
+                    pushbytes(&[0x01; 32]),
+                    vec![0xb2, 0x00], // itxn_field Sender
+                    vec![0xb3],
+                ]
+                .concat(),
+                AvmError::Unauthorized("send from this account"),
+            ),
+            (0x04, vec![0xb1], AvmError::UnknownOpcode(0xb1)),
+        ] {
+            let program = [vec![version], operation].concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = test_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
 
-        assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
+        let program = [vec![0x0a], begin_payment, vec![0xb3]].concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = test_ledger();
+        avm.inner_txn_count = 256;
+        assert_eq!(
+            AvmError::TooManyInnerTxns(256),
+            execute_program(&mut avm).unwrap_err()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_cover_at_top() -> Result<(), AvmError> {
+    fn test_itxn_effects() -> Result<(), AvmError> {
         let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x4e, 0x00], // cover 0
+            vec![0x0a],                   // #pragma version 10
+            vec![0xb1],                   // itxn_begin
+            pushint(6),                   // pushint 6
+            vec![0xb2, 0x10],             // itxn_field TypeEnum
+            pushint(7),                   // pushint 7
+            vec![0xb2, 0x18],             // itxn_field ApplicationID
+            vec![0xb6],                   // itxn_next
+            pushint(3),                   // pushint 3
+            vec![0xb2, 0x10],             // itxn_field TypeEnum
+            pushint(1000),                // pushint 1000
+            vec![0xb2, 0x22],             // itxn_field ConfigAssetTotal
+            vec![0xb3],                   // itxn_submit
+            vec![0xb4, 0x3c],             // itxn CreatedAssetID
+            pushint(0),                   // pushint 0
+            vec![0xc5, 0x1c],             // itxnas Accounts
+            vec![0xb7, 0x00, 0x3b],       // gitxn 0 NumLogs
+            vec![0xb8, 0x00, 0x3a, 0x01], // gitxna 0 Logs 1
+            pushint(0),                   // pushint 0
+            vec![0xc6, 0x00, 0x3a],       // gitxnas 0 Logs
+            vec![0xb7, 0x01, 0x16],       // gitxn 1 GroupIndex
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let ledger = inner_ledger();
+        ledger
+            .borrow_mut()
+            .apps
+            .get_mut(&7)
+            .unwrap()
+            .approval_program = [
+            vec![0x0a],          // #pragma version 10
+            pushbytes(b"hi"),    // pushbytes "hi"
+            vec![0xb0],          // log
+            pushbytes(b"there"), // pushbytes "there"
+            vec![0xb0],          // log
+            pushint(1),          // pushint 1
+        ]
+        .concat();
+        let mut avm = Avm::for_transaction(&program, test_transaction())?;
+        avm.ledger = ledger;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(6, avm.data_stack.len());
         assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Bytes(b"hi".to_vec())), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(b"there".to_vec())),
+            avm.data_stack.pop()
+        );
+        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(
+            Some(AvmData::Bytes(application_address(42).to_vec())),
+            avm.data_stack.pop()
+        );
+        assert_eq!(Some(AvmData::Uint64(43)), avm.data_stack.pop());
+        assert_eq!(
+            vec![b"hi".to_vec(), b"there".to_vec()],
+            avm.last_inner_group[0].logs
+        );
         Ok(())
     }
 
     #[test]
-    fn test_cover_outside_range() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x4e, 0x02], // cover 2
+    fn test_itxn_effects_errors() -> Result<(), AvmError> {
+        let submit_payment = [
+            vec![0xb1],             // itxn_begin
+            pushint(1),             // pushint 1
+            vec![0xb2, 0x10],       // itxn_field TypeEnum
+            pushbytes(&[0x02; 32]), // pushbytes 0x0202...
+            vec![0xb2, 0x07],       // itxn_field Receiver
+            vec![0xb3],             // itxn_submit
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidStackAccess, err);
+        for (version, operation, err) in [
+            (0x0a, vec![0xb4, 0x08], AvmError::NoInnerTxns),
+            (
+                0x0a,
+                [submit_payment.clone(), vec![0xb7, 0x01, 0x08]].concat(),
+                AvmError::GroupIndexOutOfRange(1, 1),
+            ),
+            (
+                0x0a,
+                [submit_payment.clone(), vec![0xb5, 0x1a, 0x00]].concat(),
+                AvmError::TxnArrayIndexOutOfRange(TxnField::ApplicationArgs, 0, 0),
+            ),
+            (0x05, vec![0xb7, 0x00, 0x08], AvmError::UnknownOpcode(0xb7)),
+        ] {
+            let program = [vec![version], operation].concat();
+            let mut avm = Avm::for_transaction(&program, test_transaction())?;
+            avm.ledger = inner_ledger();
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
         Ok(())
     }
 
+    // a transaction valid from round 1500 to 2000, for which
+    // the blocks of rounds 999 to 1499 are available
+    fn block_avm(program: &[u8]) -> Result<Avm<'_>, AvmError> {
+        let txn = Transaction {
+            first_valid: 1500,
+            last_valid: 2000,
+            ..test_transaction()
+        };
+        let mut blocks = MemoryBlockHistory::default();
+        for round in [999, 1200, 1499] {
+            let header = BlockHeader {
+                seed: [round as u8; 32],
+                timestamp: 1_700_000_000 + round,
+            };
+            blocks.headers.insert(round, header);
+        }
+        let mut avm = Avm::for_transaction(program, txn)?;
+        avm.blocks = Rc::new(blocks);
+        Ok(avm)
+    }
+
     #[test]
-    fn test_uncover_at_bottom() -> Result<(), AvmError> {
+    fn test_block() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x4f, 0x03], // uncover 3
+            pushint(1200),    // pushint 1200
+            vec![0xd1, 0x00], // block BlkSeed
+            pushint(999),     // pushint 999
+            vec![0xd1, 0x01], // block BlkTimestamp
+            pushint(1499),    // pushint 1499
+            vec![0xd1, 0x01], // block BlkTimestamp
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let mut avm = block_avm(&program)?;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(4, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(1)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(2)), avm.data_stack.pop());
+        assert_eq!(3, avm.data_stack.len());
+        assert_eq!(Some(AvmData::Uint64(1_700_001_499)), avm.data_stack.pop());
+        assert_eq!(Some(AvmData::Uint64(1_700_000_999)), avm.data_stack.pop());
+        // the low byte of 1200
+        assert_eq!(Some(AvmData::Bytes(vec![0xb0; 32])), avm.data_stack.pop());
         Ok(())
     }
 
     #[test]
-    fn test_uncover_at_top() -> Result<(), AvmError> {
+    fn test_block_errors() -> Result<(), AvmError> {
+        for (version, operation, err) in [
+            (
+                0x0a,
+                [pushint(998), vec![0xd1, 0x00]].concat(),
+                AvmError::RoundNotAvailable(998, 999, 1499),
+            ),
+            (
+                0x0a,
+                [pushint(1500), vec![0xd1, 0x00]].concat(),
+                AvmError::RoundNotAvailable(1500, 999, 1499),
+            ),
+            (
+                0x0a,
+                [pushint(1300), vec![0xd1, 0x00]].concat(),
+                AvmError::BlockNotFound(1300),
+            ),
+            (
+                0x0a,
+                [pushint(1200), vec![0xd1, 0x02]].concat(),
+                AvmError::UnknownField("block", 0x02),
+            ),
+            (
+                0x06,
+                [pushint(1200), vec![0xd1, 0x00]].concat(),
+                AvmError::UnknownOpcode(0xd1),
+            ),
+        ] {
+            let program = [vec![version], operation].concat();
+            let mut avm = block_avm(&program)?;
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_in_logicsig() -> Result<(), AvmError> {
         let program = [
             vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x03], // pushint 3
-            vec![0x81, 0x04], // pushint 4
-            vec![0x4f, 0x00], // uncover 0
+            pushint(1200),    // pushint 1200
+            vec![0xd1, 0x01], // block BlkTimestamp
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
+        let block_avm = block_avm(&program)?;
+        let mut avm = Avm::for_logicsig(&program, vec![])?;
+        avm.txn_group = block_avm.txn_group;
+        avm.blocks = block_avm.blocks;
         let avm = execute_program(&mut avm)?;
 
-        assert_eq!(2, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(4)), avm.data_stack.pop());
-        assert_eq!(Some(AvmData::Uint64(3)), avm.data_stack.pop());
+        assert_eq!(RunMode::Signature, avm.mode);
+        assert_eq!(vec![AvmData::Uint64(1_700_001_200)], avm.data_stack);
         Ok(())
     }
 
+    // decodes a hexadecimal test vector
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
     #[test]
-    fn test_uncover_outside_range() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],       // #pragma version 10
-            vec![0x81, 0x01], // pushint 1
-            vec![0x81, 0x02], // pushint 2
-            vec![0x4f, 0x02], // uncover 2
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidStackAccess, err);
+    fn test_vrf_verify() -> Result<(), AvmError> {
+        // the ECVRF-ED25519-SHA512-Elligator2 examples of draft-irtf-cfrg-vrf-03
+        for (public_key, message, proof, output) in [
+            (
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "b6b4699f87d56126c9117a7da55bd0085246f4c56dbc95d20172612e9d38e8d7\
+                 ca65e573a126ed88d4e30a46f80a666854d675cf3ba81de0de043c3774f06156\
+                 0f55edc256a787afe701677c0f602900",
+                "5b49b554d05c0cd5a5325376b3387de59d924fd1e13ded44648ab33c21349a60\
+                 3f25b84ec5ed887995b33da5e3bfcb87cd2f64521c4c62cf825cffabbe5d31cc",
+            ),
+            (
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "ae5b66bdf04b4c010bfe32b2fc126ead2107b697634f6f7337b9bff8785ee111\
+                 200095ece87dde4dbe87343f6df3b107d91798c8a7eb1245d3bb9c5aafb09335\
+                 8c13e6ae1111a55717e895fd15f99f07",
+                "94f4487e1b2fec954309ef1289ecb2e15043a2461ecc7b2ae7d4470607ef82eb\
+                 1cfa97d84991fe4a7bfdfd715606bc27e2967a6c557cfb5875879b671740b7d8",
+            ),
+            (
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "dfa2cba34b611cc8c833a6ea83b8eb1bb5e2ef2dd1b0c481bc42ff36ae7847f6\
+                 ab52b976cfd5def172fa412defde270c8b8bdfbaae1c7ece17d9833b1bcf3106\
+                 4fff78ef493f820055b561ece45e1009",
+                "2031837f582cd17a9af9e0c7ef5a6540e3453ed894b62c293686ca3c1e319dde\
+                 9d0aa489a4b59a9594fc2328bc3deff3c8a0929a369a72b1180a596e016b5ded",
+            ),
+        ] {
+            let program = [
+                vec![0x0a],                       // #pragma version 10
+                pushbytes(&from_hex(message)),    // pushbytes message
+                pushbytes(&from_hex(proof)),      // pushbytes proof
+                pushbytes(&from_hex(public_key)), // pushbytes public_key
+                vec![0xd0, 0x00],                 // vrf_verify VrfAlgorand
+                vec![0x44],                       // assert
+                pushbytes(&from_hex(output)),     // pushbytes output
+                vec![0x12],                       // ==
+            ]
+            .concat();
+            let mut avm = Avm::for_logicsig(&program, vec![])?;
+            let avm = execute_program(&mut avm)?;
+            assert_eq!(vec![AvmData::Uint64(1)], avm.data_stack);
+            assert_eq!(3 + 5700 + 3, avm.cost);
+
+            // vrf_verify exceeds the budget of a single app call
+            let mut avm = Avm::for_program(&program)?;
+            assert_eq!(
+                Err(AvmError::CostBudgetExceeded(3 + 5700, 700)),
+                execute_program(&mut avm).map(|_| ())
+            );
+        }
         Ok(())
     }
 
     #[test]
-    fn test_substring() -> Result<(), AvmError> {
+    fn test_vrf_verify_invalid() -> Result<(), AvmError> {
+        let public_key =
+            from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let proof = from_hex(
+            "b6b4699f87d56126c9117a7da55bd0085246f4c56dbc95d20172612e9d38e8d7\
+             ca65e573a126ed88d4e30a46f80a666854d675cf3ba81de0de043c3774f06156\
+             0f55edc256a787afe701677c0f602900",
+        );
+        let mut tampered = proof.clone();
+        tampered[40] ^= 0x01;
+        // the identity point has small order
+        let identity = [vec![0x01], vec![0x00; 31]].concat();
+        for (message, proof, public_key) in [
+            (&b"other"[..], &proof, &public_key),
+            (&b""[..], &tampered, &public_key),
+            (&b""[..], &proof, &identity),
+        ] {
+            let program = [
+                vec![0x0a],            // #pragma version 10
+                pushbytes(message),    // pushbytes message
+                pushbytes(proof),      // pushbytes proof
+                pushbytes(public_key), // pushbytes public_key
+                vec![0xd0, 0x00],      // vrf_verify VrfAlgorand
+            ]
+            .concat();
+            let mut avm = Avm::for_logicsig(&program, vec![])?;
+            let avm = execute_program(&mut avm)?;
+
+            assert_eq!(Some(AvmData::Uint64(0)), avm.data_stack.pop());
+            assert_eq!(Some(AvmData::Bytes(vec![0; 64])), avm.data_stack.pop());
+        }
+
+        for (version, operation, err) in [
+            (
+                0x0a,
+                [
+                    pushbytes(&proof),
+                    pushbytes(&public_key[1..]),
+                    vec![0xd0, 0x00],
+                ]
+                .concat(),
+                AvmError::InvalidVrfInput("public key", 31, 32),
+            ),
+            (
+                0x0a,
+                [
+                    pushbytes(&proof[1..]),
+                    pushbytes(&public_key),
+                    vec![0xd0, 0x00],
+                ]
+                .concat(),
+                AvmError::InvalidVrfInput("proof", 79, 80),
+            ),
+            (
+                0x0a,
+                [pushbytes(&proof), pushbytes(&public_key), vec![0xd0, 0x01]].concat(),
+                AvmError::UnknownField("vrf_verify", 0x01),
+            ),
+            (
+                0x06,
+                [pushbytes(&proof), pushbytes(&public_key), vec![0xd0, 0x00]].concat(),
+                AvmError::UnknownOpcode(0xd0),
+            ),
+        ] {
+            let program = [vec![version], pushbytes(b""), operation].concat();
+            let mut avm = Avm::for_program(&program)?;
+            assert_eq!(err, execute_program(&mut avm).unwrap_err());
+        }
+        Ok(())
+    }
+
+    // runs an elliptic curve opcode on the given arguments
+    fn ec_op(opcode: u8, group: u8, args: &[&[u8]]) -> Result<AvmData, AvmError> {
         let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x51, 0x01, 0x04],                   // substring 1 4
+            vec![0x0a], // #pragma version 10
+            args.iter()
+                .map(|arg| pushbytes(arg))
+                .collect::<Vec<_>>()
+                .concat(),
+            vec![opcode, group],
         ]
         .concat();
+        // the costs are checked by test_ec_cost
         let mut avm = Avm::for_program(&program)?;
+        avm.budget = u64::MAX;
         let avm = execute_program(&mut avm)?;
+        avm.data_stack.pop().ok_or(AvmError::EmptyStack)
+    }
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(
-            Some(AvmData::Bytes(vec![0xad, 0xbe, 0xef])),
-            avm.data_stack.pop()
+    fn ec_point(opcode: u8, group: u8, args: &[&[u8]]) -> Result<Vec<u8>, AvmError> {
+        match ec_op(opcode, group, args)? {
+            AvmData::Bytes(point) => Ok(point),
+            other => panic!("expected a point, got {other:?}"),
+        }
+    }
+
+    // the orders of the subgroups of BN254 and BLS12-381
+    const BN254_ORDER: &str = "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+    const BLS12_381_ORDER: &str =
+        "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+
+    // a group with two field elements and the order of its subgroup
+    type EcTestGroup = (u8, Vec<u8>, Vec<u8>, Vec<u8>);
+
+    fn ec_groups() -> Vec<EcTestGroup> {
+        let padded = |size: usize, value: u8| [vec![0; size - 1], vec![value]].concat();
+        vec![
+            (0, vec![1], vec![2], from_hex(BN254_ORDER)),
+            (1, padded(64, 1), padded(64, 2), from_hex(BN254_ORDER)),
+            (2, vec![1], vec![2], from_hex(BLS12_381_ORDER)),
+            (3, padded(96, 1), padded(96, 2), from_hex(BLS12_381_ORDER)),
+        ]
+    }
+
+    #[test]
+    fn test_ec_add_scalar_mul() -> Result<(), AvmError> {
+        for (group, u1, u2, order) in ec_groups() {
+            let p = ec_point(0xe5, group, &[&u1])?;
+            let q = ec_point(0xe5, group, &[&u2])?;
+            let identity = vec![0; p.len()];
+            assert_ne!(p, q);
+            assert_ne!(p, identity);
+
+            let sum = ec_point(0xe0, group, &[&p, &q])?;
+            assert_eq!(sum, ec_point(0xe0, group, &[&q, &p])?);
+            assert_eq!(p, ec_point(0xe0, group, &[&p, &identity])?);
+            assert_eq!(identity, ec_point(0xe0, group, &[&identity, &identity])?);
+
+            let double = ec_point(0xe0, group, &[&p, &p])?;
+            assert_eq!(double, ec_point(0xe1, group, &[&p, &[2]])?);
+            assert_eq!(p, ec_point(0xe1, group, &[&p, &[0, 1]])?);
+            assert_eq!(identity, ec_point(0xe1, group, &[&p, &[]])?);
+            assert_eq!(identity, ec_point(0xe1, group, &[&p, &order])?);
+
+            let scalars = [
+                [vec![0; 31], vec![3]].concat(),
+                [vec![0; 31], vec![5]].concat(),
+            ];
+            let expected = ec_point(
+                0xe0,
+                group,
+                &[
+                    &ec_point(0xe1, group, &[&p, &[3]])?,
+                    &ec_point(0xe1, group, &[&q, &[5]])?,
+                ],
+            )?;
+            assert_eq!(
+                expected,
+                ec_point(0xe3, group, &[&[p, q].concat(), &scalars.concat()])?
+            );
+            assert_eq!(identity, ec_point(0xe3, group, &[&[], &[]])?);
+        }
+
+        // the generator of BN254 G1 and its double
+        let generator = [vec![0; 31], vec![1], vec![0; 31], vec![2]].concat();
+        let double = from_hex(
+            "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+             15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4",
         );
+        assert_eq!(double, ec_point(0xe0, 0, &[&generator, &generator])?);
+        assert_eq!(double, ec_point(0xe1, 0, &[&generator, &[2]])?);
         Ok(())
     }
 
     #[test]
-    fn test_substring_empty() -> Result<(), AvmError> {
+    fn test_ec_pairing_check() -> Result<(), AvmError> {
+        for (g1, g2) in [(0, 1), (2, 3)] {
+            let groups = ec_groups();
+            let (_, u1, _, order) = &groups[g1 as usize];
+            let (_, u2, _, _) = &groups[g2 as usize];
+            let p = ec_point(0xe5, g1, &[u1])?;
+            let q = ec_point(0xe5, g2, &[u2])?;
+            let a = [7];
+            let ap = ec_point(0xe1, g1, &[&p, &a])?;
+            let aq = ec_point(0xe1, g2, &[&q, &a])?;
+            let mut order_minus_one = order.clone();
+            *order_minus_one.last_mut().unwrap() -= 1;
+            let neg_p = ec_point(0xe1, g1, &[&p, &order_minus_one])?;
+            let neg_q = ec_point(0xe1, g2, &[&q, &order_minus_one])?;
+
+            // e(aP, Q) * e(-P, aQ) = 1
+            let g1_points = [ap.clone(), neg_p].concat();
+            let g2_points = [q.clone(), aq.clone()].concat();
+            assert_eq!(
+                AvmData::Uint64(1),
+                ec_op(0xe2, g1, &[&g1_points, &g2_points])?
+            );
+            assert_eq!(
+                AvmData::Uint64(1),
+                ec_op(0xe2, g2, &[&g2_points, &g1_points])?
+            );
+            // e(P, aQ) * e(aP, -Q) = 1
+            assert_eq!(
+                AvmData::Uint64(1),
+                ec_op(
+                    0xe2,
+                    g1,
+                    &[&[p.clone(), ap.clone()].concat(), &[aq, neg_q].concat()]
+                )?
+            );
+            // e(aP, Q) * e(P, aQ) != 1
+            let g1_points = [ap, p.clone()].concat();
+            assert_eq!(
+                AvmData::Uint64(0),
+                ec_op(0xe2, g1, &[&g1_points, &g2_points])?
+            );
+            assert_eq!(AvmData::Uint64(0), ec_op(0xe2, g1, &[&p, &q])?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ec_program() -> Result<(), AvmError> {
+        let mut order_minus_one = from_hex(BN254_ORDER);
+        *order_minus_one.last_mut().unwrap() -= 1;
+        // checks e(aP, Q) * e(-P, aQ) = 1 for points P and Q mapped from field elements
         let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x51, 0x00, 0x00],                   // substring 0 0
+            vec![0x0a],                                  // #pragma version 10
+            pushbytes(&[1]),                             // pushbytes 0x01
+            vec![0xe5, 0x00],                            // ec_map_to BN254g1
+            vec![0x49],                                  // dup
+            pushbytes(&[7]),                             // pushbytes 0x07
+            vec![0xe1, 0x00],                            // ec_scalar_mul BN254g1
+            vec![0x4c],                                  // swap
+            pushbytes(&order_minus_one),                 // pushbytes order - 1
+            vec![0xe1, 0x00],                            // ec_scalar_mul BN254g1
+            vec![0x50],                                  // concat
+            pushbytes(&[vec![0; 63], vec![1]].concat()), // pushbytes 0x00...01
+            vec![0xe5, 0x01],                            // ec_map_to BN254g2
+            vec![0x49],                                  // dup
+            pushbytes(&[7]),                             // pushbytes 0x07
+            vec![0xe1, 0x01],                            // ec_scalar_mul BN254g2
+            vec![0x50],                                  // concat
+            vec![0xe2, 0x00],                            // ec_pairing_check BN254g1
+            vec![0x44],                                  // assert
+            pushint(1),                                  // pushint 1
         ]
         .concat();
+        let cost = 12 + 630 + 2 * 1810 + 3300 + 3430 + 8000 + 4 * 7400;
+
+        // a budget this large would be pooled from many app calls
         let mut avm = Avm::for_program(&program)?;
+        avm.budget = cost;
         let avm = execute_program(&mut avm)?;
+        assert_eq!(vec![AvmData::Uint64(1)], avm.data_stack);
+        assert_eq!(cost, avm.cost);
 
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Bytes(vec![])), avm.data_stack.pop());
+        let mut avm = Avm::for_program(&program)?;
+        // the final pushint exceeds a budget that is one lower
+        avm.budget = cost - 1;
+        assert_eq!(
+            Err(AvmError::CostBudgetExceeded(cost, cost - 1)),
+            execute_program(&mut avm).map(|_| ())
+        );
         Ok(())
     }
 
     #[test]
-    fn test_substring_outside_range() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x51, 0x01, 0x05],                   // substring 1 5
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidSubstringAccess(1, 5, 4), err);
+    fn test_ec_subgroup_check_map_to() -> Result<(), AvmError> {
+        for (group, u1, u2, _) in ec_groups() {
+            for u in [u1, u2] {
+                let point = ec_point(0xe5, group, &[&u])?;
+                assert_eq!(AvmData::Uint64(1), ec_op(0xe4, group, &[&point])?);
+            }
+            let identity = vec![0; [64, 128, 96, 192][group as usize]];
+            assert_eq!(AvmData::Uint64(1), ec_op(0xe4, group, &[&identity])?);
+        }
+
+        // points with X = 0 on the curve of BLS12-381 G1 have order 3
+        let point = [vec![0; 48], vec![0; 47], vec![2]].concat();
+        assert_eq!(AvmData::Uint64(0), ec_op(0xe4, 2, &[&point])?);
+        assert_eq!(
+            point,
+            ec_point(0xe1, 2, &[&point, &from_hex(BLS12_381_ORDER)])?
+        );
+
+        // the SVDW map of RFC 9380 for BN254 G1
+        for (u, expected) in [
+            (
+                &[1][..],
+                "2b8d79cdcaaca9beddf982188d7d92fd2acc298e53b6ec72d69aab86960a1727\
+                 16de5b0e1c87130160106734a03a0e2a4a78ed715dba060f06235c2abdb920e5",
+            ),
+            (
+                &[0, 2][..],
+                "266dc5d3b2d495c6f15f00ae673bad8c98c23fe533582e2c151b6392d554328a\
+                 302c71c5423bef26de207f69c06daddbbcb18b1429c27b756ac8ff60f6485396",
+            ),
+        ] {
+            assert_eq!(from_hex(expected), ec_point(0xe5, 0, &[u])?);
+        }
         Ok(())
     }
 
     #[test]
-    fn test_substring_end_before_start() -> Result<(), AvmError> {
-        let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x51, 0x03, 0x02],                   // substring 3 2
-        ]
-        .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let err = execute_program(&mut avm).unwrap_err();
-        assert_eq!(AvmError::InvalidSubstringAccess(3, 2, 4), err);
+    fn test_ec_cost() -> Result<(), AvmError> {
+        let generator = [vec![0; 31], vec![1], vec![0; 31], vec![2]].concat();
+        let scalar = [vec![0; 31], vec![1]].concat();
+        let g2_identity = vec![0; 128];
+        for (args, operation, cost) in [
+            ([&generator, &generator], vec![0xe0, 0x00], 125),
+            ([&generator, &scalar], vec![0xe1, 0x00], 1810),
+            (
+                [&generator, &g2_identity],
+                vec![0xe2, 0x00],
+                8000 + 2 * 7400,
+            ),
+            ([&generator, &scalar], vec![0xe3, 0x00], 3600 + 90),
+        ] {
+            let program = [
+                vec![0x0a],         // #pragma version 10
+                pushbytes(args[0]), // pushbytes A
+                pushbytes(args[1]), // pushbytes B
+                operation,
+            ]
+            .concat();
+            let mut avm = Avm::for_program(&program)?;
+            avm.budget = u64::MAX;
+            let avm = execute_program(&mut avm)?;
+            assert_eq!(2 + cost, avm.cost);
+        }
         Ok(())
     }
 
     #[test]
-    fn test_substring3() -> Result<(), AvmError> {
+    fn test_ec_errors() -> Result<(), AvmError> {
+        let generator = [vec![0; 31], vec![1], vec![0; 31], vec![2]].concat();
+        let off_curve = [vec![0; 31], vec![1], vec![0; 31], vec![3]].concat();
+        let modulus = from_hex("30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47");
+        let not_canonical = [modulus.clone(), vec![0; 31], vec![2]].concat();
+        // a point of order 3 on the curve of BLS12-381 G1
+        let small_order = [vec![0; 48], vec![0; 47], vec![2]].concat();
+        let g2_identity = vec![0; 128];
+        for (opcode, group, args, err) in [
+            (
+                0xe0,
+                0,
+                vec![&generator, &off_curve],
+                AvmError::InvalidEcPoint(EcGroup::BN254g1),
+            ),
+            (
+                0xe0,
+                0,
+                vec![&generator, &not_canonical],
+                AvmError::InvalidEcPoint(EcGroup::BN254g1),
+            ),
+            (
+                0xe0,
+                0,
+                vec![&generator, &generator[1..].to_vec()],
+                AvmError::InvalidEcPoint(EcGroup::BN254g1),
+            ),
+            (
+                0xe0,
+                1,
+                vec![&generator, &generator],
+                AvmError::InvalidEcPoint(EcGroup::BN254g2),
+            ),
+            (
+                0xe1,
+                0,
+                vec![&generator, &vec![1; 33]],
+                AvmError::EcScalarTooLong(33),
+            ),
+            (
+                0xe2,
+                0,
+                vec![
+                    &generator,
+                    &[g2_identity.clone(), g2_identity.clone()].concat(),
+                ],
+                AvmError::EcPointCountMismatch(1, 2),
+            ),
+            (
+                0xe2,
+                1,
+                vec![&vec![], &vec![]],
+                AvmError::EcPointCountMismatch(0, 0),
+            ),
+            (
+                0xe2,
+                2,
+                vec![&small_order, &vec![0; 192]],
+                AvmError::EcPointNotInSubgroup(EcGroup::BLS12_381g1),
+            ),
+            (
+                0xe3,
+                0,
+                vec![&generator, &vec![1]],
+                AvmError::InvalidEcScalars(1, 32),
+            ),
+            (
+                0xe3,
+                0,
+                vec![&generator, &vec![1; 64]],
+                AvmError::InvalidEcScalars(64, 32),
+            ),
+            (
+                0xe4,
+                2,
+                vec![&off_curve],
+                AvmError::InvalidEcPoint(EcGroup::BLS12_381g1),
+            ),
+            (
+                0xe5,
+                0,
+                vec![&modulus],
+                AvmError::InvalidEcFieldElement(EcGroup::BN254g1),
+            ),
+            (
+                0xe5,
+                0,
+                vec![&vec![1; 33]],
+                AvmError::InvalidEcFieldElement(EcGroup::BN254g1),
+            ),
+            (
+                0xe5,
+                1,
+                vec![&vec![1]],
+                AvmError::InvalidEcFieldElement(EcGroup::BN254g2),
+            ),
+            (0xe5, 4, vec![&vec![1]], AvmError::UnknownField("EC", 4)),
+        ] {
+            let args: Vec<&[u8]> = args.iter().map(|arg| arg.as_slice()).collect();
+            assert_eq!(Err(err), ec_op(opcode, group, &args));
+        }
+
         let program = [
-            vec![0x0a],                               // #pragma version 10
-            vec![0x80, 0x04, 0xde, 0xad, 0xbe, 0xef], // pushbytes 0xdeadbeef
-            vec![0x81, 0x01],                         // pushint 1
-            vec![0x81, 0x02],                         // pushint 2
-            vec![0x52],                               // substring3
+            vec![0x09],            // #pragma version 9
+            pushbytes(&generator), // pushbytes generator
+            pushbytes(&generator), // pushbytes generator
+            vec![0xe0, 0x00],      // ec_add BN254g1
         ]
         .concat();
         let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Bytes(vec![0xad])), avm.data_stack.pop());
+        assert_eq!(
+            Err(AvmError::UnknownOpcode(0xe0)),
+            execute_program(&mut avm).map(|_| ())
+        );
         Ok(())
     }
 
     #[test]
-    fn test_loop_with_bnz() -> Result<(), AvmError> {
+    fn test_itxn_group_is_atomic() {
         let program = [
             vec![0x0a],             // #pragma version 10
-            vec![0x81, 0x00],       // pushint 0
-            vec![0x81, 0x01],       // pushint 1
-            vec![0x08],             // +
-            vec![0x49],             // dup
-            vec![0x81, 0x05],       // pushint 5
-            vec![0x0c],             // <
-            vec![0x40, 0xff, 0xf6], // bnz 0xfff6 (-10 in two's complement)
+            vec![0xb1],             // itxn_begin
+            pushint(1),             // pushint 1
+            vec![0xb2, 0x10],       // itxn_field TypeEnum
+            pushint(5000),          // pushint 5000
+            vec![0xb2, 0x08],       // itxn_field Amount
+            pushbytes(&[0x02; 32]), // pushbytes 0x0202...
+            vec![0xb2, 0x07],       // itxn_field Receiver
+            vec![0xb6],             // itxn_next
+            pushint(1),             // pushint 1
+            vec![0xb2, 0x10],       // itxn_field TypeEnum
+            pushint(2_000_000),     // pushint 2000000
+            vec![0xb2, 0x08],       // itxn_field Amount
+            pushbytes(&[0x02; 32]), // pushbytes 0x0202...
+            vec![0xb2, 0x07],       // itxn_field Receiver
+            vec![0xb3],             // itxn_submit
         ]
         .concat();
-        let mut avm = Avm::for_program(&program)?;
-        let avm = execute_program(&mut avm)?;
-
-        assert_eq!(1, avm.data_stack.len());
-        assert_eq!(Some(AvmData::Uint64(5)), avm.data_stack.pop());
-        Ok(())
+        let ledger = inner_ledger();
+        let mut avm = Avm::for_transaction(&program, test_transaction()).unwrap();
+        avm.ledger = ledger.clone();
+
+        // the second payment overspends, so the first is not applied either
+        assert!(matches!(
+            execute_program(&mut avm),
+            Err(AvmError::InsufficientBalance(_, 2_000_000))
+        ));
+        assert_eq!(*inner_ledger().borrow(), *ledger.borrow());
+        assert_eq!(0, avm.inner_txn_count);
     }
 }
@@ -0,0 +1,740 @@
+use crate::AvmError;
+use sha2::{Digest, Sha512_256};
+
+use super::{AvmData, AvmVersion, LABEL_BYTES, LABEL_UINT64};
+
+pub type Address = [u8; 32];
+
+// the address of an application's account is the SHA-512/256
+// hash of the prefix "appID" and the big-endian application id
+pub fn application_address(application_id: u64) -> Address {
+    let mut hasher = Sha512_256::new();
+    hasher.update(b"appID");
+    hasher.update(application_id.to_be_bytes());
+    hasher.finalize().into()
+}
+
+// programs are exposed in pages of this size via the *ProgramPages fields
+const PROGRAM_PAGE_SIZE: usize = 4096;
+
+// limits on the fields of transactions, as enforced for inner transactions
+const MAX_NOTE_LEN: usize = 1024;
+const MAX_ASSET_DECIMALS: u64 = 19;
+const MAX_ASSET_UNIT_NAME_LEN: usize = 8;
+const MAX_ASSET_NAME_LEN: usize = 32;
+const MAX_ASSET_URL_LEN: usize = 96;
+const MAX_APP_ARGS: usize = 16;
+const MAX_APP_ARGS_LEN: usize = 2048;
+const MAX_APP_ACCOUNTS: usize = 4;
+const MAX_APP_FOREIGN_ASSETS: usize = 8;
+const MAX_APP_FOREIGN_APPS: usize = 8;
+const MAX_APP_REFERENCES: usize = 8;
+const MAX_APP_PROGRAM_LEN: usize = 8192;
+const MAX_EXTRA_PROGRAM_PAGES: u64 = 3;
+const MAX_GLOBAL_SCHEMA_ENTRIES: u64 = 64;
+const MAX_LOCAL_SCHEMA_ENTRIES: u64 = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum TxType {
+    #[default]
+    Unknown,
+    Payment,
+    KeyRegistration,
+    AssetConfig,
+    AssetTransfer,
+    AssetFreeze,
+    ApplicationCall,
+}
+
+// all transaction types in the order of their numbering
+const TX_TYPES: [TxType; 7] = [
+    TxType::Unknown,
+    TxType::Payment,
+    TxType::KeyRegistration,
+    TxType::AssetConfig,
+    TxType::AssetTransfer,
+    TxType::AssetFreeze,
+    TxType::ApplicationCall,
+];
+
+impl TxType {
+    fn name(self) -> &'static [u8] {
+        match self {
+            TxType::Unknown => b"",
+            TxType::Payment => b"pay",
+            TxType::KeyRegistration => b"keyreg",
+            TxType::AssetConfig => b"acfg",
+            TxType::AssetTransfer => b"axfer",
+            TxType::AssetFreeze => b"afrz",
+            TxType::ApplicationCall => b"appl",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum OnCompletion {
+    #[default]
+    NoOp,
+    OptIn,
+    CloseOut,
+    ClearState,
+    UpdateApplication,
+    DeleteApplication,
+}
+
+// all on completion actions in the order of their numbering
+const ON_COMPLETIONS: [OnCompletion; 6] = [
+    OnCompletion::NoOp,
+    OnCompletion::OptIn,
+    OnCompletion::CloseOut,
+    OnCompletion::ClearState,
+    OnCompletion::UpdateApplication,
+    OnCompletion::DeleteApplication,
+];
+
+// a box that an application call accesses, where app_index is an
+// index into the Applications array (0 being the called application)
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BoxReference {
+    pub app_index: u64,
+    pub name: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Transaction {
+    pub tx_type: TxType,
+    pub tx_id: [u8; 32],
+    pub sender: Address,
+    pub fee: u64,
+    pub first_valid: u64,
+    pub last_valid: u64,
+    pub note: Vec<u8>,
+    pub lease: [u8; 32],
+    pub rekey_to: Address,
+    // payment
+    pub receiver: Address,
+    pub amount: u64,
+    pub close_remainder_to: Address,
+    // key registration
+    pub vote_pk: [u8; 32],
+    pub selection_pk: [u8; 32],
+    // 64 bytes if set, empty otherwise
+    pub state_proof_pk: Vec<u8>,
+    pub vote_first: u64,
+    pub vote_last: u64,
+    pub vote_key_dilution: u64,
+    pub nonparticipation: bool,
+    // asset transfer
+    pub xfer_asset: u64,
+    pub asset_amount: u64,
+    pub asset_sender: Address,
+    pub asset_receiver: Address,
+    pub asset_close_to: Address,
+    // asset configuration
+    pub config_asset: u64,
+    pub config_asset_total: u64,
+    pub config_asset_decimals: u64,
+    pub config_asset_default_frozen: bool,
+    pub config_asset_unit_name: Vec<u8>,
+    pub config_asset_name: Vec<u8>,
+    pub config_asset_url: Vec<u8>,
+    pub config_asset_metadata_hash: Vec<u8>,
+    pub config_asset_manager: Address,
+    pub config_asset_reserve: Address,
+    pub config_asset_freeze: Address,
+    pub config_asset_clawback: Address,
+    // asset freeze
+    pub freeze_asset: u64,
+    pub freeze_asset_account: Address,
+    pub freeze_asset_frozen: bool,
+    // application call
+    pub application_id: u64,
+    pub on_completion: OnCompletion,
+    pub application_args: Vec<Vec<u8>>,
+    pub accounts: Vec<Address>,
+    pub assets: Vec<u64>,
+    pub applications: Vec<u64>,
+    pub approval_program: Vec<u8>,
+    pub clear_state_program: Vec<u8>,
+    pub global_num_uint: u64,
+    pub global_num_byte_slice: u64,
+    pub local_num_uint: u64,
+    pub local_num_byte_slice: u64,
+    pub extra_program_pages: u64,
+    pub boxes: Vec<BoxReference>,
+    // effects, only visible once the transaction has been evaluated
+    pub logs: Vec<Vec<u8>>,
+    pub created_asset_id: u64,
+    pub created_application_id: u64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TxnField {
+    Sender = 0,
+    Fee = 1,
+    FirstValid = 2,
+    FirstValidTime = 3,
+    LastValid = 4,
+    Note = 5,
+    Lease = 6,
+    Receiver = 7,
+    Amount = 8,
+    CloseRemainderTo = 9,
+    VotePK = 10,
+    SelectionPK = 11,
+    VoteFirst = 12,
+    VoteLast = 13,
+    VoteKeyDilution = 14,
+    Type = 15,
+    TypeEnum = 16,
+    XferAsset = 17,
+    AssetAmount = 18,
+    AssetSender = 19,
+    AssetReceiver = 20,
+    AssetCloseTo = 21,
+    GroupIndex = 22,
+    TxID = 23,
+    ApplicationID = 24,
+    OnCompletion = 25,
+    ApplicationArgs = 26,
+    NumAppArgs = 27,
+    Accounts = 28,
+    NumAccounts = 29,
+    ApprovalProgram = 30,
+    ClearStateProgram = 31,
+    RekeyTo = 32,
+    ConfigAsset = 33,
+    ConfigAssetTotal = 34,
+    ConfigAssetDecimals = 35,
+    ConfigAssetDefaultFrozen = 36,
+    ConfigAssetUnitName = 37,
+    ConfigAssetName = 38,
+    ConfigAssetURL = 39,
+    ConfigAssetMetadataHash = 40,
+    ConfigAssetManager = 41,
+    ConfigAssetReserve = 42,
+    ConfigAssetFreeze = 43,
+    ConfigAssetClawback = 44,
+    FreezeAsset = 45,
+    FreezeAssetAccount = 46,
+    FreezeAssetFrozen = 47,
+    Assets = 48,
+    NumAssets = 49,
+    Applications = 50,
+    NumApplications = 51,
+    GlobalNumUint = 52,
+    GlobalNumByteSlice = 53,
+    LocalNumUint = 54,
+    LocalNumByteSlice = 55,
+    ExtraProgramPages = 56,
+    Nonparticipation = 57,
+    Logs = 58,
+    NumLogs = 59,
+    CreatedAssetID = 60,
+    CreatedApplicationID = 61,
+    LastLog = 62,
+    StateProofPK = 63,
+    ApprovalProgramPages = 64,
+    NumApprovalProgramPages = 65,
+    ClearStateProgramPages = 66,
+    NumClearStateProgramPages = 67,
+}
+
+// all fields in the order of their numbering
+const TXN_FIELDS: [TxnField; 68] = [
+    TxnField::Sender,
+    TxnField::Fee,
+    TxnField::FirstValid,
+    TxnField::FirstValidTime,
+    TxnField::LastValid,
+    TxnField::Note,
+    TxnField::Lease,
+    TxnField::Receiver,
+    TxnField::Amount,
+    TxnField::CloseRemainderTo,
+    TxnField::VotePK,
+    TxnField::SelectionPK,
+    TxnField::VoteFirst,
+    TxnField::VoteLast,
+    TxnField::VoteKeyDilution,
+    TxnField::Type,
+    TxnField::TypeEnum,
+    TxnField::XferAsset,
+    TxnField::AssetAmount,
+    TxnField::AssetSender,
+    TxnField::AssetReceiver,
+    TxnField::AssetCloseTo,
+    TxnField::GroupIndex,
+    TxnField::TxID,
+    TxnField::ApplicationID,
+    TxnField::OnCompletion,
+    TxnField::ApplicationArgs,
+    TxnField::NumAppArgs,
+    TxnField::Accounts,
+    TxnField::NumAccounts,
+    TxnField::ApprovalProgram,
+    TxnField::ClearStateProgram,
+    TxnField::RekeyTo,
+    TxnField::ConfigAsset,
+    TxnField::ConfigAssetTotal,
+    TxnField::ConfigAssetDecimals,
+    TxnField::ConfigAssetDefaultFrozen,
+    TxnField::ConfigAssetUnitName,
+    TxnField::ConfigAssetName,
+    TxnField::ConfigAssetURL,
+    TxnField::ConfigAssetMetadataHash,
+    TxnField::ConfigAssetManager,
+    TxnField::ConfigAssetReserve,
+    TxnField::ConfigAssetFreeze,
+    TxnField::ConfigAssetClawback,
+    TxnField::FreezeAsset,
+    TxnField::FreezeAssetAccount,
+    TxnField::FreezeAssetFrozen,
+    TxnField::Assets,
+    TxnField::NumAssets,
+    TxnField::Applications,
+    TxnField::NumApplications,
+    TxnField::GlobalNumUint,
+    TxnField::GlobalNumByteSlice,
+    TxnField::LocalNumUint,
+    TxnField::LocalNumByteSlice,
+    TxnField::ExtraProgramPages,
+    TxnField::Nonparticipation,
+    TxnField::Logs,
+    TxnField::NumLogs,
+    TxnField::CreatedAssetID,
+    TxnField::CreatedApplicationID,
+    TxnField::LastLog,
+    TxnField::StateProofPK,
+    TxnField::ApprovalProgramPages,
+    TxnField::NumApprovalProgramPages,
+    TxnField::ClearStateProgramPages,
+    TxnField::NumClearStateProgramPages,
+];
+
+impl TxnField {
+    // parses a field immediate, taking into account
+    // in which version the field was introduced
+    pub fn parse(value: u8, version: &AvmVersion) -> Result<Self, AvmError> {
+        match TXN_FIELDS.get(value as usize) {
+            Some(field) if *version >= field.version() => Ok(*field),
+            _ => Err(AvmError::UnknownField("txn", value)),
+        }
+    }
+
+    pub fn version(self) -> AvmVersion {
+        match self as u8 {
+            3 => AvmVersion::V7,
+            0..=23 => AvmVersion::V1,
+            24..=47 => AvmVersion::V2,
+            48..=55 => AvmVersion::V3,
+            56 => AvmVersion::V4,
+            57..=61 => AvmVersion::V5,
+            62..=63 => AvmVersion::V6,
+            _ => AvmVersion::V7,
+        }
+    }
+
+    pub fn is_array(self) -> bool {
+        matches!(
+            self,
+            TxnField::ApplicationArgs
+                | TxnField::Accounts
+                | TxnField::Assets
+                | TxnField::Applications
+                | TxnField::Logs
+                | TxnField::ApprovalProgramPages
+                | TxnField::ClearStateProgramPages
+        )
+    }
+
+    // the version starting with which inner transactions may set the field
+    pub fn inner_version(self) -> Option<AvmVersion> {
+        match self {
+            TxnField::Sender
+            | TxnField::Fee
+            | TxnField::Receiver
+            | TxnField::Amount
+            | TxnField::CloseRemainderTo
+            | TxnField::Type
+            | TxnField::TypeEnum
+            | TxnField::XferAsset
+            | TxnField::AssetAmount
+            | TxnField::AssetSender
+            | TxnField::AssetReceiver
+            | TxnField::AssetCloseTo
+            | TxnField::ConfigAsset
+            | TxnField::ConfigAssetTotal
+            | TxnField::ConfigAssetDecimals
+            | TxnField::ConfigAssetDefaultFrozen
+            | TxnField::ConfigAssetUnitName
+            | TxnField::ConfigAssetName
+            | TxnField::ConfigAssetURL
+            | TxnField::ConfigAssetMetadataHash
+            | TxnField::ConfigAssetManager
+            | TxnField::ConfigAssetReserve
+            | TxnField::ConfigAssetFreeze
+            | TxnField::ConfigAssetClawback
+            | TxnField::FreezeAsset
+            | TxnField::FreezeAssetAccount
+            | TxnField::FreezeAssetFrozen => Some(AvmVersion::V5),
+            TxnField::Note
+            | TxnField::VotePK
+            | TxnField::SelectionPK
+            | TxnField::VoteFirst
+            | TxnField::VoteLast
+            | TxnField::VoteKeyDilution
+            | TxnField::RekeyTo
+            | TxnField::ApplicationID
+            | TxnField::OnCompletion
+            | TxnField::ApplicationArgs
+            | TxnField::Accounts
+            | TxnField::ApprovalProgram
+            | TxnField::ClearStateProgram
+            | TxnField::Assets
+            | TxnField::Applications
+            | TxnField::GlobalNumUint
+            | TxnField::GlobalNumByteSlice
+            | TxnField::LocalNumUint
+            | TxnField::LocalNumByteSlice
+            | TxnField::ExtraProgramPages
+            | TxnField::Nonparticipation
+            | TxnField::StateProofPK => Some(AvmVersion::V6),
+            TxnField::ApprovalProgramPages | TxnField::ClearStateProgramPages => {
+                Some(AvmVersion::V7)
+            }
+            _ => None,
+        }
+    }
+
+    // effects are the results of evaluating a transaction
+    pub fn is_effect(self) -> bool {
+        matches!(
+            self,
+            TxnField::Logs
+                | TxnField::NumLogs
+                | TxnField::CreatedAssetID
+                | TxnField::CreatedApplicationID
+                | TxnField::LastLog
+        )
+    }
+}
+
+impl Transaction {
+    // reads a scalar field of the transaction at position group_index in its group
+    pub fn field(&self, field: TxnField, group_index: usize) -> Result<AvmData, AvmError> {
+        let value = match field {
+            TxnField::Sender => self.sender.to_vec().into(),
+            TxnField::Fee => self.fee.into(),
+            TxnField::FirstValid => self.first_valid.into(),
+            TxnField::FirstValidTime => return Err(AvmError::FieldUnavailable("FirstValidTime")),
+            TxnField::LastValid => self.last_valid.into(),
+            TxnField::Note => self.note.clone().into(),
+            TxnField::Lease => self.lease.to_vec().into(),
+            TxnField::Receiver => self.receiver.to_vec().into(),
+            TxnField::Amount => self.amount.into(),
+            TxnField::CloseRemainderTo => self.close_remainder_to.to_vec().into(),
+            TxnField::VotePK => self.vote_pk.to_vec().into(),
+            TxnField::SelectionPK => self.selection_pk.to_vec().into(),
+            TxnField::VoteFirst => self.vote_first.into(),
+            TxnField::VoteLast => self.vote_last.into(),
+            TxnField::VoteKeyDilution => self.vote_key_dilution.into(),
+            TxnField::Type => self.tx_type.name().to_vec().into(),
+            TxnField::TypeEnum => (self.tx_type as u64).into(),
+            TxnField::XferAsset => self.xfer_asset.into(),
+            TxnField::AssetAmount => self.asset_amount.into(),
+            TxnField::AssetSender => self.asset_sender.to_vec().into(),
+            TxnField::AssetReceiver => self.asset_receiver.to_vec().into(),
+            TxnField::AssetCloseTo => self.asset_close_to.to_vec().into(),
+            TxnField::GroupIndex => (group_index as u64).into(),
+            TxnField::TxID => self.tx_id.to_vec().into(),
+            TxnField::ApplicationID => self.application_id.into(),
+            TxnField::OnCompletion => (self.on_completion as u64).into(),
+            TxnField::NumAppArgs => (self.application_args.len() as u64).into(),
+            TxnField::NumAccounts => (self.accounts.len() as u64).into(),
+            TxnField::ApprovalProgram => self.approval_program.clone().into(),
+            TxnField::ClearStateProgram => self.clear_state_program.clone().into(),
+            TxnField::RekeyTo => self.rekey_to.to_vec().into(),
+            TxnField::ConfigAsset => self.config_asset.into(),
+            TxnField::ConfigAssetTotal => self.config_asset_total.into(),
+            TxnField::ConfigAssetDecimals => self.config_asset_decimals.into(),
+            TxnField::ConfigAssetDefaultFrozen => self.config_asset_default_frozen.into(),
+            TxnField::ConfigAssetUnitName => self.config_asset_unit_name.clone().into(),
+            TxnField::ConfigAssetName => self.config_asset_name.clone().into(),
+            TxnField::ConfigAssetURL => self.config_asset_url.clone().into(),
+            TxnField::ConfigAssetMetadataHash => self.config_asset_metadata_hash.clone().into(),
+            TxnField::ConfigAssetManager => self.config_asset_manager.to_vec().into(),
+            TxnField::ConfigAssetReserve => self.config_asset_reserve.to_vec().into(),
+            TxnField::ConfigAssetFreeze => self.config_asset_freeze.to_vec().into(),
+            TxnField::ConfigAssetClawback => self.config_asset_clawback.to_vec().into(),
+            TxnField::FreezeAsset => self.freeze_asset.into(),
+            TxnField::FreezeAssetAccount => self.freeze_asset_account.to_vec().into(),
+            TxnField::FreezeAssetFrozen => self.freeze_asset_frozen.into(),
+            TxnField::NumAssets => (self.assets.len() as u64).into(),
+            TxnField::NumApplications => (self.applications.len() as u64).into(),
+            TxnField::GlobalNumUint => self.global_num_uint.into(),
+            TxnField::GlobalNumByteSlice => self.global_num_byte_slice.into(),
+            TxnField::LocalNumUint => self.local_num_uint.into(),
+            TxnField::LocalNumByteSlice => self.local_num_byte_slice.into(),
+            TxnField::ExtraProgramPages => self.extra_program_pages.into(),
+            TxnField::Nonparticipation => self.nonparticipation.into(),
+            TxnField::NumLogs => (self.logs.len() as u64).into(),
+            TxnField::CreatedAssetID => self.created_asset_id.into(),
+            TxnField::CreatedApplicationID => self.created_application_id.into(),
+            TxnField::LastLog => self.logs.last().cloned().unwrap_or_default().into(),
+            TxnField::StateProofPK => match self.state_proof_pk.is_empty() {
+                true => vec![0; 64].into(),
+                false => self.state_proof_pk.clone().into(),
+            },
+            TxnField::NumApprovalProgramPages => {
+                (self.approval_program.len().div_ceil(PROGRAM_PAGE_SIZE) as u64).into()
+            }
+            TxnField::NumClearStateProgramPages => {
+                (self.clear_state_program.len().div_ceil(PROGRAM_PAGE_SIZE) as u64).into()
+            }
+            TxnField::ApplicationArgs
+            | TxnField::Accounts
+            | TxnField::Assets
+            | TxnField::Applications
+            | TxnField::Logs
+            | TxnField::ApprovalProgramPages
+            | TxnField::ClearStateProgramPages => return Err(AvmError::InvalidTxnFieldUse(field)),
+        };
+        Ok(value)
+    }
+
+    // the id of the called application, which for application
+    // creations is only known once the application was created
+    pub fn current_application_id(&self) -> u64 {
+        match self.application_id {
+            0 => self.created_application_id,
+            application_id => application_id,
+        }
+    }
+
+    // sets a field of an inner transaction after validating the value,
+    // where values of array fields and program pages are appended
+    pub fn set_field(&mut self, field: TxnField, value: AvmData) -> Result<(), AvmError> {
+        match field {
+            TxnField::Sender => self.sender = to_address(field, value)?,
+            TxnField::Fee => self.fee = to_uint(value)?,
+            TxnField::Note => self.note = to_bytes(field, value, MAX_NOTE_LEN)?,
+            TxnField::Receiver => self.receiver = to_address(field, value)?,
+            TxnField::Amount => self.amount = to_uint(value)?,
+            TxnField::CloseRemainderTo => self.close_remainder_to = to_address(field, value)?,
+            TxnField::VotePK => self.vote_pk = to_address(field, value)?,
+            TxnField::SelectionPK => self.selection_pk = to_address(field, value)?,
+            TxnField::VoteFirst => self.vote_first = to_uint(value)?,
+            TxnField::VoteLast => self.vote_last = to_uint(value)?,
+            TxnField::VoteKeyDilution => self.vote_key_dilution = to_uint(value)?,
+            TxnField::Type => {
+                let name = to_bytes(field, value, usize::MAX)?;
+                self.tx_type = TX_TYPES[1..]
+                    .iter()
+                    .find(|tx_type| tx_type.name() == name)
+                    .copied()
+                    .ok_or(AvmError::InvalidInnerTxnValue(field))?;
+            }
+            TxnField::TypeEnum => {
+                self.tx_type = match to_uint(value)? {
+                    0 => None,
+                    value => TX_TYPES.get(value as usize).copied(),
+                }
+                .ok_or(AvmError::InvalidInnerTxnValue(field))?;
+            }
+            TxnField::XferAsset => self.xfer_asset = to_uint(value)?,
+            TxnField::AssetAmount => self.asset_amount = to_uint(value)?,
+            TxnField::AssetSender => self.asset_sender = to_address(field, value)?,
+            TxnField::AssetReceiver => self.asset_receiver = to_address(field, value)?,
+            TxnField::AssetCloseTo => self.asset_close_to = to_address(field, value)?,
+            TxnField::ApplicationID => self.application_id = to_uint(value)?,
+            TxnField::OnCompletion => {
+                self.on_completion = ON_COMPLETIONS
+                    .get(to_uint(value)? as usize)
+                    .copied()
+                    .ok_or(AvmError::InvalidInnerTxnValue(field))?;
+            }
+            TxnField::ApplicationArgs => {
+                let arg = to_bytes(field, value, MAX_APP_ARGS_LEN)?;
+                let total_len: usize = self.application_args.iter().map(Vec::len).sum();
+                if self.application_args.len() >= MAX_APP_ARGS
+                    || total_len + arg.len() > MAX_APP_ARGS_LEN
+                {
+                    return Err(AvmError::InvalidInnerTxnValue(field));
+                }
+                self.application_args.push(arg);
+            }
+            TxnField::Accounts => {
+                let account = to_address(field, value)?;
+                self.check_references(field, self.accounts.len(), MAX_APP_ACCOUNTS)?;
+                self.accounts.push(account);
+            }
+            TxnField::Assets => {
+                let asset = to_uint(value)?;
+                self.check_references(field, self.assets.len(), MAX_APP_FOREIGN_ASSETS)?;
+                self.assets.push(asset);
+            }
+            TxnField::Applications => {
+                let application = to_uint(value)?;
+                self.check_references(field, self.applications.len(), MAX_APP_FOREIGN_APPS)?;
+                self.applications.push(application);
+            }
+            TxnField::ApprovalProgram => {
+                self.approval_program = to_bytes(field, value, MAX_APP_PROGRAM_LEN)?
+            }
+            TxnField::ClearStateProgram => {
+                self.clear_state_program = to_bytes(field, value, MAX_APP_PROGRAM_LEN)?
+            }
+            TxnField::ApprovalProgramPages => {
+                let page = to_bytes(field, value, MAX_APP_PROGRAM_LEN)?;
+                if self.approval_program.len() + page.len() > MAX_APP_PROGRAM_LEN {
+                    return Err(AvmError::InvalidInnerTxnValue(field));
+                }
+                self.approval_program.extend(page);
+            }
+            TxnField::ClearStateProgramPages => {
+                let page = to_bytes(field, value, MAX_APP_PROGRAM_LEN)?;
+                if self.clear_state_program.len() + page.len() > MAX_APP_PROGRAM_LEN {
+                    return Err(AvmError::InvalidInnerTxnValue(field));
+                }
+                self.clear_state_program.extend(page);
+            }
+            TxnField::RekeyTo => self.rekey_to = to_address(field, value)?,
+            TxnField::ConfigAsset => self.config_asset = to_uint(value)?,
+            TxnField::ConfigAssetTotal => self.config_asset_total = to_uint(value)?,
+            TxnField::ConfigAssetDecimals => {
+                self.config_asset_decimals = to_bounded_uint(field, value, MAX_ASSET_DECIMALS)?
+            }
+            TxnField::ConfigAssetDefaultFrozen => {
+                self.config_asset_default_frozen = to_bool(field, value)?
+            }
+            TxnField::ConfigAssetUnitName => {
+                self.config_asset_unit_name = to_bytes(field, value, MAX_ASSET_UNIT_NAME_LEN)?
+            }
+            TxnField::ConfigAssetName => {
+                self.config_asset_name = to_bytes(field, value, MAX_ASSET_NAME_LEN)?
+            }
+            TxnField::ConfigAssetURL => {
+                self.config_asset_url = to_bytes(field, value, MAX_ASSET_URL_LEN)?
+            }
+            TxnField::ConfigAssetMetadataHash => {
+                self.config_asset_metadata_hash = to_address(field, value)?.to_vec()
+            }
+            TxnField::ConfigAssetManager => self.config_asset_manager = to_address(field, value)?,
+            TxnField::ConfigAssetReserve => self.config_asset_reserve = to_address(field, value)?,
+            TxnField::ConfigAssetFreeze => self.config_asset_freeze = to_address(field, value)?,
+            TxnField::ConfigAssetClawback => self.config_asset_clawback = to_address(field, value)?,
+            TxnField::FreezeAsset => self.freeze_asset = to_uint(value)?,
+            TxnField::FreezeAssetAccount => self.freeze_asset_account = to_address(field, value)?,
+            TxnField::FreezeAssetFrozen => self.freeze_asset_frozen = to_bool(field, value)?,
+            TxnField::GlobalNumUint => {
+                self.global_num_uint = to_bounded_uint(field, value, MAX_GLOBAL_SCHEMA_ENTRIES)?
+            }
+            TxnField::GlobalNumByteSlice => {
+                self.global_num_byte_slice =
+                    to_bounded_uint(field, value, MAX_GLOBAL_SCHEMA_ENTRIES)?
+            }
+            TxnField::LocalNumUint => {
+                self.local_num_uint = to_bounded_uint(field, value, MAX_LOCAL_SCHEMA_ENTRIES)?
+            }
+            TxnField::LocalNumByteSlice => {
+                self.local_num_byte_slice = to_bounded_uint(field, value, MAX_LOCAL_SCHEMA_ENTRIES)?
+            }
+            TxnField::ExtraProgramPages => {
+                self.extra_program_pages = to_bounded_uint(field, value, MAX_EXTRA_PROGRAM_PAGES)?
+            }
+            TxnField::Nonparticipation => self.nonparticipation = to_bool(field, value)?,
+            TxnField::StateProofPK => {
+                let key = to_bytes(field, value, 64)?;
+                if key.len() != 64 {
+                    return Err(AvmError::InvalidInnerTxnValue(field));
+                }
+                self.state_proof_pk = key;
+            }
+            _ => return Err(AvmError::InvalidTxnFieldUse(field)),
+        }
+        Ok(())
+    }
+
+    // accounts, assets and applications share a limit on their total number
+    fn check_references(&self, field: TxnField, len: usize, max: usize) -> Result<(), AvmError> {
+        let references = self.accounts.len() + self.assets.len() + self.applications.len();
+        if len >= max || references >= MAX_APP_REFERENCES {
+            return Err(AvmError::InvalidInnerTxnValue(field));
+        }
+        Ok(())
+    }
+
+    // reads the element at position idx of an array field
+    pub fn array_field(&self, field: TxnField, idx: usize) -> Result<AvmData, AvmError> {
+        let len = match field {
+            TxnField::ApplicationArgs => self.application_args.len(),
+            // the sender is implicitly the first element of the accounts array
+            TxnField::Accounts => self.accounts.len() + 1,
+            TxnField::Assets => self.assets.len(),
+            // the called application is implicitly the first element
+            TxnField::Applications => self.applications.len() + 1,
+            TxnField::Logs => self.logs.len(),
+            TxnField::ApprovalProgramPages => {
+                self.approval_program.len().div_ceil(PROGRAM_PAGE_SIZE)
+            }
+            TxnField::ClearStateProgramPages => {
+                self.clear_state_program.len().div_ceil(PROGRAM_PAGE_SIZE)
+            }
+            _ => return Err(AvmError::InvalidTxnFieldUse(field)),
+        };
+        if idx >= len {
+            return Err(AvmError::TxnArrayIndexOutOfRange(field, idx, len));
+        }
+
+        let value = match field {
+            TxnField::ApplicationArgs => self.application_args[idx].clone().into(),
+            TxnField::Accounts if idx == 0 => self.sender.to_vec().into(),
+            TxnField::Accounts => self.accounts[idx - 1].to_vec().into(),
+            TxnField::Assets => self.assets[idx].into(),
+            TxnField::Applications if idx == 0 => self.application_id.into(),
+            TxnField::Applications => self.applications[idx - 1].into(),
+            TxnField::Logs => self.logs[idx].clone().into(),
+            TxnField::ApprovalProgramPages => program_page(&self.approval_program, idx).into(),
+            _ => program_page(&self.clear_state_program, idx).into(),
+        };
+        Ok(value)
+    }
+}
+
+fn program_page(program: &[u8], idx: usize) -> Vec<u8> {
+    let start = idx * PROGRAM_PAGE_SIZE;
+    let end = program.len().min(start + PROGRAM_PAGE_SIZE);
+    program[start..end].to_vec()
+}
+
+fn to_uint(value: AvmData) -> Result<u64, AvmError> {
+    match value {
+        AvmData::Uint64(value) => Ok(value),
+        AvmData::Bytes(_) => Err(AvmError::IncompatibleTypes(LABEL_BYTES, LABEL_UINT64)),
+    }
+}
+
+fn to_bounded_uint(field: TxnField, value: AvmData, max: u64) -> Result<u64, AvmError> {
+    match to_uint(value)? {
+        value if value <= max => Ok(value),
+        _ => Err(AvmError::InvalidInnerTxnValue(field)),
+    }
+}
+
+fn to_bool(field: TxnField, value: AvmData) -> Result<bool, AvmError> {
+    Ok(to_bounded_uint(field, value, 1)? == 1)
+}
+
+fn to_bytes(field: TxnField, value: AvmData, max_len: usize) -> Result<Vec<u8>, AvmError> {
+    match value {
+        AvmData::Bytes(bytes) if bytes.len() <= max_len => Ok(bytes),
+        AvmData::Bytes(_) => Err(AvmError::InvalidInnerTxnValue(field)),
+        AvmData::Uint64(_) => Err(AvmError::IncompatibleTypes(LABEL_UINT64, LABEL_BYTES)),
+    }
+}
+
+fn to_address(field: TxnField, value: AvmData) -> Result<Address, AvmError> {
+    Address::try_from(to_bytes(field, value, usize::MAX)?)
+        .map_err(|_| AvmError::InvalidInnerTxnValue(field))
+}
@@ -0,0 +1,98 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+
+use crate::AvmError;
+
+pub const VRF_PUBLIC_KEY_LEN: usize = 32;
+pub const VRF_PROOF_LEN: usize = 80;
+
+// the suite string of ECVRF-ED25519-SHA512-Elligator2
+const SUITE: u8 = 0x04;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum VrfStandard {
+    VrfAlgorand = 0,
+}
+
+// all standards in the order of their numbering
+const VRF_STANDARDS: [VrfStandard; 1] = [VrfStandard::VrfAlgorand];
+
+impl VrfStandard {
+    pub fn parse(value: u8) -> Result<Self, AvmError> {
+        VRF_STANDARDS
+            .get(value as usize)
+            .copied()
+            .ok_or(AvmError::UnknownField("vrf_verify", value))
+    }
+}
+
+// verifies an ECVRF-ED25519-SHA512-Elligator2 proof as specified in
+// draft-irtf-cfrg-vrf-03 and implemented by Algorand's libsodium fork.
+// Returns the VRF output if the proof is valid.
+pub fn verify(
+    public_key: &[u8; VRF_PUBLIC_KEY_LEN],
+    proof: &[u8; VRF_PROOF_LEN],
+    message: &[u8],
+) -> Option<[u8; 64]> {
+    let y_point = decode_public_key(public_key)?;
+    let gamma = CompressedEdwardsY::from_slice(&proof[..32])
+        .ok()?
+        .decompress()?;
+    let mut c_bytes = [0; 32];
+    c_bytes[..16].copy_from_slice(&proof[32..48]);
+    let c = Scalar::from_bytes_mod_order(c_bytes);
+    let s: Option<Scalar> = Scalar::from_canonical_bytes(proof[48..].try_into().ok()?).into();
+    let s = s?;
+
+    let h_point = hash_to_curve(public_key, message);
+    // U = s*B - c*Y and V = s*H - c*Gamma
+    let u_point = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-c, &y_point, &s);
+    let v_point = h_point * s - gamma * c;
+    if hash_points(&[h_point, gamma, u_point, v_point]) != proof[32..48] {
+        return None;
+    }
+
+    let mut hash = Sha512::new();
+    hash.update([SUITE, 0x03]);
+    hash.update(gamma.mul_by_cofactor().compress().as_bytes());
+    Some(hash.finalize().into())
+}
+
+// public keys must be canonical encodings of points of large order
+fn decode_public_key(public_key: &[u8; VRF_PUBLIC_KEY_LEN]) -> Option<EdwardsPoint> {
+    let point = CompressedEdwardsY(*public_key).decompress()?;
+    if point.is_small_order() || point.compress().as_bytes() != public_key {
+        return None;
+    }
+    Some(point)
+}
+
+// maps the hash of the public key and the message to the curve with
+// Elligator2. The map of the curve library takes the sign of the point
+// from the hash, whereas the VRF always clears it, i.e., the point
+// is negated whenever the sign bit of the hash is set. The map is
+// deprecated as a general hash to the curve, but it is exactly the
+// Elligator2 map the VRF standard is built upon.
+fn hash_to_curve(public_key: &[u8; VRF_PUBLIC_KEY_LEN], message: &[u8]) -> EdwardsPoint {
+    let input = [&[SUITE, 0x01][..], public_key, message].concat();
+    #[allow(deprecated)]
+    let point = EdwardsPoint::nonspec_map_to_curve::<Sha512>(&input);
+    if Sha512::digest(&input)[31] & 0x80 != 0 {
+        -point
+    } else {
+        point
+    }
+}
+
+// the challenge is the first half of the hash of the points
+fn hash_points(points: &[EdwardsPoint]) -> [u8; 16] {
+    let mut hash = Sha512::new();
+    hash.update([SUITE, 0x02]);
+    for point in points {
+        hash.update(point.compress().as_bytes());
+    }
+    let mut challenge = [0; 16];
+    challenge.copy_from_slice(&hash.finalize()[..16]);
+    challenge
+}
@@ -1,5 +1,4 @@
 use crate::AvmError;
-use std::cmp::min;
 
 pub fn u128_to_u64_tuple(value: u128) -> (u64, u64) {
     const MASK: u128 = 0x0000_0000_0000_0000_ffff_ffff_ffff_ffff;
@@ -12,6 +11,73 @@ pub fn u64_tuple_to_u128(hi: u64, lo: u64) -> u128 {
     ((hi as u128) << 64) | (lo as u128)
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Base64Alphabet {
+    /// URL and filename safe alphabet (RFC 4648, section 5)
+    Url,
+    /// standard alphabet (RFC 4648, section 4)
+    Std,
+}
+
+impl Base64Alphabet {
+    fn decode_char(self, c: u8) -> Option<u8> {
+        match c {
+            b'A'..=b'Z' => Some(c - b'A'),
+            b'a'..=b'z' => Some(c - b'a' + 26),
+            b'0'..=b'9' => Some(c - b'0' + 52),
+            b'-' if self == Base64Alphabet::Url => Some(62),
+            b'_' if self == Base64Alphabet::Url => Some(63),
+            b'+' if self == Base64Alphabet::Std => Some(62),
+            b'/' if self == Base64Alphabet::Std => Some(63),
+            _ => None,
+        }
+    }
+}
+
+// decodes base64 strictly: the input must be padded with exactly
+// as many '=' as required and unused bits of the last character
+// must be zero. line breaks (\r and \n) are ignored.
+pub fn base64_decode(encoded: &[u8], alphabet: Base64Alphabet) -> Result<Vec<u8>, AvmError> {
+    let encoded: Vec<u8> = encoded
+        .iter()
+        .copied()
+        .filter(|&c| c != b'\r' && c != b'\n')
+        .collect();
+    if !encoded.len().is_multiple_of(4) {
+        return Err(AvmError::InvalidBase64);
+    }
+
+    let nquads = encoded.len() / 4;
+    let mut decoded = Vec::with_capacity(nquads * 3);
+    for (i, quad) in encoded.chunks(4).enumerate() {
+        // padding is only allowed at the end of the last quad
+        let npadding = match quad {
+            [_, _, b'=', b'='] => 2,
+            [_, _, _, b'='] => 1,
+            _ => 0,
+        };
+        if npadding > 0 && i + 1 != nquads {
+            return Err(AvmError::InvalidBase64);
+        }
+
+        let mut bits: u32 = 0;
+        for &c in &quad[..4 - npadding] {
+            let value = alphabet.decode_char(c).ok_or(AvmError::InvalidBase64)?;
+            bits = (bits << 6) | value as u32;
+        }
+        bits <<= 6 * npadding;
+
+        let bytes = bits.to_be_bytes();
+        let nbytes = 3 - npadding;
+        // the padded bits have to be zero, otherwise the encoding is not canonical
+        if bytes[1 + nbytes..].iter().any(|&b| b != 0) {
+            return Err(AvmError::InvalidBase64);
+        }
+        decoded.extend_from_slice(&bytes[1..1 + nbytes]);
+    }
+    Ok(decoded)
+}
+
 #[derive(Debug, PartialEq)]
 pub struct VarUint64 {
     pub value: u64,
@@ -23,13 +89,11 @@ impl TryFrom<&[u8]> for VarUint64 {
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
         let mut result = 0;
-        let mut consumed_bytes = 0;
         // a varint-encoded u64 is at most 10 bytes long
-        for i in 0..min(data.len(), 10) {
-            consumed_bytes += 1;
+        for (i, &byte) in data.iter().take(10).enumerate() {
             // the most-significant bit (MSB) indicates if we reached
             // the end of the varint (bit 0) or not (bit 1)
-            let bits = (data[i] & 0x7F) as u64;
+            let bits = (byte & 0x7F) as u64;
             // in the 10th and last byte the largest possible value
             // is 0x01. any larger value means there's an overflow
             if i == 9 && bits > 0x01 {
@@ -38,10 +102,10 @@ impl TryFrom<&[u8]> for VarUint64 {
             // varints are encoded in little-endian order
             result |= bits << (7 * i);
             // if MSB is 0 we reached the end of the varint
-            if data[i] & 0x80 == 0 {
+            if byte & 0x80 == 0 {
                 return Ok(VarUint64 {
                     value: result,
-                    nbytes: consumed_bytes,
+                    nbytes: i + 1,
                 });
             }
         }
@@ -75,6 +139,7 @@ impl<'a> TryFrom<&'a [u8]> for VarBytes<'a> {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
 
@@ -178,4 +243,51 @@ mod tests {
             VarBytes::try_from(&varbytes_invalid_length[..]).unwrap_err()
         );
     }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(Ok(vec![]), base64_decode(b"", Base64Alphabet::Std));
+        assert_eq!(
+            Ok(b"hello".to_vec()),
+            base64_decode(b"aGVsbG8=", Base64Alphabet::Std)
+        );
+        assert_eq!(
+            Ok(b"hell".to_vec()),
+            base64_decode(b"aGVs\r\nbA==", Base64Alphabet::Url)
+        );
+        assert_eq!(
+            Ok(vec![0xfb, 0xff]),
+            base64_decode(b"-_8=", Base64Alphabet::Url)
+        );
+        assert_eq!(
+            Ok(vec![0xfb, 0xff]),
+            base64_decode(b"+/8=", Base64Alphabet::Std)
+        );
+    }
+
+    #[test]
+    fn test_base64_decode_strict() {
+        let invalid: [(&[u8], Base64Alphabet); 8] = [
+            // missing padding
+            (b"aGVsbG8", Base64Alphabet::Std),
+            // too much padding
+            (b"aGVsbA===", Base64Alphabet::Std),
+            // padding in the middle
+            (b"aA==aGVs", Base64Alphabet::Std),
+            // non-zero padding bits
+            (b"aGVsbG9=", Base64Alphabet::Std),
+            (b"aGVsbB==", Base64Alphabet::Std),
+            // characters of the other alphabet
+            (b"-_8=", Base64Alphabet::Std),
+            (b"+/8=", Base64Alphabet::Url),
+            // characters outside the alphabet
+            (b"aGV sbA=", Base64Alphabet::Std),
+        ];
+        for (encoded, alphabet) in invalid {
+            assert_eq!(
+                Err(AvmError::InvalidBase64),
+                base64_decode(encoded, alphabet)
+            );
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use avm::{EcGroup, RunMode, TxnField};
+
 pub mod avm;
 pub mod encoding;
 
@@ -15,6 +17,112 @@ pub enum AvmError {
     InvalidVarUint64,
     #[error("Invalid varbytes")]
     InvalidVarBytes,
+    #[error("Invalid base64 encoding")]
+    InvalidBase64,
+    #[error("Invalid JSON text: {0}")]
+    InvalidJson(&'static str),
+    #[error("Key {0} not found in JSON text")]
+    JsonKeyNotFound(String),
+    #[error("Value of key {0} is not a valid JSON {1}")]
+    InvalidJsonValue(String, &'static str),
+    #[error("Unknown {0} field: {1}")]
+    UnknownField(&'static str, u8),
+    #[error("Field {0} is not available")]
+    FieldUnavailable(&'static str),
+    #[error("Invalid use of transaction field {0:?}")]
+    InvalidTxnFieldUse(TxnField),
+    #[error("Index {1} of transaction field {0:?} out of range {2}")]
+    TxnArrayIndexOutOfRange(TxnField, usize, usize),
+    #[error("Invalid value for inner transaction field {0:?}")]
+    InvalidInnerTxnValue(TxnField),
+    #[error("Transaction effects can only be read from past transactions")]
+    TxnEffectsNotAvailable,
+    #[error("Transaction group of size {0} exceeds maximum size 16")]
+    TxnGroupTooLarge(usize),
+    #[error("Group index {0} out of range {1}")]
+    GroupIndexOutOfRange(usize, usize),
+    #[error("Got {0} programs for a transaction group of size {1}")]
+    ProgramCountMismatch(usize, usize),
+    #[error("Scratch space of transaction {0} can only be read from later app calls")]
+    ScratchNotAvailable(usize),
+    #[error("Transaction {0} did not create an asset or application")]
+    NothingCreated(usize),
+    #[error("Program of transaction {0} rejected the transaction")]
+    ProgramRejected(usize),
+    #[error("Application {0} is not available")]
+    AppNotAvailable(u64),
+    #[error("Asset {0} is not available")]
+    AssetNotAvailable(u64),
+    #[error("Account is not available")]
+    AccountNotAvailable,
+    #[error("Account is not opted in to application {0}")]
+    NotOptedIn(u64),
+    #[error("State key of length {0} exceeds maximum length 64")]
+    StateKeyTooLong(usize),
+    #[error("State key and value of total length {0} exceed maximum length 128")]
+    StateValueTooLong(usize),
+    #[error("State exceeds schema of {1} {0} values")]
+    StateSchemaExceeded(&'static str, u64),
+    #[error("Box name of length {0} must be between 1 and 64 bytes long")]
+    InvalidBoxName(usize),
+    #[error("Box size {0} exceeds maximum size 32768")]
+    BoxTooLarge(u64),
+    #[error("Box {0:?} is not referenced by the transaction group")]
+    BoxNotAvailable(Vec<u8>),
+    #[error("Box {0:?} does not exist")]
+    BoxNotFound(Vec<u8>),
+    #[error("Box of size {0} can not be changed to size {1}")]
+    BoxSizeMismatch(u64, u64),
+    #[error("Boxes of total size {0} exceed the quota of {1} bytes")]
+    BoxQuotaExceeded(u64, u64),
+    #[error("VRF {0} of length {1} must be {2} bytes long")]
+    InvalidVrfInput(&'static str, usize, usize),
+    #[error("Invalid point encoding for group {0:?}")]
+    InvalidEcPoint(EcGroup),
+    #[error("Invalid field element encoding for group {0:?}")]
+    InvalidEcFieldElement(EcGroup),
+    #[error("Point is not in the prime order subgroup of {0:?}")]
+    EcPointNotInSubgroup(EcGroup),
+    #[error("Scalar of length {0} exceeds maximum length 32")]
+    EcScalarTooLong(usize),
+    #[error("Scalars of total length {0} must be {1} bytes long")]
+    InvalidEcScalars(usize, usize),
+    #[error("Pairing check of {0} G1 points with {1} G2 points")]
+    EcPointCountMismatch(usize, usize),
+    #[error("Round {0} is not available (must be between {1} and {2})")]
+    RoundNotAvailable(u64, u64, u64),
+    #[error("Block of round {0} not found")]
+    BlockNotFound(u64),
+    #[error("Inner transaction must be started with itxn_begin")]
+    InnerTxnNotStarted,
+    #[error("Pending inner transactions must be submitted first")]
+    InnerTxnNotSubmitted,
+    #[error("No inner transactions have been submitted")]
+    NoInnerTxns,
+    #[error("Too many inner transactions (at most {0} allowed)")]
+    TooManyInnerTxns(usize),
+    #[error("Inner transaction fees {0} below required fees {1}")]
+    InnerFeesTooLow(u64, u64),
+    #[error("Balance {0} is insufficient to pay {1}")]
+    InsufficientBalance(u64, u64),
+    #[error("Balance {0} below minimum balance {1}")]
+    BelowMinBalance(u64, u64),
+    #[error("Asset {0} does not exist")]
+    AssetNotFound(u64),
+    #[error("Application {0} does not exist")]
+    AppNotFound(u64),
+    #[error("Account is not opted in to asset {0}")]
+    AssetNotOptedIn(u64),
+    #[error("Asset {0} is frozen")]
+    AssetFrozen(u64),
+    #[error("Sender is not authorized to {0}")]
+    Unauthorized(&'static str),
+    #[error("Application call depth exceeds {0}")]
+    AppCallDepthExceeded(usize),
+    #[error("Application {0} can not be called re-entrantly")]
+    AppReentrance(u64),
+    #[error("Application {0} rejected the inner transaction")]
+    InnerAppRejected(u64),
     #[error("Program counter out of bounds")]
     PcOutOfBounds,
     #[error("Integer overflow")]
@@ -23,8 +131,16 @@ pub enum AvmError {
     IntegerUnderflow,
     #[error("Division by zero")]
     DivisionByZero,
+    #[error("Shift amount {0} out of range (must be < 64)")]
+    ShiftOutOfRange(u64),
+    #[error("Zero raised to the power of zero is undefined")]
+    ZeroToThePowerOfZero,
     #[error("Stack underflow")]
     StackUnderflow,
+    #[error("Stack overflow (exceeds depth {0})")]
+    StackOverflow(usize),
+    #[error("Program cost {0} exceeds budget {1}")]
+    CostBudgetExceeded(u64, u64),
     #[error("Invalid stack access")]
     InvalidStackAccess,
     #[error("Incompatible types (got {0}, expected {0})")]
@@ -33,10 +149,14 @@ pub enum AvmError {
     BytesTooLong,
     #[error("err opcode executed")]
     ErrOpCode,
+    #[error("Opcode is only allowed in {0:?} mode")]
+    InvalidRunMode(RunMode),
     #[error("Integer constant {0} out of range {1}")]
     IntcOutOfRange(usize, usize),
     #[error("Byte constant {0} out of range {1}")]
     BytecOutOfRange(usize, usize),
+    #[error("LogicSig argument {0} out of range {1}")]
+    ArgOutOfRange(usize, usize),
     #[error("Byte slice (length {0}) too long for btoi conversion")]
     BtoiTooLong(usize),
     #[error("Scratch position {0} out of bounds")]
@@ -45,4 +165,18 @@ pub enum AvmError {
     AssertionFailed(usize),
     #[error("Impossible to access substring from positions {0} to {1} (length {2})")]
     InvalidSubstringAccess(usize, usize, usize),
+    #[error("Impossible to replace {1} bytes at position {0} (length {2})")]
+    InvalidReplacement(usize, usize, usize),
+    #[error("Too many log calls (at most {0} allowed)")]
+    TooManyLogCalls(usize),
+    #[error("Logs exceed total size of {0} bytes")]
+    LogsTooLarge(usize),
+    #[error("Bit index {0} out of range (length {1} bits)")]
+    BitIndexOutOfRange(u64, u64),
+    #[error("Bit value {0} is neither 0 nor 1")]
+    InvalidBitValue(u64),
+    #[error("Byte index {0} out of range (length {1})")]
+    ByteIndexOutOfRange(u64, usize),
+    #[error("Byte value {0} exceeds 255")]
+    InvalidByteValue(u64),
 }